@@ -2,7 +2,7 @@ use odbc_common::error::{OdbcStdError, OdbcStdResult};
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DmDateType {
     /// `NUMERIC 数据类型用于存储零、正负定点数。其中:精度是一个无符号整数，
     /// 定义 了总的数字数，精度范围是 1至38，标度定义了小数点右边的数字位数，定义时如省略 精度，则默认是 16。
@@ -80,23 +80,64 @@ pub enum DmDateType {
 
     /// 布尔数据类型:TRUE 和 FALSE。DMSQL 程序的布尔类型和 INT 类型可以相互转化。 如果变量或方法返回的类型是布尔类型，则返回值为 0 或 1。TRUE 和非 0 值的返回值为 1，FALSE 和 0 值返回为 0。
     BOOL,
-    // TODO 时间间隔数据类型
+
+    /// 年间隔类型，描述一个若干年的间隔，引导精度规定了年的取值范围，取值范围为 1~9，如果未定义，缺省精度为 2。
+    /// INTERVAL YEAR[(引导精度)]
+    INTERVAL_YEAR,
+    /// 年-月间隔类型，描述一个若干年若干月的间隔，月的取值范围在 0 到 11 之间。
+    /// INTERVAL YEAR[(引导精度)] TO MONTH
+    INTERVAL_YEAR_TO_MONTH,
+    /// 月间隔类型，描述一个若干月的间隔。
+    /// INTERVAL MONTH[(引导精度)]
+    INTERVAL_MONTH,
+    /// 日间隔类型，描述一个若干日的间隔。
+    /// INTERVAL DAY[(引导精度)]
+    INTERVAL_DAY,
+    /// 日-时间隔类型，描述一个若干日若干小时的间隔，小时的取值范围在 0 到 23 之间。
+    /// INTERVAL DAY[(引导精度)] TO HOUR
+    INTERVAL_DAY_TO_HOUR,
+    /// 日-时-分间隔类型，描述一个若干日若干小时若干分钟的间隔。
+    /// INTERVAL DAY[(引导精度)] TO MINUTE
+    INTERVAL_DAY_TO_MINUTE,
+    /// 日-时-分-秒间隔类型，描述一个若干日若干小时若干分钟若干秒的间隔，小数秒精度的取值范围为 0~6，如果未定义，缺省精度为 6。
+    /// INTERVAL DAY[(引导精度)] TO SECOND[(小数秒精度)]
+    INTERVAL_DAY_TO_SECOND,
+    /// 时间隔类型，描述一个若干小时的间隔。
+    /// INTERVAL HOUR[(引导精度)]
+    INTERVAL_HOUR,
+    /// 时-分间隔类型，描述一个若干小时若干分钟的间隔。
+    /// INTERVAL HOUR[(引导精度)] TO MINUTE
+    INTERVAL_HOUR_TO_MINUTE,
+    /// 时-分-秒间隔类型，描述一个若干小时若干分钟若干秒的间隔。
+    /// INTERVAL HOUR[(引导精度)] TO SECOND[(小数秒精度)]
+    INTERVAL_HOUR_TO_SECOND,
+    /// 分间隔类型，描述一个若干分钟的间隔。
+    /// INTERVAL MINUTE[(引导精度)]
+    INTERVAL_MINUTE,
+    /// 分-秒间隔类型，描述一个若干分钟若干秒的间隔。
+    /// INTERVAL MINUTE[(引导精度)] TO SECOND[(小数秒精度)]
+    INTERVAL_MINUTE_TO_SECOND,
+    /// 秒间隔类型，描述一个若干秒的间隔。
+    /// INTERVAL SECOND[(引导精度 [, 小数秒精度])]
+    INTERVAL_SECOND,
+
+    /// ROWID 类型记录一行数据的物理地址，以 18 位字符串的形式显示，例如 'AAAAAAAAAAAAAAAAAB'。
+    ROWID,
+    /// 用户自定义的类类型、对象类型，系统表中以 CLASS 加类的 ID 表示，例如 CLASS234881038。
+    CLASS(Option<u32>),
 
     //未知类型
+    #[default]
     Unknown,
 }
 
-impl Default for DmDateType {
-    fn default() -> Self {
-        Self::Unknown
-    }
-}
-
 impl FromStr for DmDateType {
     type Err = OdbcStdError;
 
     fn from_str(s: &str) -> OdbcStdResult<Self, Self::Err> {
-        let data_type = match &*s.to_uppercase() {
+        // the names are single spaced, e.g. `INTERVAL DAY TO SECOND`
+        let name = s.split_whitespace().collect::<Vec<_>>().join(" ");
+        let data_type = match &*name.to_uppercase() {
             "NUMERIC" => Self::NUMERIC,
             "NUMBER" => Self::NUMBER,
             "DECIMAL" | "DEC" => Self::DECIMAL,
@@ -112,20 +153,46 @@ impl FromStr for DmDateType {
             "FLOAT" => Self::FLOAT,
             "DOUBLE" => Self::DOUBLE,
             "DOUBLE PRECISION" => Self::DOUBLE_PRECISION,
-            "CHAR" => Self::CHAR,
-            "VARCHAR" | "CHARACTER VARYING" => Self::VARCHAR,
-            "TEXT" => Self::TEXT,
-            "IMAGE" => Self::IMAGE,
+            "CHAR" | "CHARACTER" => Self::CHAR,
+            "VARCHAR" | "VARCHAR2" | "CHARACTER VARYING" => Self::VARCHAR,
+            "TEXT" | "LONGVARCHAR" => Self::TEXT,
+            "IMAGE" | "LONGVARBINARY" => Self::IMAGE,
             "BLOB" => Self::BLOB,
             "CLOB" => Self::CLOB,
             "BFILE" => Self::BFILE,
             "DATE" => Self::DATE,
             "TIME" => Self::TIME,
-            "TIMESTAMP" => Self::TIMESTAMP,
+            "TIMESTAMP" | "DATETIME" => Self::TIMESTAMP,
             "TIME WITH TIME ZONE" => Self::TIME_WITH_TIME_ZONE,
-            "DATETIME WITH TIME ZONE" => Self::TIMESTAMP_WITH_TIME_ZONE,
+            "DATETIME WITH TIME ZONE" | "TIMESTAMP WITH TIME ZONE" => {
+                Self::TIMESTAMP_WITH_TIME_ZONE
+            }
             "TIMESTAMP WITH LOCAL TIME ZONE" => Self::TIMESTAMP_WITH_LOCAL_TIME_ZONE,
-            _ => return Err(OdbcStdError::TypeConversionError(s.to_string())),
+            "BOOL" | "BOOLEAN" => Self::BOOL,
+            "INTERVAL YEAR" => Self::INTERVAL_YEAR,
+            "INTERVAL YEAR TO MONTH" => Self::INTERVAL_YEAR_TO_MONTH,
+            "INTERVAL MONTH" => Self::INTERVAL_MONTH,
+            "INTERVAL DAY" => Self::INTERVAL_DAY,
+            "INTERVAL DAY TO HOUR" => Self::INTERVAL_DAY_TO_HOUR,
+            "INTERVAL DAY TO MINUTE" => Self::INTERVAL_DAY_TO_MINUTE,
+            "INTERVAL DAY TO SECOND" => Self::INTERVAL_DAY_TO_SECOND,
+            "INTERVAL HOUR" => Self::INTERVAL_HOUR,
+            "INTERVAL HOUR TO MINUTE" => Self::INTERVAL_HOUR_TO_MINUTE,
+            "INTERVAL HOUR TO SECOND" => Self::INTERVAL_HOUR_TO_SECOND,
+            "INTERVAL MINUTE" => Self::INTERVAL_MINUTE,
+            "INTERVAL MINUTE TO SECOND" => Self::INTERVAL_MINUTE_TO_SECOND,
+            "INTERVAL SECOND" => Self::INTERVAL_SECOND,
+            "ROWID" => Self::ROWID,
+            "CLASS" => Self::CLASS(None),
+            // a class type is named by its id, e.g. `CLASS234881038`
+            v => match v
+                .strip_prefix("CLASS")
+                .filter(|id| id.bytes().all(|b| b.is_ascii_digit()))
+                .map(str::parse)
+            {
+                Some(Ok(id)) => Self::CLASS(Some(id)),
+                _ => return Err(OdbcStdError::TypeConversionError(s.to_string())),
+            },
         };
         Ok(data_type)
     }
@@ -171,7 +238,147 @@ impl TryToString for DmDateType {
             DmDateType::TIMESTAMP_WITH_LOCAL_TIME_ZONE => {
                 Ok("TIMESTAMP WITH LOCAL TIME ZONE".to_string())
             }
-            _ => Err(OdbcStdError::TypeConversionError(format!("{self:?}"))),
+            DmDateType::BOOL => Ok("BOOL".to_string()),
+            DmDateType::INTERVAL_YEAR => Ok("INTERVAL YEAR".to_string()),
+            DmDateType::INTERVAL_YEAR_TO_MONTH => Ok("INTERVAL YEAR TO MONTH".to_string()),
+            DmDateType::INTERVAL_MONTH => Ok("INTERVAL MONTH".to_string()),
+            DmDateType::INTERVAL_DAY => Ok("INTERVAL DAY".to_string()),
+            DmDateType::INTERVAL_DAY_TO_HOUR => Ok("INTERVAL DAY TO HOUR".to_string()),
+            DmDateType::INTERVAL_DAY_TO_MINUTE => Ok("INTERVAL DAY TO MINUTE".to_string()),
+            DmDateType::INTERVAL_DAY_TO_SECOND => Ok("INTERVAL DAY TO SECOND".to_string()),
+            DmDateType::INTERVAL_HOUR => Ok("INTERVAL HOUR".to_string()),
+            DmDateType::INTERVAL_HOUR_TO_MINUTE => Ok("INTERVAL HOUR TO MINUTE".to_string()),
+            DmDateType::INTERVAL_HOUR_TO_SECOND => Ok("INTERVAL HOUR TO SECOND".to_string()),
+            DmDateType::INTERVAL_MINUTE => Ok("INTERVAL MINUTE".to_string()),
+            DmDateType::INTERVAL_MINUTE_TO_SECOND => Ok("INTERVAL MINUTE TO SECOND".to_string()),
+            DmDateType::INTERVAL_SECOND => Ok("INTERVAL SECOND".to_string()),
+            DmDateType::ROWID => Ok("ROWID".to_string()),
+            DmDateType::CLASS(Some(id)) => Ok(format!("CLASS{id}")),
+            DmDateType::CLASS(None) => Ok("CLASS".to_string()),
+            DmDateType::Unknown => Err(OdbcStdError::TypeConversionError(format!("{self:?}"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // every Dameng 8 type name with its aliases
+    const NAMES: &[(&str, DmDateType)] = &[
+        ("NUMERIC", DmDateType::NUMERIC),
+        ("NUMBER", DmDateType::NUMBER),
+        ("DECIMAL", DmDateType::DECIMAL),
+        ("DEC", DmDateType::DECIMAL),
+        ("BIT", DmDateType::BIT),
+        ("INTEGER", DmDateType::INTEGER),
+        ("INT", DmDateType::INTEGER),
+        ("PLS_INTEGER", DmDateType::INTEGER),
+        ("BIGINT", DmDateType::BIGINT),
+        ("TINYINT", DmDateType::TINYINT),
+        ("BYTE", DmDateType::BYTE),
+        ("SMALLINT", DmDateType::SMALLINT),
+        ("BINARY", DmDateType::BINARY),
+        ("VARBINARY", DmDateType::VARBINARY),
+        ("REAL", DmDateType::REAL),
+        ("FLOAT", DmDateType::FLOAT),
+        ("DOUBLE", DmDateType::DOUBLE),
+        ("DOUBLE PRECISION", DmDateType::DOUBLE_PRECISION),
+        ("CHAR", DmDateType::CHAR),
+        ("CHARACTER", DmDateType::CHAR),
+        ("VARCHAR", DmDateType::VARCHAR),
+        ("VARCHAR2", DmDateType::VARCHAR),
+        ("CHARACTER VARYING", DmDateType::VARCHAR),
+        ("TEXT", DmDateType::TEXT),
+        ("LONGVARCHAR", DmDateType::TEXT),
+        ("IMAGE", DmDateType::IMAGE),
+        ("LONGVARBINARY", DmDateType::IMAGE),
+        ("BLOB", DmDateType::BLOB),
+        ("CLOB", DmDateType::CLOB),
+        ("BFILE", DmDateType::BFILE),
+        ("DATE", DmDateType::DATE),
+        ("TIME", DmDateType::TIME),
+        ("TIMESTAMP", DmDateType::TIMESTAMP),
+        ("DATETIME", DmDateType::TIMESTAMP),
+        ("TIME WITH TIME ZONE", DmDateType::TIME_WITH_TIME_ZONE),
+        (
+            "DATETIME WITH TIME ZONE",
+            DmDateType::TIMESTAMP_WITH_TIME_ZONE,
+        ),
+        (
+            "TIMESTAMP WITH TIME ZONE",
+            DmDateType::TIMESTAMP_WITH_TIME_ZONE,
+        ),
+        (
+            "TIMESTAMP WITH LOCAL TIME ZONE",
+            DmDateType::TIMESTAMP_WITH_LOCAL_TIME_ZONE,
+        ),
+        ("BOOL", DmDateType::BOOL),
+        ("BOOLEAN", DmDateType::BOOL),
+        ("INTERVAL YEAR", DmDateType::INTERVAL_YEAR),
+        ("INTERVAL YEAR TO MONTH", DmDateType::INTERVAL_YEAR_TO_MONTH),
+        ("INTERVAL MONTH", DmDateType::INTERVAL_MONTH),
+        ("INTERVAL DAY", DmDateType::INTERVAL_DAY),
+        ("INTERVAL DAY TO HOUR", DmDateType::INTERVAL_DAY_TO_HOUR),
+        ("INTERVAL DAY TO MINUTE", DmDateType::INTERVAL_DAY_TO_MINUTE),
+        ("INTERVAL DAY TO SECOND", DmDateType::INTERVAL_DAY_TO_SECOND),
+        ("INTERVAL HOUR", DmDateType::INTERVAL_HOUR),
+        (
+            "INTERVAL HOUR TO MINUTE",
+            DmDateType::INTERVAL_HOUR_TO_MINUTE,
+        ),
+        (
+            "INTERVAL HOUR TO SECOND",
+            DmDateType::INTERVAL_HOUR_TO_SECOND,
+        ),
+        ("INTERVAL MINUTE", DmDateType::INTERVAL_MINUTE),
+        (
+            "INTERVAL MINUTE TO SECOND",
+            DmDateType::INTERVAL_MINUTE_TO_SECOND,
+        ),
+        ("INTERVAL SECOND", DmDateType::INTERVAL_SECOND),
+        ("ROWID", DmDateType::ROWID),
+        ("CLASS", DmDateType::CLASS(None)),
+        ("CLASS234881038", DmDateType::CLASS(Some(234881038))),
+    ];
+
+    #[test]
+    fn test_data_type_names() {
+        for (name, expected) in NAMES {
+            let data_type: DmDateType = name.parse().unwrap();
+            assert_eq!(&data_type, expected, "{name}");
+            assert_eq!(
+                &name.to_lowercase().parse::<DmDateType>().unwrap(),
+                expected
+            );
+
+            // the canonical name parses back to the same type
+            let canonical = data_type.try_to_string().unwrap();
+            assert_eq!(
+                &canonical.parse::<DmDateType>().unwrap(),
+                expected,
+                "{name}"
+            );
+        }
+        assert_eq!(
+            "interval  day\tto second".parse::<DmDateType>().unwrap(),
+            DmDateType::INTERVAL_DAY_TO_SECOND
+        );
+        // the class id is kept
+        assert_eq!(
+            DmDateType::CLASS(Some(234881038)).try_to_string().unwrap(),
+            "CLASS234881038"
+        );
+        for invalid in [
+            "",
+            "INTERVAL",
+            "CLASSX",
+            "CLASS+1",
+            "CLASS99999999999",
+            "VARCHAR3",
+        ] {
+            assert!(invalid.parse::<DmDateType>().is_err(), "{invalid}");
         }
+        assert!(DmDateType::Unknown.try_to_string().is_err());
     }
 }
@@ -96,7 +96,6 @@ impl DmAdapter for CursorImpl<StatementImpl<'_>> {
                 let num_cols = batch.num_cols();
                 let row_data: Vec<String> = (0..num_cols)
                     .map(|col_index| (col_index, batch.at(col_index, row_index).unwrap_or(&[])))
-                    .into_iter()
                     .map(|(col_index, x)| (col_index, String::from_utf8_lossy(x)))
                     .map(|(col_index, x)| case_sensitive_fn(col_index, x))
                     .collect();
@@ -167,7 +166,7 @@ mod tests {
     fn test_print_all_tables() {
         let connection = get_dameng_conn();
         let cursor = connection
-            .conn
+            .connection()
             .execute(r#"SELECT * from SYSCOLUMNS limit 10;"#, ())
             .unwrap()
             .unwrap();
@@ -175,6 +174,40 @@ mod tests {
     }
 
     #[test]
+    fn test_dameng_statement_cache() {
+        let conn = ENV
+            .connect_with_connection_string(DAMENG_CONNECTION)
+            .unwrap();
+        let options = Options {
+            statement_cache_size: 2,
+            ..Options::new(SupportDatabase::Dameng)
+        };
+        let connection = OdbcDbConnection::new(conn, options).unwrap();
+        let before = connection.statement_cache_stats();
+
+        for _ in 0..3 {
+            connection.execute("SELECT 1 FROM DUAL").unwrap();
+        }
+        connection.execute("SELECT 2 FROM DUAL").unwrap();
+        let stats = connection.statement_cache_stats();
+        assert_eq!(stats.misses - before.misses, 2);
+        assert_eq!(stats.hits - before.hits, 2);
+        assert_eq!(stats.len, 2);
+
+        // a failed statement isn't put back
+        assert!(connection
+            .execute("SELECT * FROM NOT_EXISTS_TABLE")
+            .is_err());
+        connection.clear_statement_cache();
+        assert_eq!(connection.statement_cache_stats().len, 0);
+        connection.execute("SELECT 1 FROM DUAL").unwrap();
+
+        // the cached statement is dropped before its connection
+        drop(connection);
+    }
+
+    #[test]
+    #[allow(clippy::manual_inspect)]
     fn test_dameng_table_desc() {
         let connection = get_dameng_conn();
 
@@ -236,7 +269,7 @@ CREATE TABLE SYSDBA.T4 (
         let exec_result: ExecResult = connection.execute(create_table_t4).unwrap();
         assert_eq!(exec_result.rows_affected, 0);
 
-        let cursor_impl = connection.conn.execute(r#"SELECT A.NAME, A.ID, A.COLID, A.TYPE$, A.LENGTH$, A.SCALE, A.NULLABLE$, A.DEFVAL, A.INFO2 AS IS_IDENTITY, B.NAME AS TABLE_NAME, B.CRTDATE FROM SYSCOLUMNS AS a LEFT JOIN SYSOBJECTS AS B ON A.id = B.id WHERE B.name IN ('T4') AND B.SCHID IN (SELECT ID FROM SYSOBJECTS WHERE name = 'SYSDBA');"#, ()).unwrap().unwrap();
+        let cursor_impl = connection.connection().execute(r#"SELECT A.NAME, A.ID, A.COLID, A.TYPE$, A.LENGTH$, A.SCALE, A.NULLABLE$, A.DEFVAL, A.INFO2 AS IS_IDENTITY, B.NAME AS TABLE_NAME, B.CRTDATE FROM SYSCOLUMNS AS a LEFT JOIN SYSOBJECTS AS B ON A.id = B.id WHERE B.name IN ('T4') AND B.SCHID IN (SELECT ID FROM SYSOBJECTS WHERE name = 'SYSDBA');"#, ()).unwrap().unwrap();
         cursor_impl.print_all_tables().unwrap();
 
         //2. query table
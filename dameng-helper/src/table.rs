@@ -39,6 +39,19 @@ impl DmTableItem {
         vec.push(self.subtype.clone().unwrap_or_default());
         vec
     }
+
+    /// The fractional second digits of a TIME/TIMESTAMP column. Dameng keeps the time zone flags
+    /// in the high bits of `SCALE`, e.g. 4102(0x1006) is a TIMESTAMP(6) WITH LOCAL TIME ZONE.
+    pub fn fraction_precision(&self) -> Option<usize> {
+        match self.r#type {
+            DmDateType::TIME
+            | DmDateType::TIMESTAMP
+            | DmDateType::TIME_WITH_TIME_ZONE
+            | DmDateType::TIMESTAMP_WITH_TIME_ZONE
+            | DmDateType::TIMESTAMP_WITH_LOCAL_TIME_ZONE => Some(self.scale & 0xFF),
+            _ => None,
+        }
+    }
 }
 
 /// table describe
@@ -286,4 +299,22 @@ mod tests {
 └───────────────────┴──────┴───────┴────────────────────────────────┴────────────┴───────┴───────────┴────────────────────┴─────────────┴────────────┴────────────────────────────┴──────────┘"#;
         assert_eq!(string, expect);
     }
+
+    #[test]
+    fn test_fraction_precision() {
+        let result = mock_table_result();
+        let dm_table_desc = DmTableDesc::new(result.0, result.1).unwrap();
+        let precision = |name: &str| {
+            dm_table_desc.data["T2"]
+                .iter()
+                .find(|item| item.name == name)
+                .unwrap()
+                .fraction_precision()
+        };
+        assert_eq!(precision("C2"), Some(6));
+        assert_eq!(precision("C5"), Some(0));
+        // the local time zone flag is cut from SCALE 4102
+        assert_eq!(precision("C6"), Some(6));
+        assert_eq!(precision("C3"), None);
+    }
 }
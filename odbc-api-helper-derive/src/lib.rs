@@ -0,0 +1,209 @@
+//! Derive macros for `odbc-api-helper`, use them through the `derive` feature:
+//!
+//! ```toml
+//! odbc-api-helper = { version = "0.2", features = ["derive"] }
+//! ```
+//!
+//! * `#[derive(FromRow)]` rebuilds a struct from a `QueryResult` row, named fields are looked up
+//!   by column name and tuple fields by column index.
+//! * `#[derive(ToParams)]` turns a struct into the `SqlValue` values of a `Statement`, e.g.
+//!   `PgValueInput`, in field declaration order. Every field is converted with `Into`.
+//! * `#[derive(ToNamedParams)]` turns a struct with named fields into the values of the named
+//!   placeholders of a `Statement`, by field name.
+//!
+//! A named field could be bound to another column or parameter name with
+//! `#[odbc(rename = "name")]`.
+#![deny(missing_debug_implementations)]
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    parse_macro_input, parse_quote, Data, DeriveInput, Field, Fields, Generics, Index, LitStr,
+};
+
+#[proc_macro_derive(FromRow, attributes(odbc))]
+pub fn derive_from_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_from_row(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(ToParams, attributes(odbc))]
+pub fn derive_to_params(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_to_params(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(ToNamedParams, attributes(odbc))]
+pub fn derive_to_named_params(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_to_named_params(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn struct_fields(input: &DeriveInput) -> syn::Result<&Fields> {
+    match &input.data {
+        Data::Struct(data) => Ok(&data.fields),
+        _ => Err(syn::Error::new(
+            Span::call_site(),
+            "only struct is supported by odbc-api-helper derive",
+        )),
+    }
+}
+
+/// Parse the column name from `#[odbc(rename = "...")]`
+fn column_rename(field: &Field) -> syn::Result<Option<LitStr>> {
+    let mut rename = None;
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("odbc")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                rename = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported odbc attribute, expected `rename`"))
+            }
+        })?;
+    }
+    Ok(rename)
+}
+
+/// The column or parameter name of a named field, the field name unless renamed.
+fn field_name(field: &Field) -> syn::Result<LitStr> {
+    let ident = field.ident.as_ref().expect("named field");
+    Ok(column_rename(field)?.unwrap_or_else(|| {
+        let name = ident.to_string();
+        LitStr::new(name.trim_start_matches("r#"), ident.span())
+    }))
+}
+
+/// The generics of a `ToParams` or `ToNamedParams` impl: the struct generics and the parameter
+/// type `__V`, which every field converts into.
+fn params_generics<'f>(generics: &Generics, fields: impl Iterator<Item = &'f Field>) -> Generics {
+    let mut generics = generics.clone();
+    generics.params.push(parse_quote!(
+        __V: ::odbc_api_helper::executor::statement::SqlValue + ::std::fmt::Debug
+    ));
+    let where_clause = generics.make_where_clause();
+    for field in fields {
+        let ty = &field.ty;
+        where_clause
+            .predicates
+            .push(parse_quote!(#ty: ::std::convert::Into<__V>));
+    }
+    generics
+}
+
+fn expand_from_row(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let body = match struct_fields(&input)? {
+        Fields::Named(fields) => {
+            let values = fields
+                .named
+                .iter()
+                .map(|field| {
+                    let ident = field.ident.as_ref().expect("named field");
+                    let column = field_name(field)?;
+                    Ok(quote! { #ident: row.get(#column)? })
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            quote! { Self { #(#values),* } }
+        }
+        Fields::Unnamed(fields) => {
+            let values =
+                (0..fields.unnamed.len()).map(|index| quote! { row.get_by_index(#index)? });
+            quote! { Self ( #(#values),* ) }
+        }
+        Fields::Unit => quote! { Self },
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::odbc_api_helper::orm::FromRow for #name #ty_generics #where_clause {
+            fn from_row(
+                row: &::odbc_api_helper::orm::Row<'_>,
+            ) -> ::odbc_api_helper::odbc_common::error::OdbcStdResult<Self> {
+                Ok(#body)
+            }
+        }
+    })
+}
+
+fn expand_to_params(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let fields = struct_fields(&input)?;
+    let generics = params_generics(&input.generics, fields.iter());
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    let members: Vec<TokenStream2> = match fields {
+        Fields::Named(fields) => fields
+            .named
+            .iter()
+            .map(|field| {
+                let ident = field.ident.as_ref().expect("named field");
+                quote! { self.#ident }
+            })
+            .collect(),
+        Fields::Unnamed(fields) => (0..fields.unnamed.len())
+            .map(|index| {
+                let index = Index::from(index);
+                quote! { self.#index }
+            })
+            .collect(),
+        Fields::Unit => vec![],
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::odbc_api_helper::orm::ToParams<__V> for #name #ty_generics #where_clause {
+            fn to_params(self) -> ::std::vec::Vec<__V> {
+                ::std::vec![#(::std::convert::Into::<__V>::into(#members)),*]
+            }
+        }
+    })
+}
+
+fn expand_to_named_params(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let fields = struct_fields(&input)?;
+    let generics = params_generics(&input.generics, fields.iter());
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    let params = match fields {
+        Fields::Named(fields) => fields
+            .named
+            .iter()
+            .map(|field| {
+                let ident = field.ident.as_ref().expect("named field");
+                let param = field_name(field)?;
+                Ok(quote! {
+                    (
+                        ::std::string::String::from(#param),
+                        ::std::convert::Into::<__V>::into(self.#ident),
+                    )
+                })
+            })
+            .collect::<syn::Result<Vec<_>>>()?,
+        Fields::Unit => vec![],
+        Fields::Unnamed(fields) => {
+            return Err(syn::Error::new_spanned(
+                fields,
+                "ToNamedParams needs named fields",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::odbc_api_helper::orm::ToNamedParams<__V> for #name #ty_generics #where_clause {
+            fn to_named_params(self) -> ::std::vec::Vec<(::std::string::String, __V)> {
+                ::std::vec![#(#params),*]
+            }
+        }
+    })
+}
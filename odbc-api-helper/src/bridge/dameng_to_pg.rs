@@ -38,6 +38,25 @@ impl TryConvert<PgType> for DmDateType {
             DmDateType::TIMESTAMP_WITH_TIME_ZONE => Ok(PgType::TIMESTAMPTZ),
             DmDateType::TIMESTAMP_WITH_LOCAL_TIME_ZONE => Ok(PgType::TIMESTAMPTZ),
             DmDateType::BOOL => Ok(PgType::BOOL),
+            // the 18 characters of the row address
+            DmDateType::ROWID => Ok(PgType::VARCHAR),
+            // an object has no pg type, it's carried as its text
+            DmDateType::CLASS(_) => Ok(PgType::TEXT),
+            // read as the text of the driver, e.g. `INTERVAL '1-2' YEAR TO MONTH`, which isn't the
+            // pg interval input
+            DmDateType::INTERVAL_YEAR
+            | DmDateType::INTERVAL_YEAR_TO_MONTH
+            | DmDateType::INTERVAL_MONTH
+            | DmDateType::INTERVAL_DAY
+            | DmDateType::INTERVAL_DAY_TO_HOUR
+            | DmDateType::INTERVAL_DAY_TO_MINUTE
+            | DmDateType::INTERVAL_DAY_TO_SECOND
+            | DmDateType::INTERVAL_HOUR
+            | DmDateType::INTERVAL_HOUR_TO_MINUTE
+            | DmDateType::INTERVAL_HOUR_TO_SECOND
+            | DmDateType::INTERVAL_MINUTE
+            | DmDateType::INTERVAL_MINUTE_TO_SECOND
+            | DmDateType::INTERVAL_SECOND => Ok(PgType::TEXT),
             DmDateType::Unknown => Ok(PgType::UNKNOWN),
         }
     }
@@ -47,7 +66,7 @@ impl TryConvert<PgTableItem> for DmTableItem {
     type Error = OdbcStdError;
 
     fn try_convert(self) -> OdbcStdResult<PgTableItem, Self::Error> {
-        let pg_type: PgType = self.r#type.try_convert()?;
+        let pg_type: PgType = self.r#type.clone().try_convert()?;
         Ok(PgTableItem {
             name: self.name.to_string(),
             table_id: self.table_id,
@@ -55,6 +74,7 @@ impl TryConvert<PgTableItem> for DmTableItem {
             r#type: pg_type,
             length: self.length,
             scale: self.scale,
+            precision: self.fraction_precision(),
             nullable: self.nullable,
             is_identity: self.is_identity,
             default_val: self.default_val,
@@ -64,3 +84,33 @@ impl TryConvert<PgTableItem> for DmTableItem {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dameng_type_to_pg() {
+        let cases = [
+            ("NUMBER", PgType::NUMERIC),
+            ("PLS_INTEGER", PgType::INT4),
+            ("VARCHAR2", PgType::VARCHAR),
+            ("CHARACTER VARYING", PgType::VARCHAR),
+            ("LONGVARCHAR", PgType::TEXT),
+            ("LONGVARBINARY", PgType::BYTEA),
+            ("DATETIME", PgType::TIMESTAMP),
+            ("TIMESTAMP WITH TIME ZONE", PgType::TIMESTAMPTZ),
+            ("BOOLEAN", PgType::BOOL),
+            ("INTERVAL YEAR TO MONTH", PgType::TEXT),
+            ("INTERVAL DAY TO SECOND", PgType::TEXT),
+            ("INTERVAL SECOND", PgType::TEXT),
+            ("ROWID", PgType::VARCHAR),
+            ("CLASS234881038", PgType::TEXT),
+        ];
+        for (name, expected) in cases {
+            let data_type: DmDateType = name.parse().unwrap();
+            let pg_type: PgType = data_type.try_convert().unwrap();
+            assert_eq!(pg_type, expected, "{name}");
+        }
+    }
+}
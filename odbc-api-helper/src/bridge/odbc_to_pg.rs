@@ -37,6 +37,7 @@ impl TryConvert<OdbcState> for PgState {
             PgState::STMT_FETCH_OUT_OF_RANGE => Ok(OdbcState::STMT_FETCH_OUT_OF_RANGE),
             PgState::STMT_INVALID_NULL_ARG => Ok(OdbcState::STMT_INVALID_NULL_ARG),
             PgState::STMT_COMMUNICATION_ERROR => Ok(OdbcState::STMT_COMMUNICATION_ERROR),
+            PgState::STMT_OPERATION_CANCELED => Ok(OdbcState::STMT_OPERATION_CANCELED),
         }
     }
 }
@@ -74,6 +75,9 @@ impl TryConvert<PgState> for OdbcState {
             OdbcState::STMT_FETCH_OUT_OF_RANGE => Ok(PgState::STMT_FETCH_OUT_OF_RANGE),
             OdbcState::STMT_INVALID_NULL_ARG => Ok(PgState::STMT_INVALID_NULL_ARG),
             OdbcState::STMT_COMMUNICATION_ERROR => Ok(PgState::STMT_COMMUNICATION_ERROR),
+            OdbcState::STMT_OPERATION_CANCELED | OdbcState::STMT_TIMEOUT => {
+                Ok(PgState::STMT_OPERATION_CANCELED)
+            }
         }
     }
 }
@@ -112,5 +116,8 @@ mod tests {
         let pg_state = PgState::STMT_COMMUNICATION_ERROR;
         let odbc_state = pg_state.try_convert().unwrap();
         assert_eq!(odbc_state.to_string(), "08S01");
+
+        let pg_state = OdbcState::STMT_TIMEOUT.try_convert().unwrap();
+        assert_eq!(pg_state, PgState::STMT_OPERATION_CANCELED);
     }
 }
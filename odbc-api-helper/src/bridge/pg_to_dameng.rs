@@ -25,6 +25,10 @@ impl TryConvert<DmDateType> for &PgType {
             PgType::TIMESTAMP => Ok(DmDateType::TIMESTAMP),
             PgType::TIMETZ => Ok(DmDateType::TIME_WITH_TIME_ZONE),
             PgType::TIMESTAMPTZ => Ok(DmDateType::TIMESTAMP_WITH_TIME_ZONE),
+            // a pg interval holds months and seconds, a Dameng interval either of them
+            PgType::INTERVAL => Err(OdbcStdError::TypeConversionError(format!(
+                "no dameng interval type holds the months and seconds of pg {self}"
+            ))),
             _ => Err(OdbcStdError::TypeConversionError(format!(
                 "convert pg data_type to dameng data_type error:{self}"
             ))),
@@ -0,0 +1,386 @@
+//! Asynchronous facade over a [`ConnectionTrait`], enabled by the `async` feature.
+//!
+//! An ODBC connection handle isn't `Send`, and every ODBC call blocks. [`AsyncConnection`] owns
+//! one dedicated thread per connection: the connection is created on that thread by a factory
+//! closure, never leaves it, and every job runs there in submission order. The returned futures
+//! only wait for the job result, so they don't block the async runtime.
+//!
+//! # Cancellation
+//!
+//! A job is submitted as soon as the method is called, not when the future is first polled.
+//! Dropping a future returned by [`AsyncConnectionTrait`] (e.g. by `tokio::time::timeout` or
+//! `select!`) never interrupts the ODBC call:
+//!
+//! * if the job has not been started yet, it still runs, after the jobs submitted before it;
+//! * if the job is running, it runs to completion;
+//!
+//! in both cases the result is discarded. So a cancelled `execute` may still have modified the
+//! database. A transaction must not be spread across several futures: a cancelled `commit`
+//! after a `begin` leaves the connection inside the transaction. Use
+//! [`AsyncConnectionTrait::transaction`] instead, which runs `begin`, the closure and
+//! `commit`/`rollback` as one job, so the transaction is finished even if the caller goes away.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use odbc_api_helper::executor::asynchronous::{AsyncConnection, AsyncConnectionTrait};
+//! use odbc_api_helper::executor::database::{OdbcDbConnection, Options};
+//! use odbc_api_helper::executor::SupportDatabase;
+//! use odbc_api_helper::odbc_common::odbc_api::Environment;
+//!
+//! # async fn run() -> odbc_api_helper::odbc_common::error::OdbcStdResult<()> {
+//! let env: &'static Environment = Box::leak(Box::new(Environment::new()?));
+//! let conn = AsyncConnection::connect(move || {
+//!     let conn = env.connect_with_connection_string("DSN=dameng;UID=SYSDBA;PWD=SYSDBA")?;
+//!     OdbcDbConnection::new(conn, Options::new(SupportDatabase::Dameng))
+//! })
+//! .await?;
+//! let result = conn.query("SELECT * FROM SYSCOLUMNS").await?;
+//! println!("{:?}", result);
+//! # Ok(())
+//! # }
+//! ```
+use crate::executor::batch::{BatchError, BatchMode, BatchResult};
+use crate::executor::bulk::BulkRows;
+use crate::executor::database::ConnectionTrait;
+use crate::executor::execute::ExecResult;
+use crate::executor::procedure::{ProcedureParam, ProcedureResult};
+use crate::executor::query::QueryResult;
+use crate::executor::statement::StatementInput;
+use crate::executor::table::TableDescResult;
+use crate::TryConvert;
+use odbc_common::error::{OdbcStdError, OdbcStdResult};
+use std::any::Any;
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::thread;
+use tokio::sync::oneshot;
+
+type Job<C> = Box<dyn FnOnce(&C) + Send>;
+
+pub trait AsyncConnectionTrait {
+    type Connection: ConnectionTrait;
+
+    /// Execute a `[Statement]`  INSERT,UPDATE,DELETE
+    fn execute<S>(&self, stmt: S) -> impl Future<Output = OdbcStdResult<ExecResult>> + Send
+    where
+        S: StatementInput + Send + 'static;
+
+    /// Execute a `[Statement]` and return a [`QueryResult`] on success
+    fn query<S>(&self, stmt: S) -> impl Future<Output = OdbcStdResult<QueryResult>> + Send
+    where
+        S: StatementInput + Send + 'static;
+
+    /// Execute a `[Statement]` and return every result set it produces
+    fn query_multi<S>(
+        &self,
+        stmt: S,
+    ) -> impl Future<Output = OdbcStdResult<Vec<QueryResult>>> + Send
+    where
+        S: StatementInput + Send + 'static;
+
+    fn show_table<S>(&self, stmt: S) -> impl Future<Output = OdbcStdResult<TableDescResult>> + Send
+    where
+        S: StatementInput + Send + 'static;
+
+    fn bulk_insert<B>(
+        &self,
+        table: &str,
+        rows: B,
+    ) -> impl Future<Output = OdbcStdResult<usize>> + Send
+    where
+        B: TryConvert<BulkRows, Error = OdbcStdError> + Send + 'static;
+
+    fn call_procedure(
+        &self,
+        name: &str,
+        params: Vec<ProcedureParam>,
+    ) -> impl Future<Output = OdbcStdResult<ProcedureResult>> + Send;
+
+    fn batch<S>(
+        &self,
+        stmt: Vec<S>,
+    ) -> impl Future<Output = Result<BatchResult, BatchError>> + Send
+    where
+        S: StatementInput + Send + 'static;
+
+    fn batch_with<S>(
+        &self,
+        stmt: Vec<S>,
+        mode: BatchMode,
+    ) -> impl Future<Output = Result<BatchResult, BatchError>> + Send
+    where
+        S: StatementInput + Send + 'static;
+
+    // begin transaction
+    fn begin(&self) -> impl Future<Output = OdbcStdResult<()>> + Send;
+
+    // finish transaction
+    fn finish(&self) -> impl Future<Output = OdbcStdResult<()>> + Send;
+
+    fn commit(&self) -> impl Future<Output = OdbcStdResult<()>> + Send;
+
+    fn rollback(&self) -> impl Future<Output = OdbcStdResult<()>> + Send;
+
+    /// Run `f` inside a transaction as a single job: commit when `f` returns `Ok`,
+    /// otherwise rollback. The transaction is always finished, even if the future is dropped.
+    fn transaction<R, F>(&self, f: F) -> impl Future<Output = OdbcStdResult<R>> + Send
+    where
+        R: Send + 'static,
+        F: FnOnce(&Self::Connection) -> OdbcStdResult<R> + Send + 'static;
+}
+
+/// A connection which runs on its own thread, see the [module documentation](self).
+pub struct AsyncConnection<C> {
+    sender: mpsc::Sender<Job<C>>,
+}
+
+impl<C> Debug for AsyncConnection<C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncConnection").finish_non_exhaustive()
+    }
+}
+
+impl<C: ConnectionTrait + 'static> AsyncConnection<C> {
+    /// Spawn the connection thread and create the connection on it with `factory`.
+    ///
+    /// The thread exits after the `AsyncConnection` is dropped and the submitted jobs are done.
+    pub async fn connect<F>(factory: F) -> OdbcStdResult<Self>
+    where
+        F: FnOnce() -> OdbcStdResult<C> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel::<Job<C>>();
+        let (connected_tx, connected_rx) = oneshot::channel();
+        thread::Builder::new()
+            .name("odbc-connection".to_string())
+            .spawn(move || {
+                let conn = match factory() {
+                    Ok(conn) => {
+                        let _ = connected_tx.send(Ok(()));
+                        conn
+                    }
+                    Err(e) => {
+                        let _ = connected_tx.send(Err(e));
+                        return;
+                    }
+                };
+                while let Ok(job) = receiver.recv() {
+                    job(&conn);
+                }
+            })
+            .map_err(|e| OdbcStdError::StringError(format!("spawn connection thread: {e}")))?;
+
+        connected_rx.await.map_err(|_| worker_gone())??;
+        Ok(Self { sender })
+    }
+
+    /// Run `f` with the connection on the connection thread. A panic in `f` is returned as an
+    /// error, the connection stays usable.
+    pub fn run<R, F>(&self, f: F) -> impl Future<Output = OdbcStdResult<R>> + Send
+    where
+        R: Send + 'static,
+        F: FnOnce(&C) -> OdbcStdResult<R> + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let sent = self.sender.send(Box::new(move |conn: &C| {
+            // A panic is the job's error, the thread and its connection serve the next jobs.
+            let result = panic::catch_unwind(AssertUnwindSafe(|| f(conn)))
+                .unwrap_or_else(|payload| Err(job_panicked(payload)));
+            // The receiver is gone when the future was dropped, the result is discarded.
+            let _ = tx.send(result);
+        }));
+        async move {
+            sent.map_err(|_| worker_gone())?;
+            rx.await.map_err(|_| worker_gone())?
+        }
+    }
+}
+
+fn job_panicked(payload: Box<dyn Any + Send>) -> OdbcStdError {
+    let message = match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "unknown panic".to_string(),
+        },
+    };
+    OdbcStdError::StringError(format!("odbc connection job panicked: {message}"))
+}
+
+fn worker_gone() -> OdbcStdError {
+    OdbcStdError::StringError("odbc connection thread has exited".to_string())
+}
+
+impl<C: ConnectionTrait + 'static> AsyncConnectionTrait for AsyncConnection<C> {
+    type Connection = C;
+
+    fn execute<S>(&self, stmt: S) -> impl Future<Output = OdbcStdResult<ExecResult>> + Send
+    where
+        S: StatementInput + Send + 'static,
+    {
+        self.run(move |conn| conn.execute(stmt))
+    }
+
+    fn query<S>(&self, stmt: S) -> impl Future<Output = OdbcStdResult<QueryResult>> + Send
+    where
+        S: StatementInput + Send + 'static,
+    {
+        self.run(move |conn| conn.query(stmt))
+    }
+
+    fn query_multi<S>(
+        &self,
+        stmt: S,
+    ) -> impl Future<Output = OdbcStdResult<Vec<QueryResult>>> + Send
+    where
+        S: StatementInput + Send + 'static,
+    {
+        self.run(move |conn| conn.query_multi(stmt))
+    }
+
+    fn show_table<S>(&self, stmt: S) -> impl Future<Output = OdbcStdResult<TableDescResult>> + Send
+    where
+        S: StatementInput + Send + 'static,
+    {
+        self.run(move |conn| conn.show_table(stmt))
+    }
+
+    fn bulk_insert<B>(
+        &self,
+        table: &str,
+        rows: B,
+    ) -> impl Future<Output = OdbcStdResult<usize>> + Send
+    where
+        B: TryConvert<BulkRows, Error = OdbcStdError> + Send + 'static,
+    {
+        let table = table.to_string();
+        self.run(move |conn| conn.bulk_insert(&table, rows))
+    }
+
+    fn call_procedure(
+        &self,
+        name: &str,
+        params: Vec<ProcedureParam>,
+    ) -> impl Future<Output = OdbcStdResult<ProcedureResult>> + Send {
+        let name = name.to_string();
+        self.run(move |conn| conn.call_procedure(&name, params))
+    }
+
+    fn batch<S>(&self, stmt: Vec<S>) -> impl Future<Output = Result<BatchResult, BatchError>> + Send
+    where
+        S: StatementInput + Send + 'static,
+    {
+        let batch = self.run(move |conn| Ok(conn.batch(stmt)));
+        async move { batch.await? }
+    }
+
+    fn batch_with<S>(
+        &self,
+        stmt: Vec<S>,
+        mode: BatchMode,
+    ) -> impl Future<Output = Result<BatchResult, BatchError>> + Send
+    where
+        S: StatementInput + Send + 'static,
+    {
+        let batch = self.run(move |conn| Ok(conn.batch_with(stmt, mode)));
+        async move { batch.await? }
+    }
+
+    fn begin(&self) -> impl Future<Output = OdbcStdResult<()>> + Send {
+        self.run(|conn| conn.begin())
+    }
+
+    fn finish(&self) -> impl Future<Output = OdbcStdResult<()>> + Send {
+        self.run(|conn| conn.finish())
+    }
+
+    fn commit(&self) -> impl Future<Output = OdbcStdResult<()>> + Send {
+        self.run(|conn| conn.commit())
+    }
+
+    fn rollback(&self) -> impl Future<Output = OdbcStdResult<()>> + Send {
+        self.run(|conn| conn.rollback())
+    }
+
+    fn transaction<R, F>(&self, f: F) -> impl Future<Output = OdbcStdResult<R>> + Send
+    where
+        R: Send + 'static,
+        F: FnOnce(&C) -> OdbcStdResult<R> + Send + 'static,
+    {
+        self.run(move |conn| conn.in_transaction(|_| f(conn)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::mock::{call_names, Calls, MockConnection};
+    use std::time::Duration;
+
+    async fn connect() -> (AsyncConnection<MockConnection>, Calls) {
+        let (mock, calls) = MockConnection::new();
+        let conn = AsyncConnection::connect(move || Ok(mock)).await.unwrap();
+        (conn, calls)
+    }
+
+    #[tokio::test]
+    async fn test_async_connection_runs_on_one_thread() {
+        let (conn, calls) = connect().await;
+        assert_eq!(conn.execute("0").await.unwrap().rows_affected, 1);
+        conn.query("SELECT 1").await.unwrap();
+        let result_sets = conn.query_multi("SELECT 1; SELECT 2").await.unwrap();
+        assert_eq!(result_sets.len(), 2);
+
+        let calls = calls.lock().unwrap();
+        assert_ne!(calls[0].1, thread::current().id());
+        assert!(calls.iter().all(|c| c.1 == calls[0].1));
+    }
+
+    #[tokio::test]
+    async fn test_async_connection_cancellation() {
+        let (conn, calls) = connect().await;
+
+        // The dropped future still runs to completion, before the next submitted job.
+        let cancelled = tokio::time::timeout(Duration::from_millis(10), conn.execute("200")).await;
+        assert!(cancelled.is_err());
+        conn.query("SELECT 1").await.unwrap();
+        assert_eq!(call_names(&calls), vec!["execute 200", "query SELECT 1"]);
+
+        // A dropped transaction future is still committed and finished.
+        drop(conn.transaction(|c| c.execute("50")));
+        conn.query("SELECT 2").await.unwrap();
+        assert_eq!(
+            call_names(&calls)[2..],
+            ["begin", "execute 50", "commit", "finish", "query SELECT 2"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_async_connection_transaction_rollback() {
+        let (conn, calls) = connect().await;
+        let result: OdbcStdResult<()> = conn.transaction(|_| Err("foo".into())).await;
+        assert_eq!(result.unwrap_err().to_string(), "foo");
+        assert_eq!(call_names(&calls), vec!["begin", "rollback", "finish"]);
+
+        let result = AsyncConnection::<MockConnection>::connect(|| Err("bar".into())).await;
+        assert_eq!(result.unwrap_err().to_string(), "bar");
+    }
+
+    #[tokio::test]
+    async fn test_async_connection_job_panic() {
+        let (conn, calls) = connect().await;
+        let result: OdbcStdResult<()> = conn.run(|_| panic!("foo")).await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "odbc connection job panicked: foo"
+        );
+        let result: OdbcStdResult<()> = conn.run(|_| panic!("{}", "bar")).await;
+        assert!(result.unwrap_err().to_string().ends_with("bar"));
+
+        // the connection thread keeps running the next jobs
+        conn.query("SELECT 1").await.unwrap();
+        assert_eq!(call_names(&calls), vec!["query SELECT 1"]);
+    }
+}
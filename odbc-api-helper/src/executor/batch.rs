@@ -1,10 +1,11 @@
-use odbc_common::error::OdbcStdResult;
+use odbc_common::error::{OdbcStdError, OdbcStdResult};
 
 use crate::executor::database::ConnectionTrait;
 use crate::executor::execute::ExecResult;
 use crate::executor::query::QueryResult;
 use crate::executor::statement::StatementInput;
 use crate::executor::table::TableDescResult;
+use std::fmt::{Display, Formatter};
 
 pub trait Operation {
     fn call<Conn, S>(
@@ -22,6 +23,8 @@ pub trait Operation {
 pub enum OdbcOperation {
     Execute,
     Query,
+    /// A query with several result sets, see [`ConnectionTrait::query_multi`].
+    QueryMulti,
     ShowTable,
 }
 
@@ -40,36 +43,281 @@ impl Operation for OdbcOperation {
         match self {
             OdbcOperation::Execute => Ok(conn.execute(stmt)?.to_batch(batch_result)),
             OdbcOperation::Query => Ok(conn.query(stmt)?.to_batch(batch_result)),
+            OdbcOperation::QueryMulti => Ok(conn.query_multi(stmt)?.to_batch(batch_result)),
             OdbcOperation::ShowTable => Ok(conn.show_table(stmt)?.to_batch(batch_result)),
         }
     }
 }
 
+/// How [`ConnectionTrait::batch_with`] handles a failed statement.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BatchMode {
+    /// Run the batch in one transaction, the first error rolls back the whole batch
+    /// and is returned as a [`BatchError`] with the outcomes of the statements before it.
+    ///
+    /// The batch begins and commits its own transaction, don't use it inside a transaction
+    /// begun by the caller.
+    #[default]
+    AllOrNothing,
+    /// Run every statement as it comes, a failed statement is recorded and the
+    /// batch goes on with the next one.
+    ///
+    /// In autocommit mode each statement commits on its own, inside a transaction begun by
+    /// the caller the statements stay in it until the caller commits or rolls back.
+    ContinueOnError,
+    /// Like [`BatchMode::ContinueOnError`], but stop at the first error which is recorded.
+    StopOnError,
+}
+
+/// The outcome of one statement in a batch.
+#[derive(Debug)]
+pub enum BatchOutcome {
+    Execute(ExecResult),
+    Query(QueryResult),
+    /// The result sets of an [`OdbcOperation::QueryMulti`].
+    QueryMulti(Vec<QueryResult>),
+    TableDesc(TableDescResult),
+    Error(OdbcStdError),
+}
+
+impl BatchOutcome {
+    pub fn is_error(&self) -> bool {
+        matches!(self, BatchOutcome::Error(_))
+    }
+}
+
 pub trait AnyBatchResult {
     fn to_batch(self, batch: &mut BatchResult);
 }
 
 impl AnyBatchResult for ExecResult {
     fn to_batch(self, batch: &mut BatchResult) {
-        batch.execute.push(self);
+        batch.outcomes.push(BatchOutcome::Execute(self));
     }
 }
 
 impl AnyBatchResult for QueryResult {
     fn to_batch(self, batch: &mut BatchResult) {
-        batch.query.push(self);
+        batch.outcomes.push(BatchOutcome::Query(self));
+    }
+}
+
+impl AnyBatchResult for Vec<QueryResult> {
+    fn to_batch(self, batch: &mut BatchResult) {
+        batch.outcomes.push(BatchOutcome::QueryMulti(self));
     }
 }
 
 impl AnyBatchResult for TableDescResult {
     fn to_batch(self, batch: &mut BatchResult) {
-        batch.table_desc.push(self);
+        batch.outcomes.push(BatchOutcome::TableDesc(self));
+    }
+}
+
+impl AnyBatchResult for OdbcStdError {
+    fn to_batch(self, batch: &mut BatchResult) {
+        batch.outcomes.push(BatchOutcome::Error(self));
     }
 }
 
+/// The outcomes of a batch, one per statement in submission order.
+///
+/// With [`BatchMode::StopOnError`] the statements after the failed one have no outcome.
 #[derive(Default, Debug)]
 pub struct BatchResult {
-    execute: Vec<ExecResult>,
-    query: Vec<QueryResult>,
-    table_desc: Vec<TableDescResult>,
+    outcomes: Vec<BatchOutcome>,
+}
+
+impl BatchResult {
+    pub fn outcomes(&self) -> &[BatchOutcome] {
+        &self.outcomes
+    }
+
+    pub fn into_outcomes(self) -> Vec<BatchOutcome> {
+        self.outcomes
+    }
+
+    pub fn len(&self) -> usize {
+        self.outcomes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.outcomes.is_empty()
+    }
+
+    /// No statement failed.
+    pub fn is_success(&self) -> bool {
+        !self.outcomes.iter().any(BatchOutcome::is_error)
+    }
+
+    pub fn execute(&self) -> impl Iterator<Item = &ExecResult> {
+        self.outcomes.iter().filter_map(|o| match o {
+            BatchOutcome::Execute(r) => Some(r),
+            _ => None,
+        })
+    }
+
+    /// The result sets of all queries.
+    pub fn query(&self) -> impl Iterator<Item = &QueryResult> {
+        self.outcomes.iter().flat_map(|o| match o {
+            BatchOutcome::Query(r) => std::slice::from_ref(r),
+            BatchOutcome::QueryMulti(r) => r.as_slice(),
+            _ => &[],
+        })
+    }
+
+    pub fn table_desc(&self) -> impl Iterator<Item = &TableDescResult> {
+        self.outcomes.iter().filter_map(|o| match o {
+            BatchOutcome::TableDesc(r) => Some(r),
+            _ => None,
+        })
+    }
+
+    /// The failed statements with their index in the batch.
+    pub fn errors(&self) -> impl Iterator<Item = (usize, &OdbcStdError)> {
+        self.outcomes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, o)| match o {
+                BatchOutcome::Error(e) => Some((i, e)),
+                _ => None,
+            })
+    }
+}
+
+/// The error of a [`BatchMode::AllOrNothing`] batch.
+///
+/// `result` holds the outcomes of the statements which ran before the failed one, at index
+/// `result.len()`, they are rolled back. The outcomes are empty if the transaction couldn't begin.
+#[derive(Debug)]
+pub struct BatchError {
+    pub error: OdbcStdError,
+    pub result: BatchResult,
+}
+
+impl Display for BatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "batch failed after {} statements: {}",
+            self.result.len(),
+            self.error
+        )
+    }
+}
+
+impl std::error::Error for BatchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<OdbcStdError> for BatchError {
+    fn from(error: OdbcStdError) -> Self {
+        BatchError {
+            error,
+            result: BatchResult::default(),
+        }
+    }
+}
+
+impl From<BatchError> for OdbcStdError {
+    fn from(e: BatchError) -> Self {
+        e.error
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::mock::{call_names, MockConnection};
+    use crate::executor::statement::Statement;
+    use crate::extension::pg::PgValueInput;
+
+    fn statements(sql: &[&str]) -> Vec<Statement<PgValueInput, OdbcOperation>> {
+        sql.iter()
+            .map(|sql| {
+                let op = if sql.starts_with("SELECT") {
+                    OdbcOperation::Query
+                } else {
+                    OdbcOperation::Execute
+                };
+                Statement::new(*sql, vec![]).operation(op)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_batch_all_or_nothing() {
+        let (conn, calls) = MockConnection::new();
+        let result = conn.batch(statements(&["0", "SELECT 1"])).unwrap();
+        assert_eq!(result.len(), 2);
+        assert!(matches!(result.outcomes()[0], BatchOutcome::Execute(_)));
+        assert!(matches!(result.outcomes()[1], BatchOutcome::Query(_)));
+
+        let err = conn.batch(statements(&["0", "fail", "1"])).unwrap_err();
+        assert_eq!(err.result.len(), 1);
+        assert!(matches!(err.result.outcomes()[0], BatchOutcome::Execute(_)));
+        assert!(matches!(err.error, OdbcStdError::StringError(_)));
+        assert_eq!(
+            call_names(&calls)[5..],
+            ["begin", "execute 0", "execute fail", "rollback", "finish"]
+        );
+    }
+
+    #[test]
+    fn test_batch_continue_on_error() {
+        let (conn, calls) = MockConnection::new();
+        let result = conn
+            .batch_with(
+                statements(&["0", "fail", "SELECT 1"]),
+                BatchMode::ContinueOnError,
+            )
+            .unwrap();
+        assert_eq!(result.len(), 3);
+        assert!(!result.is_success());
+        assert_eq!(result.errors().map(|e| e.0).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(result.execute().count(), 1);
+        assert_eq!(result.query().count(), 1);
+        assert_eq!(
+            call_names(&calls),
+            vec!["execute 0", "execute fail", "query SELECT 1"]
+        );
+    }
+
+    #[test]
+    fn test_batch_query_multi() {
+        let (conn, calls) = MockConnection::new();
+        let stmt = |sql, op| Statement::<PgValueInput, _>::new(sql, vec![]).operation(op);
+        let result = conn
+            .batch(vec![
+                stmt("SELECT 1", OdbcOperation::Query),
+                stmt("SELECT 1; SELECT 2", OdbcOperation::QueryMulti),
+            ])
+            .unwrap();
+        assert!(matches!(result.outcomes()[0], BatchOutcome::Query(_)));
+        assert!(matches!(&result.outcomes()[1], BatchOutcome::QueryMulti(r) if r.len() == 2));
+        assert_eq!(result.query().count(), 3);
+        assert_eq!(
+            call_names(&calls),
+            vec![
+                "begin",
+                "query SELECT 1",
+                "query_multi SELECT 1; SELECT 2",
+                "commit",
+                "finish"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_batch_stop_on_error() {
+        let (conn, calls) = MockConnection::new();
+        let result = conn
+            .batch_with(statements(&["0", "fail", "1"]), BatchMode::StopOnError)
+            .unwrap();
+        assert_eq!(result.len(), 2);
+        assert!(result.outcomes()[1].is_error());
+        assert_eq!(call_names(&calls), vec!["execute 0", "execute fail"]);
+    }
 }
@@ -0,0 +1,489 @@
+//! Bulk insert with ODBC parameter arrays, see [`ConnectionTrait::bulk_insert`].
+//!
+//! [`ConnectionTrait::bulk_insert`]: crate::executor::database::ConnectionTrait::bulk_insert
+use crate::executor::query::QueryResult;
+use crate::extension::odbc::{OdbcColumnType, OdbcColumnValue};
+use crate::extension::pg::{PgQueryResult, PgValueInput};
+use crate::extension::util::{format_data_time_tz, format_time, format_time_tz, fraction_digits};
+use crate::{Convert, TryConvert};
+use chrono::{NaiveTime, Timelike};
+use odbc_common::error::{OdbcStdError, OdbcStdResult};
+use odbc_common::odbc_api::buffers::{AnyBuffer, AnySliceMut, BufferDesc};
+use odbc_common::odbc_api::handles::StatementRef;
+use odbc_common::odbc_api::{Bit, ColumnarBulkInserter};
+use pg_helper::PgType;
+
+/// The rows of a bulk insert, each row has one value per column, `None` is NULL.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BulkRows {
+    pub columns: Vec<String>,
+    pub types: Vec<OdbcColumnType>,
+    pub rows: Vec<Vec<Option<OdbcColumnValue>>>,
+}
+
+impl BulkRows {
+    pub fn new(columns: Vec<(String, OdbcColumnType)>) -> Self {
+        let (columns, types) = columns.into_iter().unzip();
+        Self {
+            columns,
+            types,
+            rows: vec![],
+        }
+    }
+
+    pub fn push_row(&mut self, row: Vec<Option<OdbcColumnValue>>) -> OdbcStdResult<()> {
+        if row.len() != self.columns.len() {
+            return Err(OdbcStdError::SqlParamsError(format!(
+                "bulk row has {} values, expect {} columns",
+                row.len(),
+                self.columns.len()
+            )));
+        }
+        self.rows.push(row);
+        Ok(())
+    }
+
+    /// The names are checked, they can't be bound as parameters.
+    pub(crate) fn insert_sql(&self, table: &str) -> OdbcStdResult<String> {
+        if !table.split('.').all(is_identifier) {
+            return Err(OdbcStdError::SqlParamsError(format!(
+                "invalid bulk insert table `{table}`"
+            )));
+        }
+        if let Some(column) = self.columns.iter().find(|c| !is_identifier(c)) {
+            return Err(OdbcStdError::SqlParamsError(format!(
+                "invalid bulk insert column `{column}`"
+            )));
+        }
+        let placeholders = vec!["?"; self.columns.len()].join(",");
+        Ok(format!(
+            "INSERT INTO {table} ({}) VALUES ({placeholders})",
+            self.columns.join(",")
+        ))
+    }
+
+    /// The parameter buffer of every column, text and binary buffers fit the longest value.
+    pub(crate) fn buffer_descs(&self) -> OdbcStdResult<Vec<BufferDesc>> {
+        self.types
+            .iter()
+            .enumerate()
+            .map(|(index, odbc_type)| {
+                let values = self.rows.iter().filter_map(|row| row[index].as_ref());
+                let desc = match odbc_type {
+                    OdbcColumnType::Text | OdbcColumnType::WText => {
+                        let mut max_str_len = 1;
+                        for value in values {
+                            let value: String = value.try_convert()?;
+                            max_str_len = max_str_len.max(value.len());
+                        }
+                        BufferDesc::Text { max_str_len }
+                    }
+                    OdbcColumnType::Binary => {
+                        let mut length = 1;
+                        for value in values {
+                            let value: Vec<u8> = value.try_convert()?;
+                            length = length.max(value.len());
+                        }
+                        BufferDesc::Binary { length }
+                    }
+                    OdbcColumnType::Date => BufferDesc::Date { nullable: true },
+                    // the length of `HH:MM:SS.fffffffff`, see `fill_time`
+                    OdbcColumnType::Time => BufferDesc::Text { max_str_len: 18 },
+                    OdbcColumnType::Timestamp => BufferDesc::Timestamp { nullable: true },
+                    OdbcColumnType::F64 => BufferDesc::F64 { nullable: true },
+                    OdbcColumnType::F32 => BufferDesc::F32 { nullable: true },
+                    OdbcColumnType::I8 => BufferDesc::I8 { nullable: true },
+                    OdbcColumnType::I16 => BufferDesc::I16 { nullable: true },
+                    OdbcColumnType::I32 => BufferDesc::I32 { nullable: true },
+                    OdbcColumnType::I64 => BufferDesc::I64 { nullable: true },
+                    OdbcColumnType::U8 => BufferDesc::U8 { nullable: true },
+                    OdbcColumnType::Bit => BufferDesc::Bit { nullable: true },
+                };
+                Ok(desc)
+            })
+            .collect()
+    }
+
+    /// Copy `chunk` into the bound parameter buffers of `inserter`.
+    pub(crate) fn fill(
+        &self,
+        inserter: &mut ColumnarBulkInserter<StatementRef<'_>, AnyBuffer>,
+        chunk: &[Vec<Option<OdbcColumnValue>>],
+    ) -> OdbcStdResult<()> {
+        inserter.set_num_rows(chunk.len());
+        for index in 0..self.columns.len() {
+            let values = chunk.iter().map(|row| row[index].as_ref());
+            match self.types[index] {
+                OdbcColumnType::Time => fill_time(inserter.column_mut(index), values)?,
+                _ => fill_column(inserter.column_mut(index), values)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// The time struct has no fraction of second, so a time column is bound as text with every
+/// fractional second digit of the value.
+fn fill_time<'a>(
+    column: AnySliceMut<'_>,
+    values: impl Iterator<Item = Option<&'a OdbcColumnValue>>,
+) -> OdbcStdResult<()> {
+    let mut col = match column {
+        AnySliceMut::Text(col) => col,
+        _ => {
+            return Err(OdbcStdError::TypeConversionError(
+                "bulk time parameter buffer is not text".to_string(),
+            ))
+        }
+    };
+    for (row, value) in values.enumerate() {
+        let value = value.map(time_text).transpose()?;
+        col.set_cell(row, value.as_deref().map(str::as_bytes));
+    }
+    Ok(())
+}
+
+/// The text of a bulk time, e.g. `17:28:26.308`.
+fn time_text(value: &OdbcColumnValue) -> OdbcStdResult<String> {
+    let time: NaiveTime = value.try_convert()?;
+    Ok(format_time(&time, fraction_digits(time.nanosecond())))
+}
+
+fn fill_column<'a>(
+    column: AnySliceMut<'_>,
+    values: impl Iterator<Item = Option<&'a OdbcColumnValue>>,
+) -> OdbcStdResult<()> {
+    macro_rules! fill_nullable {
+        ($col:expr, $t:ty, $to_cell:expr) => {{
+            let mut col = $col;
+            for (row, value) in values.enumerate() {
+                let cell = value
+                    .map(|v| TryConvert::<$t>::try_convert(v).map($to_cell))
+                    .transpose()?;
+                col.set_cell(row, cell);
+            }
+        }};
+    }
+
+    match column {
+        AnySliceMut::Text(mut col) => {
+            for (row, value) in values.enumerate() {
+                let value: Option<String> = value.map(|v| v.try_convert()).transpose()?;
+                col.set_cell(row, value.as_deref().map(str::as_bytes));
+            }
+        }
+        AnySliceMut::Binary(mut col) => {
+            for (row, value) in values.enumerate() {
+                let value: Option<Vec<u8>> = value.map(|v| v.try_convert()).transpose()?;
+                col.set_cell(row, value.as_deref());
+            }
+        }
+        AnySliceMut::NullableDate(col) => {
+            fill_nullable!(col, chrono::NaiveDate, |v| (&v).convert())
+        }
+        AnySliceMut::NullableTime(col) => {
+            fill_nullable!(col, chrono::NaiveTime, |v| (&v).convert())
+        }
+        AnySliceMut::NullableTimestamp(col) => {
+            fill_nullable!(col, chrono::NaiveDateTime, |v| (&v).convert())
+        }
+        AnySliceMut::NullableF64(col) => fill_nullable!(col, f64, |v| v),
+        AnySliceMut::NullableF32(col) => fill_nullable!(col, f32, |v| v),
+        AnySliceMut::NullableI8(col) => fill_nullable!(col, i8, |v| v),
+        AnySliceMut::NullableI16(col) => fill_nullable!(col, i16, |v| v),
+        AnySliceMut::NullableI32(col) => fill_nullable!(col, i32, |v| v),
+        AnySliceMut::NullableI64(col) => fill_nullable!(col, i64, |v| v),
+        AnySliceMut::NullableU8(mut col) => {
+            for (row, value) in values.enumerate() {
+                let cell = value
+                    .map(|v| {
+                        let i: i16 = v.try_convert()?;
+                        u8::try_from(i).map_err(|_| {
+                            OdbcStdError::TypeConversionError(format!("u8 from value:{v:?}"))
+                        })
+                    })
+                    .transpose()?;
+                col.set_cell(row, cell);
+            }
+        }
+        AnySliceMut::NullableBit(col) => fill_nullable!(col, bool, Bit::from_bool),
+        // `buffer_descs` only creates the buffers above
+        _ => {
+            return Err(OdbcStdError::TypeConversionError(
+                "unsupported bulk parameter buffer".to_string(),
+            ))
+        }
+    }
+    Ok(())
+}
+
+impl TryConvert<BulkRows> for BulkRows {
+    type Error = OdbcStdError;
+
+    fn try_convert(self) -> Result<BulkRows, Self::Error> {
+        Ok(self)
+    }
+}
+
+impl TryConvert<BulkRows> for QueryResult {
+    type Error = OdbcStdError;
+
+    fn try_convert(self) -> Result<BulkRows, Self::Error> {
+        let mut types = vec![None; self.columns.len()];
+        for row in &self.data {
+            for (column_type, item) in types.iter_mut().zip(row) {
+                match column_type {
+                    None => *column_type = Some(item.odbc_type),
+                    Some(t) if *t != item.odbc_type => {
+                        return Err(OdbcStdError::TypeConversionError(format!(
+                            "bulk column has mixed types {t:?} and {:?}",
+                            item.odbc_type
+                        )))
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+        let types = types.into_iter().map(|t| t.unwrap_or(OdbcColumnType::Text));
+        let mut bulk = BulkRows::new(
+            self.columns
+                .into_iter()
+                .map(|c| c.name)
+                .zip(types)
+                .collect(),
+        );
+        for row in self.data {
+            bulk.push_row(row.into_iter().map(|item| item.value).collect())?;
+        }
+        Ok(bulk)
+    }
+}
+
+impl TryConvert<BulkRows> for PgQueryResult {
+    type Error = OdbcStdError;
+
+    fn try_convert(self) -> Result<BulkRows, Self::Error> {
+        let mut bulk = BulkRows::new(
+            self.columns
+                .into_iter()
+                .map(|c| {
+                    let odbc_type = c.pg_type.convert();
+                    (c.name, odbc_type)
+                })
+                .collect(),
+        );
+        for row in self.data {
+            bulk.push_row(
+                row.into_iter()
+                    .map(|item| item.data.and_then(Convert::convert))
+                    .collect(),
+            )?;
+        }
+        Ok(bulk)
+    }
+}
+
+impl Convert<OdbcColumnType> for PgType {
+    fn convert(self) -> OdbcColumnType {
+        match self {
+            PgType::INT2 => OdbcColumnType::I16,
+            PgType::INT4 => OdbcColumnType::I32,
+            PgType::INT8 => OdbcColumnType::I64,
+            PgType::FLOAT4 => OdbcColumnType::F32,
+            PgType::FLOAT8 => OdbcColumnType::F64,
+            PgType::CHAR => OdbcColumnType::I8,
+            PgType::BOOL => OdbcColumnType::Bit,
+            PgType::BYTEA => OdbcColumnType::Binary,
+            PgType::DATE => OdbcColumnType::Date,
+            PgType::TIME => OdbcColumnType::Time,
+            PgType::TIMESTAMP => OdbcColumnType::Timestamp,
+            // TIMETZ/TIMESTAMPTZ are text which keeps the offset
+            _ => OdbcColumnType::Text,
+        }
+    }
+}
+
+impl Convert<Option<OdbcColumnValue>> for PgValueInput {
+    fn convert(self) -> Option<OdbcColumnValue> {
+        match self {
+            PgValueInput::Int2(v) => v.map(OdbcColumnValue::I16),
+            PgValueInput::Int4(v) => v.map(OdbcColumnValue::I32),
+            // bound as text, the driver converts it without losing precision
+            PgValueInput::Numeric(v) => v.map(|v| OdbcColumnValue::Text(v.to_string())),
+            PgValueInput::Int8(v) => v.map(OdbcColumnValue::I64),
+            PgValueInput::Float4(v) => v.map(OdbcColumnValue::F32),
+            PgValueInput::Float8(v) => v.map(OdbcColumnValue::F64),
+            PgValueInput::Char(v) => v.map(OdbcColumnValue::I8),
+            PgValueInput::Varchar(v) | PgValueInput::Text(v) | PgValueInput::Name(v) => {
+                v.map(OdbcColumnValue::Text)
+            }
+            PgValueInput::Bool(v) => v.map(OdbcColumnValue::Bit),
+            PgValueInput::Bytea(v) => v.map(OdbcColumnValue::Binary),
+            PgValueInput::Time(v) => v.map(OdbcColumnValue::Time),
+            PgValueInput::Timez(v) => v.map(|v| OdbcColumnValue::Text(format_time_tz(&v))),
+            PgValueInput::Timestamp(v) => v.map(OdbcColumnValue::Timestamp),
+            PgValueInput::Timestampz(v) => {
+                v.map(|v| OdbcColumnValue::Text(format_data_time_tz(&v)))
+            }
+            PgValueInput::Date(v) => v.map(OdbcColumnValue::Date),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extension::odbc::{OdbcColumnDesc, OdbcColumnItem};
+    use crate::extension::pg::{PgColumn, PgColumnItem};
+    use odbc_common::odbc_api::buffers::ColumnBuffer;
+    use odbc_common::odbc_api::DataType;
+
+    #[test]
+    fn test_bulk_rows_from_pg_query_result() {
+        let column = |name: &str, pg_type: PgType| PgColumn {
+            name: name.to_string(),
+            oid: pg_type.oid(),
+            pg_type,
+            nullable: true,
+            scale: None,
+            precision: None,
+        };
+        let result = PgQueryResult {
+            columns: vec![column("ID", PgType::INT4), column("NAME", PgType::VARCHAR)],
+            data: vec![
+                vec![
+                    PgColumnItem {
+                        data: Some(PgValueInput::Int4(Some(1))),
+                    },
+                    PgColumnItem {
+                        data: Some(PgValueInput::Varchar(Some("foo".to_string()))),
+                    },
+                ],
+                vec![
+                    PgColumnItem {
+                        data: Some(PgValueInput::Int4(Some(2))),
+                    },
+                    PgColumnItem { data: None },
+                ],
+            ],
+        };
+        let bulk: BulkRows = result.try_convert().unwrap();
+        assert_eq!(bulk.types, vec![OdbcColumnType::I32, OdbcColumnType::Text]);
+        assert_eq!(bulk.rows[1], vec![Some(OdbcColumnValue::I32(2)), None]);
+        assert_eq!(
+            bulk.insert_sql("S.T").unwrap(),
+            "INSERT INTO S.T (ID,NAME) VALUES (?,?)"
+        );
+        assert!(bulk.insert_sql("T; DROP TABLE T").is_err());
+        assert!(bulk.insert_sql("T.").is_err());
+        assert_eq!(
+            bulk.buffer_descs().unwrap(),
+            vec![
+                BufferDesc::I32 { nullable: true },
+                BufferDesc::Text { max_str_len: 3 }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bulk_rows_from_query_result() {
+        let column = |name: &str| OdbcColumnDesc::new(name.to_string(), DataType::Integer, true);
+        let result = |name_type| QueryResult {
+            columns: vec![column("ID"), column("NAME")],
+            data: vec![
+                vec![
+                    OdbcColumnItem::new(OdbcColumnType::I32, None),
+                    OdbcColumnItem::new(OdbcColumnType::WText, None),
+                ],
+                vec![
+                    OdbcColumnItem::new(OdbcColumnType::I32, Some(OdbcColumnValue::I32(1))),
+                    OdbcColumnItem::new(name_type, None),
+                ],
+            ],
+            ..Default::default()
+        };
+        let bulk: BulkRows = result(OdbcColumnType::WText).try_convert().unwrap();
+        assert_eq!(bulk.types, vec![OdbcColumnType::I32, OdbcColumnType::WText]);
+        assert!(bulk.columns.iter().eq(["ID", "NAME"]));
+        assert!(TryConvert::<BulkRows>::try_convert(result(OdbcColumnType::Binary)).is_err());
+    }
+
+    #[test]
+    fn test_fill_column() {
+        let values = [Some(OdbcColumnValue::I32(1)), None];
+        let mut buffer = AnyBuffer::from_desc(3, BufferDesc::I64 { nullable: true });
+        let AnyBuffer::NullableI64(column) = &mut buffer else {
+            unreachable!()
+        };
+        fill_column(
+            AnySliceMut::NullableI64(column.writer_n(2)),
+            values.iter().map(Option::as_ref),
+        )
+        .unwrap();
+        let filled: Vec<_> = buffer
+            .view(2)
+            .as_nullable_slice::<i64>()
+            .unwrap()
+            .map(|v| v.copied())
+            .collect();
+        assert_eq!(filled, vec![Some(1), None]);
+
+        let mut buffer = AnyBuffer::from_desc(1, BufferDesc::U8 { nullable: true });
+        let AnyBuffer::NullableU8(column) = &mut buffer else {
+            unreachable!()
+        };
+        let values = [Some(OdbcColumnValue::I32(256))];
+        assert!(fill_column(
+            AnySliceMut::NullableU8(column.writer_n(1)),
+            values.iter().map(Option::as_ref),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_bulk_time() {
+        let mut bulk = BulkRows::new(vec![("T".to_string(), OdbcColumnType::Time)]);
+        let times = [
+            NaiveTime::from_hms_opt(17, 28, 26),
+            NaiveTime::from_hms_milli_opt(17, 28, 26, 308),
+            NaiveTime::from_hms_nano_opt(17, 28, 26, 308_123_456),
+        ];
+        for time in times {
+            bulk.push_row(vec![time.map(OdbcColumnValue::Time)])
+                .unwrap();
+        }
+        bulk.push_row(vec![None]).unwrap();
+        let desc = bulk.buffer_descs().unwrap()[0];
+        assert_eq!(desc, BufferDesc::Text { max_str_len: 18 });
+
+        let filled: Vec<_> = bulk
+            .rows
+            .iter()
+            .map(|row| row[0].as_ref().map(|v| time_text(v).unwrap()))
+            .collect();
+        assert_eq!(
+            filled,
+            vec![
+                Some("17:28:26".to_string()),
+                Some("17:28:26.308".to_string()),
+                Some("17:28:26.308123456".to_string()),
+                None,
+            ]
+        );
+        assert!(time_text(&OdbcColumnValue::I32(1)).is_err());
+    }
+
+    #[test]
+    fn test_bulk_rows_push_row() {
+        let mut bulk = BulkRows::new(vec![("ID".to_string(), OdbcColumnType::I64)]);
+        assert!(bulk.push_row(vec![Some(OdbcColumnValue::I64(1))]).is_ok());
+        assert!(bulk.push_row(vec![None, None]).is_err());
+        assert_eq!(bulk.rows.len(), 1);
+    }
+}
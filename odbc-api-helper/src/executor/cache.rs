@@ -0,0 +1,113 @@
+//! The prepared statement cache of a connection, sized by `Options.statement_cache_size`.
+use lru::LruCache;
+use std::num::NonZeroUsize;
+
+/// The counters of a statement cache, see `OdbcDbConnection::statement_cache_stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StatementCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    // the number of cached statements
+    pub len: usize,
+    pub capacity: usize,
+}
+
+/// A least recently used cache keyed by sql text, a size of 0 disables the cache.
+///
+/// A statement is taken out of the cache while it runs and put back afterwards, so a failed
+/// statement is dropped instead of being reused.
+pub(crate) struct StatementCache<T> {
+    statements: Option<LruCache<String, T>>,
+    hits: u64,
+    misses: u64,
+}
+
+impl<T> StatementCache<T> {
+    pub(crate) fn new(size: usize) -> Self {
+        Self {
+            statements: NonZeroUsize::new(size).map(LruCache::new),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.statements.is_some()
+    }
+
+    /// Take the statement of `sql` out of the cache, count a hit or a miss.
+    pub(crate) fn take(&mut self, sql: &str) -> Option<T> {
+        let statements = self.statements.as_mut()?;
+        let statement = statements.pop(sql);
+        if statement.is_some() {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+        statement
+    }
+
+    /// Put the statement back, the least recently used one is dropped when the cache is full.
+    pub(crate) fn put(&mut self, sql: String, statement: T) {
+        if let Some(statements) = self.statements.as_mut() {
+            statements.put(sql, statement);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        if let Some(statements) = self.statements.as_mut() {
+            statements.clear();
+        }
+    }
+
+    pub(crate) fn stats(&self) -> StatementCacheStats {
+        StatementCacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            len: self.statements.as_ref().map_or(0, LruCache::len),
+            capacity: self
+                .statements
+                .as_ref()
+                .map_or(0, |statements| statements.cap().get()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statement_cache() {
+        let mut cache = StatementCache::new(2);
+        assert_eq!(cache.take("a"), None);
+        cache.put("a".to_string(), 1);
+        assert_eq!(cache.take("a"), Some(1));
+        cache.put("a".to_string(), 1);
+        cache.put("b".to_string(), 2);
+        cache.put("c".to_string(), 3);
+        // `a` is the least recently used one
+        assert_eq!(cache.take("a"), None);
+        assert_eq!(
+            cache.stats(),
+            StatementCacheStats {
+                hits: 1,
+                misses: 2,
+                len: 2,
+                capacity: 2
+            }
+        );
+        cache.clear();
+        assert_eq!(cache.take("b"), None);
+        assert_eq!(cache.stats().len, 0);
+    }
+
+    #[test]
+    fn test_statement_cache_disabled() {
+        let mut cache = StatementCache::new(0);
+        assert!(!cache.is_enabled());
+        cache.put("a".to_string(), 1);
+        assert_eq!(cache.take("a"), None);
+        assert_eq!(cache.stats(), StatementCacheStats::default());
+    }
+}
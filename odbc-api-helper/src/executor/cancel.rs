@@ -0,0 +1,170 @@
+//! Cancel a running statement from another thread, see [`OdbcDbConnection::cancel_handle`].
+//!
+//! [`OdbcDbConnection::cancel_handle`]: crate::executor::database::OdbcDbConnection::cancel_handle
+use crate::executor::handle::cancel_statement;
+use odbc_common::error::OdbcStdResult;
+use odbc_common::odbc_api::handles::{AsStatementRef, Statement};
+use odbc_common::odbc_api::sys::HStmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// A handle to cancel the statement running on a connection, it can be cloned and sent to
+/// another thread.
+///
+/// The canceled call fails with [`OdbcStdError::Canceled`], the same as a statement whose
+/// `Options.query_timeout` or `Statement.timeout` expired.
+///
+/// The handle of a pooled connection is invalidated when the connection is returned, so it
+/// can't cancel the statements of the next user.
+///
+/// # Example
+///
+/// ```no_run
+/// use odbc_api_helper::executor::database::{ConnectionTrait, OdbcDbConnection};
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// fn run(conn: &OdbcDbConnection) {
+///     let handle = conn.cancel_handle();
+///     thread::spawn(move || {
+///         thread::sleep(Duration::from_secs(5));
+///         handle.cancel().unwrap();
+///     });
+///     let err = conn.execute("CALL LONG_RUNNING()").unwrap_err();
+///     assert!(err.is_canceled());
+/// }
+/// ```
+///
+/// [`OdbcStdError::Canceled`]: odbc_common::error::OdbcStdError::Canceled
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle {
+    registry: Arc<Mutex<Registry>>,
+    // the handle cancels nothing once the epoch of the registry moved on
+    epoch: u64,
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    running: Option<RunningStatement>,
+    epoch: u64,
+}
+
+#[derive(Debug)]
+struct RunningStatement(HStmt);
+
+// SAFETY: the handle is only passed to `SQLCancel`, which may be called from another thread,
+// and it's cleared before the statement is freed.
+unsafe impl Send for RunningStatement {}
+
+impl CancelHandle {
+    /// Cancel the running statement, return `false` when no statement is running or the handle
+    /// is invalidated.
+    pub fn cancel(&self) -> OdbcStdResult<bool> {
+        let registry = self.lock();
+        if registry.epoch != self.epoch {
+            return Ok(false);
+        }
+        match registry.running.as_ref() {
+            Some(stmt) => {
+                // SAFETY: the lock is held, the statement can't be unregistered and freed.
+                unsafe { cancel_statement(stmt.0)? };
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Mark `stmt` as the running statement until the guard is dropped, the guard must be dropped
+    /// before the statement.
+    pub(crate) fn register(&self, stmt: &mut impl AsStatementRef) -> Running<'_> {
+        self.lock().running = Some(RunningStatement(stmt.as_stmt_ref().as_sys()));
+        Running { handle: self }
+    }
+
+    /// A handle of the current epoch, see [`CancelHandle::invalidate`].
+    pub(crate) fn current(&self) -> CancelHandle {
+        CancelHandle {
+            registry: self.registry.clone(),
+            epoch: self.lock().epoch,
+        }
+    }
+
+    /// Invalidate every handle given out so far.
+    pub(crate) fn invalidate(&self) {
+        self.lock().epoch += 1;
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Registry> {
+        self.registry.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The guard of a registered statement, see [`CancelHandle::register`].
+#[derive(Debug)]
+pub(crate) struct Running<'h> {
+    handle: &'h CancelHandle,
+}
+
+impl<'h> Drop for Running<'h> {
+    fn drop(&mut self) {
+        self.handle.lock().running = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use odbc_common::error::OdbcStdError;
+    use odbc_common::odbc_api::handles::{Record, State};
+    use odbc_common::odbc_api::Error;
+    use odbc_common::state::OdbcState;
+
+    fn diagnostics(function: &'static str, state: &[u8; 5]) -> Error {
+        Error::Diagnostics {
+            record: Record {
+                state: State(*state),
+                ..Record::default()
+            },
+            function,
+        }
+    }
+
+    #[test]
+    fn test_cancel_without_statement() {
+        let handle = CancelHandle::default();
+        assert!(!handle.clone().cancel().unwrap());
+    }
+
+    #[test]
+    fn test_invalidate() {
+        let owner = CancelHandle::default();
+        let handle = owner.current();
+        assert_eq!(handle.epoch, 0);
+        owner.invalidate();
+        assert_eq!(handle.clone().epoch, 0);
+        assert_eq!(owner.current().epoch, 1);
+
+        // a registered statement is out of reach of the invalidated handle
+        owner.lock().running = Some(RunningStatement(std::ptr::null_mut()));
+        assert!(!handle.cancel().unwrap());
+    }
+
+    #[test]
+    fn test_canceled_error() {
+        let err: OdbcStdError = diagnostics("SQLExecDirect", b"HY008").into();
+        assert!(err.is_canceled());
+        assert_eq!(err.odbc_state(), Some(OdbcState::STMT_OPERATION_CANCELED));
+
+        let err: OdbcStdError = diagnostics("SQLFetch", b"HYT00").into();
+        assert!(err.is_canceled());
+        assert_eq!(err.odbc_state(), Some(OdbcState::STMT_TIMEOUT));
+
+        // the login timeout
+        let err: OdbcStdError = diagnostics("SQLDriverConnect", b"HYT00").into();
+        assert!(!err.is_canceled());
+        assert_eq!(err.odbc_state(), Some(OdbcState::STMT_TIMEOUT));
+
+        let err: OdbcStdError = diagnostics("SQLExecDirect", b"08S01").into();
+        assert!(!err.is_canceled());
+        assert_eq!(err.odbc_state(), Some(OdbcState::STMT_COMMUNICATION_ERROR));
+    }
+}
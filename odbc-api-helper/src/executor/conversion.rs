@@ -0,0 +1,119 @@
+//! Values which can't be converted from the fetched buffers, see `Options.conversion`.
+use odbc_common::error::{OdbcStdError, OdbcStdResult};
+use std::fmt::{Display, Formatter};
+
+/// How a query handles a value which can't be converted, e.g. a date out of range.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConversionPolicy {
+    /// Fail the query with an [`OdbcStdError::TypeConversionError`].
+    #[default]
+    Fail,
+    /// Substitute NULL for the value and log a warning.
+    Null,
+    /// Skip the row of the value and log a warning.
+    SkipRow,
+}
+
+/// A value which can't be converted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionError {
+    pub column: String,
+    // the row index in the result set, starting at 0
+    pub row: usize,
+    // the value as read from the buffer
+    pub raw: String,
+    pub cause: String,
+}
+
+impl Display for ConversionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "convert column `{}` of row {} from `{}` error:{}",
+            self.column, self.row, self.raw, self.cause
+        )
+    }
+}
+
+impl From<ConversionError> for OdbcStdError {
+    fn from(e: ConversionError) -> Self {
+        OdbcStdError::TypeConversionError(e.to_string())
+    }
+}
+
+/// A value of a converted row.
+#[derive(Debug)]
+pub(crate) enum Cell<T> {
+    Valid(T),
+    // the error and the NULL value to substitute
+    Invalid(ConversionError, T),
+}
+
+/// Resolve the invalid values of a row per the policy, return `None` when the row is skipped.
+pub(crate) fn resolve_row<T>(
+    row: Vec<Cell<T>>,
+    policy: ConversionPolicy,
+) -> OdbcStdResult<Option<Vec<T>>> {
+    let mut values = Vec::with_capacity(row.len());
+    for cell in row {
+        match cell {
+            Cell::Valid(v) => values.push(v),
+            Cell::Invalid(e, null) => match policy {
+                ConversionPolicy::Fail => return Err(e.into()),
+                ConversionPolicy::Null => {
+                    warn!("{}, substitute NULL", e);
+                    values.push(null);
+                }
+                ConversionPolicy::SkipRow => {
+                    warn!("{}, skip the row", e);
+                    return Ok(None);
+                }
+            },
+        }
+    }
+    Ok(Some(values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row() -> Vec<Cell<Option<i32>>> {
+        vec![
+            Cell::Valid(Some(1)),
+            Cell::Invalid(
+                ConversionError {
+                    column: "C2".to_string(),
+                    row: 3,
+                    raw: "Date { year: 2023, month: 2, day: 30 }".to_string(),
+                    cause: "date from:2023-02-30".to_string(),
+                },
+                None,
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_resolve_row() {
+        let err = resolve_row(row(), ConversionPolicy::Fail).unwrap_err();
+        match err {
+            OdbcStdError::TypeConversionError(msg) => {
+                assert!(msg.contains("`C2`"), "{msg}");
+                assert!(msg.contains("row 3"), "{msg}");
+                assert!(msg.contains("year: 2023"), "{msg}");
+            }
+            other => panic!("expect type conversion error:{other:?}"),
+        }
+        assert_eq!(
+            resolve_row(row(), ConversionPolicy::Null).unwrap(),
+            Some(vec![Some(1), None])
+        );
+        assert_eq!(resolve_row(row(), ConversionPolicy::SkipRow).unwrap(), None);
+
+        let valid = vec![Cell::Valid(1), Cell::Valid(2)];
+        assert_eq!(
+            resolve_row(valid, ConversionPolicy::Fail).unwrap(),
+            Some(vec![1, 2])
+        );
+    }
+}
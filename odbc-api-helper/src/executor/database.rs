@@ -1,24 +1,37 @@
-use crate::executor::batch::BatchResult;
-use crate::executor::batch::Operation;
+use crate::executor::batch::{AnyBatchResult, BatchError, BatchMode, BatchResult, Operation};
+use crate::executor::bulk::BulkRows;
+use crate::executor::cache::{StatementCache, StatementCacheStats};
+use crate::executor::cancel::CancelHandle;
+use crate::executor::conversion::ConversionPolicy;
 use crate::executor::execute::ExecResult;
+use crate::executor::handle::{row_count, set_query_timeout};
+use crate::executor::isolation::{AccessMode, IsolationLevel, TransactionOptions};
+use crate::executor::long_data::LongDataCursor;
 use crate::executor::prepare::OdbcPrepared;
+use crate::executor::procedure::{call_sql, ProcedureParam, ProcedureParams, ProcedureResult};
 use crate::executor::query::QueryResult;
-use crate::executor::statement::StatementInput;
+use crate::executor::statement::{BoundStatement, StatementInput};
+use crate::executor::stream::{fetch_query_result, fetch_result_sets, QueryStream};
 use crate::executor::table::{TableDescArgsString, TableDescResult};
+use crate::executor::transaction::Transaction;
+use crate::executor::truncation::TruncationPolicy;
 use crate::executor::SupportDatabase;
-use crate::extension::odbc::{OdbcColumnDesc, OdbcColumnItem, OdbcParamDesc};
-use crate::{Convert, TryConvert};
+use crate::extension::odbc::{OdbcColumnDesc, OdbcColumnItem, OdbcColumnValue, OdbcParamDesc};
+use crate::TryConvert;
+use chrono::FixedOffset;
 use dameng_helper::DmAdapter;
-use either::Either;
 use odbc_common::error::OdbcStdError;
 use odbc_common::error::OdbcStdResult;
 use odbc_common::error::OdbcWrapperError;
 use odbc_common::odbc_api::{
-    buffers::{AnySlice, BufferDesc, ColumnarAnyBuffer},
-    handles::StatementImpl,
-    ColumnDescription, Connection, Cursor, CursorImpl, ParameterCollectionRef, ResultSetMetadata,
+    handles::{AsStatementRef, Statement, StatementImpl},
+    ColumnDescription, Connection, CursorImpl, ParameterCollectionRef, Preallocated, Prepared,
+    ResultSetMetadata,
 };
-use std::ops::IndexMut;
+use serde::de::DeserializeOwned;
+use std::cell::{Cell, RefCell};
+use std::mem;
+use std::time::Duration;
 
 pub trait ConnectionTrait {
     /// Execute a `[Statement]`  INSERT,UPDATE,DELETE
@@ -31,32 +44,337 @@ pub trait ConnectionTrait {
     where
         S: StatementInput;
 
+    /// Execute a `[Statement]` and return every result set it produces, e.g. of a Dameng
+    /// procedure or a multi-statement script. The results without columns are skipped.
+    fn query_multi<S>(&self, stmt: S) -> OdbcStdResult<Vec<QueryResult>>
+    where
+        S: StatementInput,
+    {
+        let _ = stmt;
+        Err(unsupported("query_multi"))
+    }
+
+    /// Execute a `[Statement]` and deserialize every row into `T`, the struct fields are mapped
+    /// from the column names, see [`QueryResult::deserialize_rows`].
+    fn query_as<T, S>(&self, stmt: S) -> OdbcStdResult<Vec<T>>
+    where
+        T: DeserializeOwned,
+        S: StatementInput,
+    {
+        let case_sensitive = self.options().is_some_and(|o| o.case_sensitive);
+        self.query(stmt)?.deserialize_rows(case_sensitive)
+    }
+
+    /// Execute a `[Statement]` and return a [`QueryStream`] which fetches rows lazily,
+    /// `Options.max_batch_size` rows per fetch.
+    fn query_stream<S>(&self, stmt: S) -> OdbcStdResult<QueryStream<'_>>
+    where
+        S: StatementInput,
+    {
+        let _ = stmt;
+        Err(unsupported("query_stream"))
+    }
+
     fn show_table<S>(&self, stmt: S) -> OdbcStdResult<TableDescResult>
     where
         S: StatementInput;
 
-    fn batch<S>(&self, stmt: Vec<S>) -> OdbcStdResult<BatchResult>
+    /// Insert the rows into `table` with parameter arrays of `Options.max_batch_size` rows,
+    /// return the total rows affected as counted by the driver, e.g. without the rows skipped by
+    /// `INSERT IGNORE` or with the rows written by a trigger. The rows of an array whose count
+    /// the driver doesn't know are counted as inserted.
+    ///
+    /// Every array is committed on its own in autocommit mode, use a [`Transaction`] to insert
+    /// all rows or none.
+    fn bulk_insert<B>(&self, table: &str, rows: B) -> OdbcStdResult<usize>
     where
-        S: StatementInput;
+        B: TryConvert<BulkRows, Error = OdbcStdError>,
+    {
+        let _ = (table, rows);
+        Err(unsupported("bulk_insert"))
+    }
+
+    /// Call the stored procedure `name` with `{call name(?,...)}`, return the values of the
+    /// OUT and INOUT parameters and the result sets the procedure produces.
+    fn call_procedure(
+        &self,
+        name: &str,
+        params: Vec<ProcedureParam>,
+    ) -> OdbcStdResult<ProcedureResult> {
+        let _ = (name, params);
+        Err(unsupported("call_procedure"))
+    }
+
+    /// Run the statements with [`BatchMode::AllOrNothing`].
+    fn batch<S>(&self, stmt: Vec<S>) -> Result<BatchResult, BatchError>
+    where
+        S: StatementInput,
+        Self: Sized,
+    {
+        self.batch_with(stmt, BatchMode::AllOrNothing)
+    }
+
+    /// Run the statements one by one in submission order, every statement needs an operation.
+    ///
+    /// Only a [`BatchMode::AllOrNothing`] batch returns an error, the other modes record the
+    /// failed statements in the [`BatchResult`].
+    fn batch_with<S>(&self, stmt: Vec<S>, mode: BatchMode) -> Result<BatchResult, BatchError>
+    where
+        S: StatementInput,
+        Self: Sized,
+    {
+        // TODO 1. need change to parallel execution
+        // the detail link:<https://github.com/baoyachi/odbc-bridge/issues/38>
+        let run = |s: S, batch_result: &mut BatchResult| {
+            let op = s
+                .operation()
+                .ok_or_else(|| OdbcStdError::from("not found Operation"))?;
+            op.call(self, s, batch_result)
+        };
+        let mut batch_result = BatchResult::default();
+        match mode {
+            BatchMode::AllOrNothing => {
+                let tx = self.transaction()?;
+                for s in stmt {
+                    if let Err(error) = run(s, &mut batch_result) {
+                        // dropping `tx` rolls back
+                        return Err(BatchError {
+                            error,
+                            result: batch_result,
+                        });
+                    }
+                }
+                match tx.commit() {
+                    Ok(()) => Ok(batch_result),
+                    Err(error) => Err(BatchError {
+                        error,
+                        result: batch_result,
+                    }),
+                }
+            }
+            BatchMode::ContinueOnError | BatchMode::StopOnError => {
+                for s in stmt {
+                    if let Err(e) = run(s, &mut batch_result) {
+                        e.to_batch(&mut batch_result);
+                        if mode == BatchMode::StopOnError {
+                            break;
+                        }
+                    }
+                }
+                Ok(batch_result)
+            }
+        }
+    }
+
+    /// Begin a transaction, which is rolled back when the returned guard is dropped
+    /// without [`Transaction::commit`].
+    fn transaction(&self) -> OdbcStdResult<Transaction<'_, Self>>
+    where
+        Self: Sized,
+    {
+        Transaction::begin(self)
+    }
+
+    /// Begin a transaction with its own isolation level and access mode, `None` keeps the
+    /// connection setting.
+    fn transaction_with(&self, options: TransactionOptions) -> OdbcStdResult<Transaction<'_, Self>>
+    where
+        Self: Sized,
+    {
+        Transaction::begin_with(self, options)
+    }
+
+    /// Run `f` inside a transaction: commit when `f` returns `Ok`, otherwise rollback.
+    fn in_transaction<R, F>(&self, f: F) -> OdbcStdResult<R>
+    where
+        Self: Sized,
+        F: FnOnce(&Transaction<'_, Self>) -> OdbcStdResult<R>,
+    {
+        let tx = self.transaction()?;
+        let result = f(&tx)?;
+        tx.commit()?;
+        Ok(result)
+    }
 
-    // begin transaction
+    // begin transaction, prefer `transaction` which always finishes the transaction
     fn begin(&self) -> OdbcStdResult<()>;
 
-    // finish transaction
+    /// Begin a transaction and set its isolation level and access mode with `SET TRANSACTION`,
+    /// prefer `transaction_with` which always finishes the transaction.
+    fn begin_with(&self, options: TransactionOptions) -> OdbcStdResult<()> {
+        self.begin()?;
+        set_transaction(self, options)
+    }
+
+    // finish transaction, restore autocommit
     fn finish(&self) -> OdbcStdResult<()>;
 
     fn commit(&self) -> OdbcStdResult<()>;
 
     fn rollback(&self) -> OdbcStdResult<()>;
+
+    /// Set a savepoint inside the current transaction.
+    fn savepoint(&self, name: &str) -> OdbcStdResult<()> {
+        let sql = sql_dialect(self).savepoint_sql(check_savepoint(name)?);
+        self.execute(sql)?;
+        Ok(())
+    }
+
+    /// Rollback the work done after the savepoint, the savepoint is kept.
+    fn rollback_to(&self, name: &str) -> OdbcStdResult<()> {
+        let sql = sql_dialect(self).rollback_to_savepoint_sql(check_savepoint(name)?);
+        self.execute(sql)?;
+        Ok(())
+    }
+
+    /// Release the savepoint and keep the work done after it, a no-op on Dameng.
+    fn release(&self, name: &str) -> OdbcStdResult<()> {
+        if let Some(sql) = sql_dialect(self).release_savepoint_sql(check_savepoint(name)?) {
+            self.execute(sql)?;
+        }
+        Ok(())
+    }
+
+    /// Set the isolation level of the transactions begun on the connection, it's applied with
+    /// `SET TRANSACTION` by [`ConnectionTrait::begin_with`]. The ODBC connection attribute
+    /// `SQL_ATTR_TXN_ISOLATION` isn't used, odbc-api doesn't give out the connection handle, so
+    /// the statements run in autocommit mode keep the database default.
+    fn set_isolation_level(&self, level: IsolationLevel) -> OdbcStdResult<()> {
+        let _ = level;
+        Err(unsupported("set_isolation_level"))
+    }
+
+    /// The isolation level set for the transactions, `None` is the database default. It's kept
+    /// by the connection, see [`ConnectionTrait::current_isolation_level`] for the level read
+    /// from the database.
+    fn isolation_level(&self) -> OdbcStdResult<Option<IsolationLevel>> {
+        Err(unsupported("isolation_level"))
+    }
+
+    /// Set the access mode of the transactions begun on the connection, it's applied like
+    /// [`ConnectionTrait::set_isolation_level`] instead of with `SQL_ATTR_ACCESS_MODE`.
+    fn set_access_mode(&self, mode: AccessMode) -> OdbcStdResult<()> {
+        let _ = mode;
+        Err(unsupported("set_access_mode"))
+    }
+
+    /// The access mode set for the transactions, `None` is the database default, see
+    /// [`ConnectionTrait::current_access_mode`] for the mode read from the database.
+    fn access_mode(&self) -> OdbcStdResult<Option<AccessMode>> {
+        Err(unsupported("access_mode"))
+    }
+
+    /// Read the isolation level of the current transaction back from the database, e.g. to
+    /// check `set_isolation_level` or `transaction_with` in a test, see
+    /// [`SupportDatabase::isolation_level_sql`].
+    fn current_isolation_level(&self) -> OdbcStdResult<IsolationLevel> {
+        let value = query_value(self, sql_dialect(self).isolation_level_sql())?;
+        IsolationLevel::from_session(&value)
+    }
+
+    /// Read the access mode of the current transaction back from the database, see
+    /// [`SupportDatabase::access_mode_sql`].
+    fn current_access_mode(&self) -> OdbcStdResult<AccessMode> {
+        let value = query_value(self, sql_dialect(self).access_mode_sql())?;
+        AccessMode::from_session(&value)
+    }
+
+    /// The options of the connection. Without options, the column names are converted to
+    /// uppercase and the savepoints use the standard SQL of [`SupportDatabase::Pg`].
+    fn options(&self) -> Option<&Options> {
+        None
+    }
+}
+
+/// The database which decides the savepoint and `SET TRANSACTION` SQL, see
+/// [`ConnectionTrait::options`].
+fn sql_dialect<C: ConnectionTrait + ?Sized>(conn: &C) -> SupportDatabase {
+    conn.options()
+        .map_or(SupportDatabase::Pg, |o| o.database.clone())
+}
+
+/// The first value of the first row of `sql` as text.
+fn query_value<C: ConnectionTrait + ?Sized>(conn: &C, sql: &str) -> OdbcStdResult<String> {
+    let result = conn.query(sql)?;
+    match result
+        .data
+        .first()
+        .and_then(|row| row.first())
+        .and_then(|item| item.value.as_ref())
+    {
+        Some(value) => Ok(value.to_string()),
+        None => Err(OdbcStdError::StringError(format!(
+            "`{sql}` returned no value"
+        ))),
+    }
+}
+
+/// Run `SET TRANSACTION` right after the transaction began, the transaction is rolled back and
+/// autocommit restored when it fails.
+fn set_transaction<C: ConnectionTrait + ?Sized>(
+    conn: &C,
+    options: TransactionOptions,
+) -> OdbcStdResult<()> {
+    for sql in sql_dialect(conn).set_transaction_sql(options) {
+        if let Err(e) = conn.execute(sql) {
+            if let Err(end) = conn.rollback().and_then(|_| conn.finish()) {
+                error!("end transaction after `SET TRANSACTION` error:{}", end);
+            }
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// The error of a `ConnectionTrait` method which the connection doesn't implement.
+fn unsupported(method: &str) -> OdbcStdError {
+    OdbcStdError::StringError(format!("`{method}` isn't supported by the connection"))
+}
+
+/// The savepoint name is written into the sql, only an identifier is accepted.
+fn check_savepoint(name: &str) -> OdbcStdResult<&str> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(name)
+    } else {
+        Err(OdbcStdError::SqlParamsError(format!(
+            "invalid savepoint name:{name}"
+        )))
+    }
+}
+
+/// The database and table names of a `show_table` statement, see [`TableDescArgsString`].
+pub(crate) fn table_desc_args<S: StatementInput>(stmt: S) -> OdbcStdResult<TableDescArgsString> {
+    let any = stmt.to_value().right().ok_or_else(|| {
+        OdbcStdError::OdbcError(OdbcWrapperError::DataHandlerError(
+            "expect table desc args".to_string(),
+        ))
+    })?;
+    let args = any.downcast::<TableDescArgsString>().map_err(|_| {
+        OdbcStdError::TypeConversionError("cast TableDescArgsString error".to_string())
+    })?;
+    Ok(*args)
 }
 
 #[allow(missing_debug_implementations)]
 pub struct OdbcDbConnection<'a> {
-    pub conn: Connection<'a>,
+    // declared before `conn`, the statements must be dropped before the connection. `conn` is
+    // private, so it can't be replaced or moved out while the statements borrow it
+    statement_cache: RefCell<StatementCache<CachedStatement<'a>>>,
+    conn: Connection<'a>,
     pub options: Options,
+    cancel: CancelHandle,
+    // the isolation level and access mode of the transactions, see `ConnectionTrait::begin_with`
+    transaction_options: Cell<TransactionOptions>,
 }
 
-#[derive(Debug)]
+type CachedStatement<'a> = Prepared<StatementImpl<'a>>;
+
+#[derive(Debug, Clone)]
 pub struct Options {
     pub database: SupportDatabase,
     pub max_batch_size: usize,
@@ -66,6 +384,33 @@ pub struct Options {
     // false:all column name convert uppercase
     // true: ignore，keep original column name
     pub case_sensitive: bool,
+    // the isolation level and access mode of the transactions, `None` keeps the database default.
+    // They are set with `SET TRANSACTION` when a transaction begins, see
+    // `ConnectionTrait::set_isolation_level`
+    pub isolation_level: Option<IsolationLevel>,
+    pub access_mode: Option<AccessMode>,
+    // the number of prepared statements cached by sql text, default is 0 which disables the
+    // cache. A cached statement keeps its server resources and may fail after a DDL changed its
+    // tables, see `OdbcDbConnection::clear_statement_cache`. `execute`, `query`, `query_multi`
+    // and `batch` use the cache, a `query_stream` or `query_long_data` cursor outlives the call
+    // so it always prepares its own statement
+    pub statement_cache_size: usize,
+    // fetch LONGVARCHAR/LONGVARBINARY columns with `SQLGetData` in full, default is true.
+    // false binds them to buffers of `max_str_len`/`max_binary_len` which truncate the values
+    pub long_data: bool,
+    // how a value cut to `max_str_len`/`max_binary_len` is handled, default is ignore. The cut
+    // columns are recorded in `QueryResult.truncated` in any case
+    pub truncation: TruncationPolicy,
+    // the default `SQL_ATTR_QUERY_TIMEOUT` of the statements, rounded up to seconds.
+    // `None` or zero waits without limit, `Statement.timeout` overrides it
+    pub query_timeout: Option<Duration>,
+    // how a value which can't be converted (e.g. a date out of range) is handled, default is fail
+    pub conversion: ConversionPolicy,
+    // the time zone of the session, set on connect. A TIMESTAMP/TIME WITH TIME ZONE value read
+    // without offset is in it, so reading such a value requires it. The session keeps this fixed
+    // offset across DST changes. `None` keeps the database default zone, whose offset can change,
+    // see `OdbcDbConnection::session_time_zone` to query it
+    pub time_zone: Option<FixedOffset>,
 }
 
 impl Options {
@@ -83,9 +428,27 @@ impl Options {
             max_str_len: Self::MAX_STR_LEN,
             max_binary_len: Self::MAX_BINARY_LEN,
             case_sensitive: false,
+            isolation_level: None,
+            access_mode: None,
+            statement_cache_size: 0,
+            long_data: true,
+            truncation: TruncationPolicy::Ignore,
+            query_timeout: None,
+            conversion: ConversionPolicy::Fail,
+            time_zone: None,
         }
     }
 
+    /// The offset of a time zone aware value read without offset, see `time_zone`. It fails when
+    /// `time_zone` isn't set.
+    pub fn session_offset(&self) -> OdbcStdResult<FixedOffset> {
+        self.time_zone.ok_or_else(|| {
+            OdbcStdError::StringError(
+                "the session time zone is unknown, set `Options.time_zone`".to_string(),
+            )
+        })
+    }
+
     fn check(mut self) -> Self {
         if self.max_batch_size == 0 {
             self.max_batch_size = Self::MAX_BATCH_SIZE
@@ -109,23 +472,29 @@ impl<'a> ConnectionTrait for OdbcDbConnection<'a> {
     where
         S: StatementInput,
     {
-        let sql = stmt.to_sql().to_string();
-        match stmt.input_values()? {
-            Either::Left(params) => self.exec_result(sql, &params[..]),
-            Either::Right(_) => self.exec_result(sql, ()),
-        }
+        self.execute_bound(&BoundStatement::new(stmt)?)
     }
 
     fn query<S>(&self, stmt: S) -> OdbcStdResult<QueryResult>
     where
         S: StatementInput,
     {
-        let sql = stmt.to_sql().to_string();
+        self.query_bound(&BoundStatement::new(stmt)?)
+    }
 
-        match stmt.input_values()? {
-            Either::Left(params) => self.query_result(&sql, &params[..]),
-            Either::Right(_) => self.query_result(&sql, ()),
-        }
+    fn query_multi<S>(&self, stmt: S) -> OdbcStdResult<Vec<QueryResult>>
+    where
+        S: StatementInput,
+    {
+        self.query_multi_bound(&BoundStatement::new(stmt)?)
+    }
+
+    fn query_stream<S>(&self, stmt: S) -> OdbcStdResult<QueryStream<'_>>
+    where
+        S: StatementInput,
+    {
+        let stmt = BoundStatement::new(stmt)?;
+        self.query_stream_result(&stmt.sql, &stmt.params[..], self.query_timeout(&stmt))
     }
 
     /// The `TableDescArgs` impl  `StatementInput` trait.
@@ -133,48 +502,60 @@ impl<'a> ConnectionTrait for OdbcDbConnection<'a> {
     where
         S: StatementInput,
     {
-        let any = stmt.to_value().right().ok_or_else(|| {
-            OdbcStdError::OdbcError(OdbcWrapperError::DataHandlerError(
-                "expect table desc args".to_string(),
-            ))
-        })?;
-        let args = any.downcast::<TableDescArgsString>().map_err(|_| {
-            OdbcStdError::TypeConversionError("cast TableDescArgsString error".to_string())
-        })?;
-        self.table_desc(args.0, args.1)
-    }
-
-    fn batch<S>(&self, stmt: Vec<S>) -> OdbcStdResult<BatchResult>
+        let (db_name, table_names) = table_desc_args(stmt)?;
+        self.table_desc(db_name, table_names)
+    }
+
+    fn bulk_insert<B>(&self, table: &str, rows: B) -> OdbcStdResult<usize>
     where
-        S: StatementInput,
+        B: TryConvert<BulkRows, Error = OdbcStdError>,
     {
-        self.begin()?;
-        let mut batch_result = BatchResult::default();
-        // TODO 1. Consider the current execution in the transaction
-        // TODO 2. need change to parallel execution
-        // TODO 3. consider when execute try_for_each result return error, transaction need rollback
-        // the detail link:<https://github.com/baoyachi/odbc-bridge/issues/38>
-        let result = stmt.into_iter().try_for_each(|s| {
-            let op = s
-                .operation()
-                .ok_or_else(|| OdbcStdError::from("not found Operation"))?;
-            op.call(self, s, &mut batch_result)
-        });
-        match result {
-            Ok(_) => {
-                self.commit()?;
-                self.finish()?;
-                Ok(batch_result)
-            }
-            Err(err) => {
-                self.rollback()?;
-                Err(err)
-            }
+        let bulk: BulkRows = rows.try_convert()?;
+        if bulk.rows.is_empty() {
+            return Ok(0);
         }
+        let descs = bulk.buffer_descs()?;
+        let capacity = self.options.max_batch_size.min(bulk.rows.len());
+        let mut prepared = self.conn.prepare(&bulk.insert_sql(table)?)?;
+        set_query_timeout(&mut prepared, self.options.query_timeout)?;
+        let _running = self.cancel.register(&mut prepared);
+        let hstmt = prepared.as_stmt_ref().as_sys();
+        // the buffers fit the longest value of all rows, so they are bound once
+        let mut inserter = prepared.column_inserter(capacity, descs)?;
+        let mut rows_affected = 0;
+        for chunk in bulk.rows.chunks(capacity) {
+            bulk.fill(&mut inserter, chunk)?;
+            inserter.execute()?;
+            // SAFETY: `hstmt` is the handle of `prepared`, which outlives the inserter.
+            let count = unsafe { row_count(hstmt)? };
+            rows_affected += count.unwrap_or(chunk.len());
+            inserter.clear();
+        }
+        Ok(rows_affected)
+    }
+
+    fn call_procedure(
+        &self,
+        name: &str,
+        params: Vec<ProcedureParam>,
+    ) -> OdbcStdResult<ProcedureResult> {
+        let mut params = ProcedureParams::new(params, &self.options)?;
+        let sql = call_sql(name, params.len())?;
+        // the driver writes the output parameters once all results are consumed
+        let result_sets = self.query_multi_result(&sql, &mut params, self.options.query_timeout)?;
+        Ok(ProcedureResult {
+            outputs: params.into_outputs()?,
+            result_sets,
+        })
     }
 
     fn begin(&self) -> OdbcStdResult<()> {
-        Ok(self.conn.set_autocommit(false)?)
+        self.begin_with(TransactionOptions::default())
+    }
+
+    fn begin_with(&self, options: TransactionOptions) -> OdbcStdResult<()> {
+        self.conn.set_autocommit(false)?;
+        set_transaction(self, options.or(self.transaction_options.get()))
     }
 
     fn finish(&self) -> OdbcStdResult<()> {
@@ -191,32 +572,281 @@ impl<'a> ConnectionTrait for OdbcDbConnection<'a> {
         self.conn.rollback()?;
         Ok(())
     }
+
+    fn set_isolation_level(&self, level: IsolationLevel) -> OdbcStdResult<()> {
+        let options = self.transaction_options.get().isolation_level(level);
+        self.transaction_options.set(options);
+        Ok(())
+    }
+
+    fn isolation_level(&self) -> OdbcStdResult<Option<IsolationLevel>> {
+        Ok(self.transaction_options.get().isolation_level)
+    }
+
+    fn set_access_mode(&self, mode: AccessMode) -> OdbcStdResult<()> {
+        let mut options = self.transaction_options.get();
+        options.access_mode = Some(mode);
+        self.transaction_options.set(options);
+        Ok(())
+    }
+
+    fn access_mode(&self) -> OdbcStdResult<Option<AccessMode>> {
+        Ok(self.transaction_options.get().access_mode)
+    }
+
+    fn options(&self) -> Option<&Options> {
+        Some(&self.options)
+    }
 }
 
 impl<'a> OdbcDbConnection<'a> {
     pub fn new(conn: Connection<'a>, options: Options) -> OdbcStdResult<Self> {
         let options = options.check();
-        let connection = Self { conn, options };
+        let connection = Self {
+            statement_cache: RefCell::new(StatementCache::new(options.statement_cache_size)),
+            conn,
+            options,
+            cancel: CancelHandle::default(),
+            transaction_options: Cell::new(TransactionOptions::default()),
+        };
+        connection
+            .transaction_options
+            .set(connection.default_transaction_options());
+        if let Some(offset) = connection.options.time_zone {
+            let sql = connection.options.database.set_time_zone_sql(offset);
+            connection.conn.execute(&sql, ())?;
+        }
         Ok(connection)
     }
 
-    fn exec_result<S: Into<String>>(
+    /// Query the current UTC offset of the session, e.g. to fill `Options.time_zone` of a pooled
+    /// connection. The offset of a named zone changes with DST, query it again after a change.
+    pub fn session_time_zone(&self) -> OdbcStdResult<FixedOffset> {
+        let result = self.query(self.options.database.time_zone_sql())?;
+        match result.data.first().and_then(|row| row.first()) {
+            Some(OdbcColumnItem {
+                value: Some(OdbcColumnValue::Text(offset)),
+                ..
+            }) => self.options.database.parse_time_zone(offset),
+            other => Err(OdbcStdError::StringError(format!(
+                "unexpected session time zone:{other:?}"
+            ))),
+        }
+    }
+
+    /// Make the connection ready for its next user, e.g. when it's returned to a pool: rollback
+    /// an open transaction, restore autocommit and the isolation level and access mode of
+    /// `Options`. The cancel handles given out so far are invalidated.
+    pub(crate) fn reset_session(&self) -> OdbcStdResult<()> {
+        self.cancel.invalidate();
+        self.conn.rollback()?;
+        self.conn.set_autocommit(true)?;
+        self.transaction_options
+            .set(self.default_transaction_options());
+        Ok(())
+    }
+
+    fn default_transaction_options(&self) -> TransactionOptions {
+        TransactionOptions {
+            isolation_level: self.options.isolation_level,
+            access_mode: self.options.access_mode,
+        }
+    }
+
+    /// The odbc connection, e.g. to run a statement which isn't covered by [`ConnectionTrait`].
+    pub fn connection(&self) -> &Connection<'a> {
+        &self.conn
+    }
+
+    /// A handle to cancel the running statement from another thread, see [`CancelHandle`].
+    ///
+    /// It covers `execute`, `query`, `query_multi`, `batch`, `bulk_insert` and `call_procedure`,
+    /// a `query_stream` or `query_long_data` can be canceled only while the statement executes.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.current()
+    }
+
+    /// The timeout of `stmt`, or the default of `Options.query_timeout`.
+    fn query_timeout(&self, stmt: &BoundStatement) -> Option<Duration> {
+        stmt.timeout.or(self.options.query_timeout)
+    }
+
+    pub(crate) fn execute_bound(&self, stmt: &BoundStatement) -> OdbcStdResult<ExecResult> {
+        let timeout = self.query_timeout(stmt);
+        if self.statement_cache.borrow().is_enabled() {
+            return self.with_cached_statement(&stmt.sql, timeout, |prepared| {
+                prepared.execute(&stmt.params[..])?;
+                let result = prepared
+                    .row_count()?
+                    .map(|r| ExecResult { rows_affected: r })
+                    .unwrap_or_default();
+                Ok(result)
+            });
+        }
+        self.exec_result(&stmt.sql, &stmt.params[..], timeout)
+    }
+
+    pub(crate) fn query_bound(&self, stmt: &BoundStatement) -> OdbcStdResult<QueryResult> {
+        let timeout = self.query_timeout(stmt);
+        if self.statement_cache.borrow().is_enabled() {
+            return self.with_cached_statement(&stmt.sql, timeout, |prepared| {
+                let mut cursor = prepared.execute(&stmt.params[..])?.ok_or_else(|| {
+                    OdbcStdError::OdbcError(OdbcWrapperError::DataHandlerError(
+                        "query error".to_string(),
+                    ))
+                })?;
+                let columns = Self::get_cursor_columns(&mut cursor)?;
+                fetch_query_result(cursor, columns, &self.options)
+            });
+        }
+        self.query_result(&stmt.sql, &stmt.params[..], timeout)
+    }
+
+    pub(crate) fn query_multi_bound(
+        &self,
+        stmt: &BoundStatement,
+    ) -> OdbcStdResult<Vec<QueryResult>> {
+        let timeout = self.query_timeout(stmt);
+        if self.statement_cache.borrow().is_enabled() {
+            return self.with_cached_statement(&stmt.sql, timeout, |prepared| {
+                if let Some(cursor) = prepared.execute(&stmt.params[..])? {
+                    // dropping the cursor closes it, which discards the pending results too
+                    mem::forget(cursor);
+                }
+                // all results are consumed, so the statement can be executed again
+                fetch_result_sets(prepared.as_stmt_ref(), &self.options)
+            });
+        }
+        self.query_multi_result(&stmt.sql, &stmt.params[..], timeout)
+    }
+
+    /// Run `f` with a new statement of the query timeout, it can be canceled until `f` returns.
+    fn with_statement<R>(
+        &self,
+        timeout: Option<Duration>,
+        f: impl FnOnce(&mut Preallocated<'_>) -> OdbcStdResult<R>,
+    ) -> OdbcStdResult<R> {
+        let mut stmt = self.conn.preallocate()?;
+        set_query_timeout(&mut stmt, timeout)?;
+        let _running = self.cancel.register(&mut stmt);
+        f(&mut stmt)
+    }
+
+    /// Execute a query whose cursor outlives the call, e.g. of a stream.
+    fn execute_cursor(
         &self,
-        sql: S,
+        sql: &str,
         params: impl ParameterCollectionRef,
-    ) -> OdbcStdResult<ExecResult> {
+        timeout: Option<Duration>,
+    ) -> OdbcStdResult<CursorImpl<StatementImpl<'_>>> {
         let mut stmt = self.conn.preallocate()?;
-        stmt.execute(&sql.into(), params)?;
-        let row_op = stmt.row_count()?;
-        let result = row_op
-            .map(|r| ExecResult { rows_affected: r })
-            .unwrap_or_default();
-        Ok(result)
+        set_query_timeout(&mut stmt, timeout)?;
+        let has_cursor = {
+            let _running = self.cancel.register(&mut stmt);
+            // the cursor borrows `stmt`, it's closed by the owned cursor created below
+            stmt.execute(sql, params)?.map(mem::forget).is_some()
+        };
+        if !has_cursor {
+            return Err(OdbcStdError::OdbcError(OdbcWrapperError::DataHandlerError(
+                "query error".to_string(),
+            )));
+        }
+        // SAFETY: the statement is in cursor state, as the forgotten cursor is never closed.
+        Ok(unsafe { CursorImpl::new(stmt.into_statement()) })
+    }
+
+    fn exec_result(
+        &self,
+        sql: &str,
+        params: impl ParameterCollectionRef,
+        timeout: Option<Duration>,
+    ) -> OdbcStdResult<ExecResult> {
+        self.with_statement(timeout, |stmt| {
+            stmt.execute(sql, params)?;
+            let row_op = stmt.row_count()?;
+            let result = row_op
+                .map(|r| ExecResult { rows_affected: r })
+                .unwrap_or_default();
+            Ok(result)
+        })
+    }
+
+    /// The hit/miss counters of the statement cache, see `Options.statement_cache_size`.
+    pub fn statement_cache_stats(&self) -> StatementCacheStats {
+        self.statement_cache.borrow().stats()
+    }
+
+    /// Drop all cached statements, e.g. after a DDL changed the tables they use.
+    pub fn clear_statement_cache(&self) {
+        self.statement_cache.borrow_mut().clear();
+    }
+
+    /// Run `f` with the cached statement of `sql`, prepare it on a miss. The statement is put
+    /// back into the cache only when `f` succeeds.
+    fn with_cached_statement<R>(
+        &self,
+        sql: &str,
+        timeout: Option<Duration>,
+        f: impl FnOnce(&mut CachedStatement<'a>) -> OdbcStdResult<R>,
+    ) -> OdbcStdResult<R> {
+        let cached = self.statement_cache.borrow_mut().take(sql);
+        let mut prepared = match cached {
+            Some(prepared) => prepared,
+            None => {
+                let prepared = self.conn.prepare(sql)?;
+                // SAFETY: the statement borrows `self.conn`, it's only kept in the cache which is
+                // dropped before the connection, see the field order of `OdbcDbConnection`.
+                unsafe {
+                    std::mem::transmute::<Prepared<StatementImpl<'_>>, CachedStatement<'a>>(
+                        prepared,
+                    )
+                }
+            }
+        };
+        set_query_timeout(&mut prepared, timeout)?;
+        let running = self.cancel.register(&mut prepared);
+        let result = f(&mut prepared);
+        drop(running);
+        if result.is_ok() {
+            self.statement_cache
+                .borrow_mut()
+                .put(sql.to_string(), prepared);
+        }
+        result
+    }
+
+    /// Execute a `[Statement]` and fetch the rows one by one without a bound buffer, a long
+    /// value can be read in full or streamed with [`LongDataRow::reader`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use odbc_api_helper::executor::database::OdbcDbConnection;
+    /// use std::error::Error;
+    /// use std::io;
+    ///
+    /// fn export(conn: &OdbcDbConnection, out: &mut impl io::Write) -> Result<(), Box<dyn Error>> {
+    ///     let mut cursor = conn.query_long_data("SELECT CONTENT FROM DOC")?;
+    ///     while let Some(mut row) = cursor.next_row()? {
+    ///         io::copy(&mut row.reader(0), out)?;
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn query_long_data<S>(&self, stmt: S) -> OdbcStdResult<LongDataCursor<'_>>
+    where
+        S: StatementInput,
+    {
+        let stmt = BoundStatement::new(stmt)?;
+        let mut cursor =
+            self.execute_cursor(&stmt.sql, &stmt.params[..], self.query_timeout(&stmt))?;
+        let columns = Self::get_cursor_columns(&mut cursor)?;
+        LongDataCursor::new(cursor, columns, &self.options)
     }
 
-    //TODO add test case
     pub fn prepare(&self, sql: impl AsRef<str>) -> OdbcStdResult<OdbcPrepared<StatementImpl<'_>>> {
         let mut prepared = self.conn.prepare(sql.as_ref())?;
+        set_query_timeout(&mut prepared, self.options.query_timeout)?;
 
         let columns: Vec<OdbcColumnDesc> = Self::get_cursor_columns(&mut prepared)?;
 
@@ -225,60 +855,66 @@ impl<'a> OdbcDbConnection<'a> {
             params.push(prepared.describe_param(i)?.try_into()?)
         }
 
-        Ok(OdbcPrepared::new(prepared, columns, params))
+        Ok(OdbcPrepared::new(
+            prepared,
+            columns,
+            params,
+            self.options.clone(),
+        ))
     }
 
     fn query_result(
         &self,
         sql: &str,
         params: impl ParameterCollectionRef,
+        timeout: Option<Duration>,
     ) -> OdbcStdResult<QueryResult> {
-        let mut cursor = self.conn.execute(sql, params)?.ok_or_else(|| {
-            OdbcStdError::OdbcError(OdbcWrapperError::DataHandlerError(
-                "query error".to_string(),
-            ))
-        })?;
+        self.with_statement(timeout, |stmt| {
+            let mut cursor = stmt.execute(sql, params)?.ok_or_else(|| {
+                OdbcStdError::OdbcError(OdbcWrapperError::DataHandlerError(
+                    "query error".to_string(),
+                ))
+            })?;
+            let columns = Self::get_cursor_columns(&mut cursor)?;
+            fetch_query_result(cursor, columns, &self.options)
+        })
+    }
+
+    fn query_multi_result(
+        &self,
+        sql: &str,
+        params: impl ParameterCollectionRef,
+        timeout: Option<Duration>,
+    ) -> OdbcStdResult<Vec<QueryResult>> {
+        // `Connection::execute` drops the statement when the first result has no columns,
+        // which discards the results after it.
+        self.with_statement(timeout, |stmt| {
+            if let Some(cursor) = stmt.execute(sql, params)? {
+                // dropping the cursor closes it, which discards the pending results too
+                mem::forget(cursor);
+            }
+            fetch_result_sets(stmt.as_stmt_ref(), &self.options)
+        })
+    }
+
+    fn query_stream_result(
+        &self,
+        sql: &str,
+        params: impl ParameterCollectionRef,
+        timeout: Option<Duration>,
+    ) -> OdbcStdResult<QueryStream<'_>> {
+        let mut cursor = self.execute_cursor(sql, params, timeout)?;
 
         let columns: Vec<OdbcColumnDesc> = Self::get_cursor_columns(&mut cursor)?;
 
         debug!("columns:{:?}", columns);
 
-        let descs = columns.iter().map(|c| {
-            <(&OdbcColumnDesc, &Options) as TryConvert<BufferDesc>>::try_convert((c, &self.options))
-                .unwrap()
-        });
-
-        let row_set_buffer =
-            ColumnarAnyBuffer::try_from_descs(self.options.max_batch_size, descs).unwrap();
-
-        let mut row_set_cursor = cursor.bind_buffer(row_set_buffer).unwrap();
-
-        let mut total_row = vec![];
-        while let Some(row_set) = row_set_cursor.fetch()? {
-            for index in 0..columns.len() {
-                let column_view: AnySlice = row_set.column(index);
-                let column_types: Vec<OdbcColumnItem> = column_view.convert();
-                if index == 0 {
-                    // Set the entire first column
-                    for c in column_types.into_iter() {
-                        total_row.push(vec![c]);
-                    }
-                } else {
-                    for (col_index, c) in column_types.into_iter().enumerate() {
-                        let row = total_row.index_mut(col_index);
-                        row.push(c)
-                    }
-                }
-            }
-        }
-
-        Ok(QueryResult {
-            columns,
-            data: total_row,
-        })
+        QueryStream::new(cursor, columns, &self.options)
     }
 
-    fn get_cursor_columns<C: ResultSetMetadata>(c: &mut C) -> OdbcStdResult<Vec<OdbcColumnDesc>> {
+    pub(crate) fn get_cursor_columns<C: ResultSetMetadata>(
+        c: &mut C,
+    ) -> OdbcStdResult<Vec<OdbcColumnDesc>> {
         let mut result_cols: Vec<OdbcColumnDesc> = Vec::new();
         for i in 1..=c.num_result_cols()?.try_into()? {
             let mut description = ColumnDescription::default();
@@ -288,7 +924,7 @@ impl<'a> OdbcDbConnection<'a> {
         Ok(result_cols)
     }
 
-    fn table_desc(
+    pub(crate) fn table_desc(
         &self,
         db_name: String,
         table_names: Vec<String>,
@@ -314,3 +950,72 @@ impl<'a> OdbcDbConnection<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// A connection which implements only the required methods.
+    #[derive(Default)]
+    struct MinimalConnection {
+        executed: RefCell<Vec<String>>,
+    }
+
+    impl ConnectionTrait for MinimalConnection {
+        fn execute<S: StatementInput>(&self, stmt: S) -> OdbcStdResult<ExecResult> {
+            self.executed.borrow_mut().push(stmt.to_sql().to_string());
+            Ok(ExecResult { rows_affected: 0 })
+        }
+
+        fn query<S: StatementInput>(&self, _stmt: S) -> OdbcStdResult<QueryResult> {
+            Ok(QueryResult::default())
+        }
+
+        fn show_table<S: StatementInput>(&self, _stmt: S) -> OdbcStdResult<TableDescResult> {
+            Err("not supported".into())
+        }
+
+        fn begin(&self) -> OdbcStdResult<()> {
+            Ok(())
+        }
+
+        fn finish(&self) -> OdbcStdResult<()> {
+            Ok(())
+        }
+
+        fn commit(&self) -> OdbcStdResult<()> {
+            Ok(())
+        }
+
+        fn rollback(&self) -> OdbcStdResult<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_provided_methods() {
+        let conn = MinimalConnection::default();
+        assert!(conn.options().is_none());
+        assert!(conn.query_multi("SELECT 1").is_err());
+        assert!(conn.call_procedure("P", vec![]).is_err());
+        assert!(conn
+            .set_isolation_level(IsolationLevel::Serializable)
+            .is_err());
+        conn.transaction_with(TransactionOptions::default().read_only())
+            .unwrap()
+            .commit()
+            .unwrap();
+
+        conn.in_transaction(|tx| tx.in_transaction(|_| Ok(())))
+            .unwrap();
+        assert_eq!(
+            conn.executed.into_inner(),
+            [
+                "SET TRANSACTION READ ONLY",
+                "SAVEPOINT odbc_bridge_sp_1",
+                "RELEASE SAVEPOINT odbc_bridge_sp_1"
+            ]
+        );
+    }
+}
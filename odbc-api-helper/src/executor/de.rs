@@ -0,0 +1,474 @@
+//! Deserialize `QueryResult` rows into user types with `serde`.
+//!
+//! Struct fields are matched by column name, see [`QueryResult::deserialize_rows`].
+use crate::executor::query::QueryResult;
+use crate::extension::odbc::{OdbcColumnDesc, OdbcColumnItem, OdbcColumnValue};
+use crate::TryConvert;
+use odbc_common::error::{OdbcStdError, OdbcStdResult};
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug)]
+pub struct DeError(String);
+
+impl Display for DeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for DeError {}
+
+impl de::Error for DeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        DeError(msg.to_string())
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        DeError(format!("missing column `{field}` in query result"))
+    }
+}
+
+impl QueryResult {
+    /// Deserialize every row into `T`.
+    ///
+    /// Struct fields are looked up by column name, ignoring case unless `case_sensitive` is set.
+    /// A missing column, a NULL value into a non `Option` field, or a value which can't be
+    /// converted to the field type returns `OdbcStdError::DeserializeError`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use odbc_api_helper::executor::query::QueryResult;
+    /// use odbc_api_helper::extension::odbc::{
+    ///     OdbcColumnDesc, OdbcColumnItem, OdbcColumnType, OdbcColumnValue,
+    /// };
+    /// use odbc_api_helper::odbc_common::odbc_api::DataType;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq)]
+    /// struct User {
+    ///     id: i64,
+    ///     name: Option<String>,
+    /// }
+    ///
+    /// let result = QueryResult {
+    ///     columns: vec![
+    ///         OdbcColumnDesc {
+    ///             name: "ID".to_string(),
+    ///             data_type: DataType::BigInt,
+    ///             nullable: false,
+    ///         },
+    ///         OdbcColumnDesc {
+    ///             name: "NAME".to_string(),
+    ///             data_type: DataType::Varchar { length: 255 },
+    ///             nullable: true,
+    ///         },
+    ///     ],
+    ///     data: vec![vec![
+    ///         OdbcColumnItem::new(OdbcColumnType::I64, Some(OdbcColumnValue::I64(1))),
+    ///         OdbcColumnItem::new(OdbcColumnType::Text, None),
+    ///     ]],
+    ///     truncated: vec![],
+    /// };
+    /// let users: Vec<User> = result.deserialize_rows(false).unwrap();
+    /// assert_eq!(users, vec![User { id: 1, name: None }]);
+    /// ```
+    pub fn deserialize_rows<T: DeserializeOwned>(
+        &self,
+        case_sensitive: bool,
+    ) -> OdbcStdResult<Vec<T>> {
+        self.data
+            .iter()
+            .enumerate()
+            .map(|(index, row)| {
+                T::deserialize(RowDeserializer {
+                    columns: &self.columns,
+                    row,
+                    case_sensitive,
+                })
+                .map_err(|e| OdbcStdError::DeserializeError(format!("row {index}: {e}")))
+            })
+            .collect()
+    }
+}
+
+struct RowDeserializer<'a> {
+    columns: &'a [OdbcColumnDesc],
+    row: &'a [OdbcColumnItem],
+    case_sensitive: bool,
+}
+
+impl<'a> RowDeserializer<'a> {
+    fn map_access(self, fields: &'static [&'static str]) -> RowMapAccess<'a> {
+        RowMapAccess {
+            columns: self.columns,
+            row: self.row,
+            fields,
+            case_sensitive: self.case_sensitive,
+            index: 0,
+        }
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for RowDeserializer<'a> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(self.map_access(&[]))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(self.map_access(fields))
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(RowSeqAccess {
+            columns: self.columns,
+            row: self.row,
+            index: 0,
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct map enum identifier ignored_any
+    }
+}
+
+struct RowMapAccess<'a> {
+    columns: &'a [OdbcColumnDesc],
+    row: &'a [OdbcColumnItem],
+    fields: &'static [&'static str],
+    case_sensitive: bool,
+    index: usize,
+}
+
+impl<'a> RowMapAccess<'a> {
+    /// Map the column name to the struct field name, so the lookup honors `case_sensitive`.
+    fn key(&self, name: &'a str) -> &'a str {
+        if self.case_sensitive {
+            return name;
+        }
+        self.fields
+            .iter()
+            .find(|field| field.eq_ignore_ascii_case(name))
+            .copied()
+            .unwrap_or(name)
+    }
+}
+
+impl<'de, 'a> MapAccess<'de> for RowMapAccess<'a> {
+    type Error = DeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        match self.columns.get(self.index) {
+            Some(column) => {
+                let key = self.key(&column.name);
+                seed.deserialize(key.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let index = self.index;
+        self.index += 1;
+        let column = &self.columns[index];
+        let item = self
+            .row
+            .get(index)
+            .ok_or_else(|| DeError(format!("column `{}` has no value", column.name)))?;
+        seed.deserialize(ValueDeserializer { column, item })
+    }
+}
+
+struct RowSeqAccess<'a> {
+    columns: &'a [OdbcColumnDesc],
+    row: &'a [OdbcColumnItem],
+    index: usize,
+}
+
+impl<'de, 'a> SeqAccess<'de> for RowSeqAccess<'a> {
+    type Error = DeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        let (column, item) = match (self.columns.get(self.index), self.row.get(self.index)) {
+            (Some(column), Some(item)) => (column, item),
+            _ => return Ok(None),
+        };
+        self.index += 1;
+        seed.deserialize(ValueDeserializer { column, item })
+            .map(Some)
+    }
+}
+
+struct ValueDeserializer<'a> {
+    column: &'a OdbcColumnDesc,
+    item: &'a OdbcColumnItem,
+}
+
+impl<'a> ValueDeserializer<'a> {
+    fn value(&self) -> Result<&'a OdbcColumnValue, DeError> {
+        self.item.value.as_ref().ok_or_else(|| {
+            DeError(format!(
+                "column `{}` is NULL, but the field is not Option",
+                self.column.name
+            ))
+        })
+    }
+
+    fn convert<T>(&self, target: &str) -> Result<T, DeError>
+    where
+        for<'v> &'v OdbcColumnValue: TryConvert<T, Error = OdbcStdError>,
+    {
+        let value = self.value()?;
+        value.try_convert().map_err(|e| {
+            DeError(format!(
+                "column `{}` value {value:?} can't convert to {target}: {e}",
+                self.column.name
+            ))
+        })
+    }
+
+    fn convert_unsigned<T: TryFrom<i64>>(&self, target: &str) -> Result<T, DeError> {
+        let v: i64 = self.convert(target)?;
+        T::try_from(v).map_err(|_| {
+            DeError(format!(
+                "column `{}` value {v} can't convert to {target}",
+                self.column.name
+            ))
+        })
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for ValueDeserializer<'a> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let value = match &self.item.value {
+            Some(v) => v,
+            None => return visitor.visit_none(),
+        };
+        match value {
+            OdbcColumnValue::Text(v) => visitor.visit_str(v),
+            OdbcColumnValue::Binary(v) => visitor.visit_bytes(v),
+            OdbcColumnValue::F64(v) => visitor.visit_f64(*v),
+            OdbcColumnValue::F32(v) => visitor.visit_f32(*v),
+            OdbcColumnValue::I8(v) => visitor.visit_i8(*v),
+            OdbcColumnValue::I16(v) => visitor.visit_i16(*v),
+            OdbcColumnValue::I32(v) => visitor.visit_i32(*v),
+            OdbcColumnValue::I64(v) => visitor.visit_i64(*v),
+            OdbcColumnValue::U8(v) => visitor.visit_u8(*v),
+            OdbcColumnValue::Bit(v) => visitor.visit_bool(*v),
+            OdbcColumnValue::Date(_) | OdbcColumnValue::Time(_) | OdbcColumnValue::Timestamp(_) => {
+                visitor.visit_string(value.to_string())
+            }
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_bool(self.convert("bool")?)
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_i8(self.convert("i8")?)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_i16(self.convert("i16")?)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_i32(self.convert("i32")?)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_i64(self.convert("i64")?)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_u8(self.convert_unsigned("u8")?)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_u16(self.convert_unsigned("u16")?)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_u32(self.convert_unsigned("u32")?)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_u64(self.convert_unsigned("u64")?)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_f32(self.convert("f32")?)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_f64(self.convert("f64")?)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let v: String = self.convert("char")?;
+        let mut chars = v.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => visitor.visit_char(c),
+            _ => Err(DeError(format!(
+                "column `{}` value {v:?} can't convert to char",
+                self.column.name
+            ))),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_string(self.convert("string")?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_byte_buf(self.convert("bytes")?)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.item.value {
+            Some(_) => visitor.visit_some(self),
+            None => visitor.visit_none(),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 unit unit_struct seq tuple tuple_struct map struct enum identifier
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extension::odbc::OdbcColumnType;
+    use odbc_common::odbc_api::DataType;
+    use serde::Deserialize;
+
+    fn column(name: &str, nullable: bool) -> OdbcColumnDesc {
+        OdbcColumnDesc {
+            name: name.to_string(),
+            data_type: DataType::Integer,
+            nullable,
+        }
+    }
+
+    fn query_result(value: Option<OdbcColumnValue>) -> QueryResult {
+        QueryResult {
+            columns: vec![column("ID", false), column("Name", true)],
+            data: vec![vec![
+                OdbcColumnItem::new(OdbcColumnType::I32, Some(OdbcColumnValue::I32(7))),
+                OdbcColumnItem::new(OdbcColumnType::Text, value),
+            ]],
+            truncated: vec![],
+        }
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct User {
+        id: i64,
+        name: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct StrictUser {
+        id: u32,
+        name: String,
+    }
+
+    #[test]
+    fn test_deserialize_rows() {
+        let result = query_result(Some(OdbcColumnValue::Text("foo".to_string())));
+        let users: Vec<User> = result.deserialize_rows(false).unwrap();
+        assert_eq!(
+            users,
+            vec![User {
+                id: 7,
+                name: Some("foo".to_string())
+            }]
+        );
+
+        let users: Vec<StrictUser> = result.deserialize_rows(false).unwrap();
+        assert_eq!(users[0].id, 7);
+
+        let tuples: Vec<(i32, String)> = result.deserialize_rows(false).unwrap();
+        assert_eq!(tuples, vec![(7, "foo".to_string())]);
+    }
+
+    #[test]
+    fn test_deserialize_rows_error() {
+        let result = query_result(None);
+        let users: Vec<User> = result.deserialize_rows(false).unwrap();
+        assert_eq!(users, vec![User { id: 7, name: None }]);
+
+        let err = result.deserialize_rows::<StrictUser>(false).unwrap_err();
+        assert!(err.to_string().contains("column `Name` is NULL"), "{err}");
+
+        // case sensitive: `ID` doesn't match field `id`
+        let err = result.deserialize_rows::<User>(true).unwrap_err();
+        assert!(err.to_string().contains("missing column `id`"), "{err}");
+
+        let result = query_result(Some(OdbcColumnValue::Text("foo".to_string())));
+        let err = result.deserialize_rows::<(i32, i32)>(false).unwrap_err();
+        assert!(err.to_string().contains("can't convert to i32"), "{err}");
+    }
+}
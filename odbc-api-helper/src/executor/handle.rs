@@ -0,0 +1,110 @@
+//! Raw ODBC calls which `odbc-api` doesn't wrap yet.
+use odbc_common::error::{OdbcStdError, OdbcStdResult};
+use odbc_common::odbc_api::handles::{self, Diagnostics, Record};
+use odbc_common::odbc_api::handles::{AsStatementRef, Statement};
+use odbc_common::odbc_api::sys::{
+    HStmt, Len, Pointer, SQLCancel, SQLMoreResults, SQLRowCount, SQLSetStmtAttrW, SqlReturn,
+    StatementAttribute,
+};
+use odbc_common::odbc_api::Error;
+use std::mem::ManuallyDrop;
+use std::time::Duration;
+
+/// Turn the return code into a result, the diagnostic record is read from `handle`.
+pub(crate) fn check_sql_return(
+    ret: SqlReturn,
+    handle: &impl Diagnostics,
+    function: &'static str,
+) -> OdbcStdResult<()> {
+    match ret {
+        SqlReturn::SUCCESS | SqlReturn::SUCCESS_WITH_INFO => Ok(()),
+        SqlReturn::ERROR => {
+            let mut record = Record::default();
+            if record.fill_from(handle, 1) {
+                Err(Error::Diagnostics { record, function }.into())
+            } else {
+                Err(Error::NoDiagnostics { function }.into())
+            }
+        }
+        other => Err(OdbcStdError::StringError(format!(
+            "{function} returned unexpected code:{other:?}"
+        ))),
+    }
+}
+
+/// Move to the next result of an executed statement, return `false` when there's none.
+pub(crate) fn more_results(stmt: &mut impl AsStatementRef) -> OdbcStdResult<bool> {
+    let stmt = stmt.as_stmt_ref();
+    // SAFETY: the handle is a valid statement handle borrowed from `stmt`.
+    let ret = unsafe { SQLMoreResults(stmt.as_sys()) };
+    match ret {
+        SqlReturn::NO_DATA => Ok(false),
+        ret => check_sql_return(ret, &stmt, "SQLMoreResults").map(|()| true),
+    }
+}
+
+/// Set `SQL_ATTR_QUERY_TIMEOUT` in seconds, `None` or zero waits without limit.
+pub(crate) fn set_query_timeout(
+    stmt: &mut impl AsStatementRef,
+    timeout: Option<Duration>,
+) -> OdbcStdResult<()> {
+    let stmt = stmt.as_stmt_ref();
+    let seconds = timeout.map(timeout_seconds).unwrap_or(0);
+    // SAFETY: integer attributes are passed by value in the pointer argument.
+    let ret = unsafe {
+        SQLSetStmtAttrW(
+            stmt.as_sys(),
+            StatementAttribute::QueryTimeout,
+            seconds as Pointer,
+            0,
+        )
+    };
+    check_sql_return(ret, &stmt, "SQLSetStmtAttr")
+}
+
+/// The timeout in whole seconds, a fraction is rounded up as 0 means no timeout.
+fn timeout_seconds(timeout: Duration) -> usize {
+    (timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0)) as usize
+}
+
+/// The rows affected by the last execution on `hstmt`, the sum of all parameter sets of an
+/// array. `None` when the driver can't count them.
+///
+/// # Safety
+///
+/// `hstmt` must be a valid statement handle which isn't freed during the call.
+pub(crate) unsafe fn row_count(hstmt: HStmt) -> OdbcStdResult<Option<usize>> {
+    let mut count: Len = 0;
+    let ret = SQLRowCount(hstmt, &mut count);
+    // a non owning handle, it must not free the statement on drop
+    let stmt = ManuallyDrop::new(handles::StatementImpl::new(hstmt));
+    check_sql_return(ret, &*stmt, "SQLRowCount")?;
+    // the driver returns -1 for an unknown count
+    Ok(usize::try_from(count).ok())
+}
+
+/// Cancel the function running on `hstmt`, it may be called from another thread.
+///
+/// # Safety
+///
+/// `hstmt` must be a valid statement handle which isn't freed during the call.
+pub(crate) unsafe fn cancel_statement(hstmt: HStmt) -> OdbcStdResult<()> {
+    let ret = SQLCancel(hstmt);
+    // a non owning handle, it must not free the statement on drop
+    let stmt = ManuallyDrop::new(handles::StatementImpl::new(hstmt));
+    check_sql_return(ret, &*stmt, "SQLCancel")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeout_seconds() {
+        assert_eq!(timeout_seconds(Duration::ZERO), 0);
+        assert_eq!(timeout_seconds(Duration::from_nanos(1)), 1);
+        assert_eq!(timeout_seconds(Duration::from_millis(200)), 1);
+        assert_eq!(timeout_seconds(Duration::from_secs(30)), 30);
+        assert_eq!(timeout_seconds(Duration::from_millis(30_001)), 31);
+    }
+}
@@ -0,0 +1,127 @@
+use odbc_common::error::{OdbcStdError, OdbcStdResult};
+
+/// Transaction isolation level, set with `SET TRANSACTION` when a transaction begins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IsolationLevel {
+    ReadUncommitted,
+    ReadCommitted,
+    RepeatableRead,
+    Serializable,
+}
+
+impl IsolationLevel {
+    pub(crate) fn sql(self) -> &'static str {
+        match self {
+            IsolationLevel::ReadUncommitted => "READ UNCOMMITTED",
+            IsolationLevel::ReadCommitted => "READ COMMITTED",
+            IsolationLevel::RepeatableRead => "REPEATABLE READ",
+            IsolationLevel::Serializable => "SERIALIZABLE",
+        }
+    }
+
+    /// Parse the level read from the database, e.g. `read committed` of pg, `READ-COMMITTED`
+    /// of MySQL or the number of the Dameng `V$TRX.ISOLATION` column.
+    pub(crate) fn from_session(value: &str) -> OdbcStdResult<Self> {
+        match &*value.trim().to_uppercase().replace(['-', '_'], " ") {
+            "READ UNCOMMITTED" | "0" => Ok(IsolationLevel::ReadUncommitted),
+            "READ COMMITTED" | "1" => Ok(IsolationLevel::ReadCommitted),
+            "REPEATABLE READ" | "2" => Ok(IsolationLevel::RepeatableRead),
+            "SERIALIZABLE" | "3" => Ok(IsolationLevel::Serializable),
+            _ => Err(OdbcStdError::StringError(format!(
+                "unknown isolation level:{value}"
+            ))),
+        }
+    }
+}
+
+/// Transaction access mode, set with `SET TRANSACTION` when a transaction begins.
+///
+/// The database may use read-only as a hint only, and still accept writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessMode {
+    ReadWrite,
+    ReadOnly,
+}
+
+impl AccessMode {
+    pub(crate) fn sql(self) -> &'static str {
+        match self {
+            AccessMode::ReadWrite => "READ WRITE",
+            AccessMode::ReadOnly => "READ ONLY",
+        }
+    }
+
+    /// Parse the read-only flag read from the database, e.g. `on` of pg, `1` of MySQL or `Y` of
+    /// the Dameng `V$TRX.READ_ONLY` column.
+    pub(crate) fn from_session(value: &str) -> OdbcStdResult<Self> {
+        match &*value.trim().to_uppercase() {
+            "ON" | "1" | "Y" => Ok(AccessMode::ReadOnly),
+            "OFF" | "0" | "N" => Ok(AccessMode::ReadWrite),
+            _ => Err(OdbcStdError::StringError(format!(
+                "unknown access mode:{value}"
+            ))),
+        }
+    }
+}
+
+/// The settings of a single transaction, see `ConnectionTrait::transaction_with`.
+/// `None` keeps the connection setting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransactionOptions {
+    pub isolation_level: Option<IsolationLevel>,
+    pub access_mode: Option<AccessMode>,
+}
+
+impl TransactionOptions {
+    pub fn isolation_level(mut self, level: IsolationLevel) -> Self {
+        self.isolation_level = Some(level);
+        self
+    }
+
+    pub fn read_only(mut self) -> Self {
+        self.access_mode = Some(AccessMode::ReadOnly);
+        self
+    }
+
+    /// Fill the unset settings from `defaults`.
+    pub(crate) fn or(self, defaults: TransactionOptions) -> Self {
+        TransactionOptions {
+            isolation_level: self.isolation_level.or(defaults.isolation_level),
+            access_mode: self.access_mode.or(defaults.access_mode),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_session() {
+        let levels = [
+            ("read committed", IsolationLevel::ReadCommitted),
+            ("REPEATABLE-READ", IsolationLevel::RepeatableRead),
+            ("0", IsolationLevel::ReadUncommitted),
+            ("3", IsolationLevel::Serializable),
+        ];
+        for (value, level) in levels {
+            assert_eq!(
+                IsolationLevel::from_session(value).unwrap(),
+                level,
+                "{value}"
+            );
+        }
+        assert!(IsolationLevel::from_session("snapshot").is_err());
+
+        let modes = [
+            ("on", AccessMode::ReadOnly),
+            ("off", AccessMode::ReadWrite),
+            ("1", AccessMode::ReadOnly),
+            ("N", AccessMode::ReadWrite),
+        ];
+        for (value, mode) in modes {
+            assert_eq!(AccessMode::from_session(value).unwrap(), mode, "{value}");
+        }
+        assert!(AccessMode::from_session("").is_err());
+    }
+}
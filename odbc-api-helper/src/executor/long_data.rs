@@ -0,0 +1,378 @@
+//! Long data (e.g. Dameng TEXT/CLOB/BLOB/IMAGE) fetched with `SQLGetData` in chunks.
+//!
+//! A bound row set buffer holds at most `Options.max_str_len`/`Options.max_binary_len` per value,
+//! so a LONGVARCHAR/LONGVARBINARY column is fetched row by row without a buffer when
+//! `Options.long_data` is set, see [`OdbcColumnDesc::is_long_data`]. The values are fetched in
+//! full, or streamed with a [`LongDataReader`] from [`OdbcDbConnection::query_long_data`].
+//!
+//! [`OdbcDbConnection::query_long_data`]: crate::executor::database::OdbcDbConnection::query_long_data
+use crate::executor::conversion::{resolve_row, Cell};
+use crate::executor::database::Options;
+use crate::executor::query::OdbcRow;
+use crate::executor::truncation::{record_fetched_in_full, ColumnTruncation};
+use crate::extension::odbc::{
+    utf8_text, ColumnValue, InvalidValue, OdbcColumnDesc, OdbcColumnItem, OdbcColumnType,
+    OdbcColumnValue,
+};
+use crate::TryConvert;
+use odbc_common::error::{OdbcStdError, OdbcStdResult};
+use odbc_common::odbc_api::buffers::{BufferDesc, Indicator};
+use odbc_common::odbc_api::handles::StatementImpl;
+use odbc_common::odbc_api::parameter::VarBinarySliceMut;
+use odbc_common::odbc_api::sys::{Date, Time, Timestamp};
+use odbc_common::odbc_api::{Bit, Cursor, CursorImpl, CursorRow, Nullable};
+use std::io::{self, Read};
+
+/// Whether the result set is fetched with `SQLGetData` instead of a bound buffer.
+pub(crate) fn use_long_data(columns: &[OdbcColumnDesc], options: &Options) -> bool {
+    options.long_data && columns.iter().any(OdbcColumnDesc::is_long_data)
+}
+
+/// Fetch all rows with `SQLGetData`, also return the columns with values longer than the buffer
+/// size, i.e. they would be truncated by a bound buffer but are fetched in full.
+pub(crate) fn fetch_rows_by_get_data<C: Cursor>(
+    cursor: &mut C,
+    columns: &[OdbcColumnDesc],
+    options: &Options,
+) -> OdbcStdResult<(Vec<OdbcRow>, Vec<ColumnTruncation>)> {
+    let descs = buffer_descs(columns, options)?;
+    let mut rows = vec![];
+    let mut truncated = vec![];
+    let mut buf = vec![];
+    let mut row_index = 0;
+    while let Some(mut row) = cursor.next_row()? {
+        let mut items = Vec::with_capacity(columns.len());
+        for (index, (column, desc)) in columns.iter().zip(&descs).enumerate() {
+            let (odbc_type, value) = get_value(&mut row, index as u16 + 1, *desc, &mut buf)?;
+            items.push(match value {
+                Ok(value) => {
+                    if value_exceeds_buffer(&value, options) {
+                        record_fetched_in_full(index, column, &mut truncated);
+                    }
+                    Cell::Valid(OdbcColumnItem::new(odbc_type, column.with_precision(value)))
+                }
+                Err(e) => Cell::Invalid(
+                    e.at(&column.name, row_index),
+                    OdbcColumnItem::new(odbc_type, None),
+                ),
+            });
+        }
+        if let Some(items) = resolve_row(items, options.conversion)? {
+            rows.push(items);
+        }
+        row_index += 1;
+    }
+    Ok((rows, truncated))
+}
+
+fn buffer_descs(columns: &[OdbcColumnDesc], options: &Options) -> OdbcStdResult<Vec<BufferDesc>> {
+    columns
+        .iter()
+        .map(|c| <(&OdbcColumnDesc, &Options) as TryConvert<BufferDesc>>::try_convert((c, options)))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(OdbcStdError::from)
+}
+
+fn value_exceeds_buffer(value: &Option<OdbcColumnValue>, options: &Options) -> bool {
+    match value {
+        // the buffer length is in bytes
+        Some(OdbcColumnValue::Text(v)) => v.len() > options.max_str_len,
+        Some(OdbcColumnValue::Binary(v)) => v.len() > options.max_binary_len,
+        _ => false,
+    }
+}
+
+/// Get the value of the column `col` of the current row, `buf` is reused by text and binary.
+/// A value which can't be converted is kept as an error, see `Options.conversion`.
+fn get_value(
+    row: &mut CursorRow<'_>,
+    col: u16,
+    desc: BufferDesc,
+    buf: &mut Vec<u8>,
+) -> OdbcStdResult<(OdbcColumnType, ColumnValue)> {
+    macro_rules! fixed {
+        ($t:ty, $odbc_type:ident, |$v:ident| $value:expr) => {{
+            let mut target = Nullable::<$t>::null();
+            row.get_data(col, &mut target)?;
+            let value = target
+                .into_opt()
+                .map(|$v| -> Result<OdbcColumnValue, InvalidValue> { Ok($value) })
+                .transpose();
+            (OdbcColumnType::$odbc_type, value)
+        }};
+    }
+
+    macro_rules! fixed_time {
+        ($t:ty, $odbc_type:ident) => {{
+            fixed!($t, $odbc_type, |v| OdbcColumnValue::$odbc_type(
+                (&v).try_convert().map_err(|e| InvalidValue::new(&v, e))?
+            ))
+        }};
+    }
+
+    let value = match desc {
+        BufferDesc::Text { .. } | BufferDesc::WText { .. } => {
+            let value = if row.get_text(col, buf)? {
+                utf8_text(buf).map(Some)
+            } else {
+                Ok(None)
+            };
+            (OdbcColumnType::WText, value)
+        }
+        BufferDesc::Binary { .. } => {
+            let value = row
+                .get_binary(col, buf)?
+                .then(|| OdbcColumnValue::Binary(buf.clone()));
+            (OdbcColumnType::Binary, Ok(value))
+        }
+        BufferDesc::Date { .. } => fixed_time!(Date, Date),
+        BufferDesc::Time { .. } => fixed_time!(Time, Time),
+        BufferDesc::Timestamp { .. } => fixed_time!(Timestamp, Timestamp),
+        BufferDesc::F64 { .. } => fixed!(f64, F64, |v| OdbcColumnValue::F64(v)),
+        BufferDesc::F32 { .. } => fixed!(f32, F32, |v| OdbcColumnValue::F32(v)),
+        BufferDesc::I8 { .. } => fixed!(i8, I8, |v| OdbcColumnValue::I8(v)),
+        BufferDesc::I16 { .. } => fixed!(i16, I16, |v| OdbcColumnValue::I16(v)),
+        BufferDesc::I32 { .. } => fixed!(i32, I32, |v| OdbcColumnValue::I32(v)),
+        BufferDesc::I64 { .. } => fixed!(i64, I64, |v| OdbcColumnValue::I64(v)),
+        BufferDesc::U8 { .. } => fixed!(u8, U8, |v| OdbcColumnValue::U8(v)),
+        BufferDesc::Bit { .. } => fixed!(Bit, Bit, |v| OdbcColumnValue::Bit(v.as_bool())),
+    };
+    Ok(value)
+}
+
+/// A cursor which fetches one row at a time without a bound buffer, see
+/// [`OdbcDbConnection::query_long_data`].
+///
+/// [`OdbcDbConnection::query_long_data`]: crate::executor::database::OdbcDbConnection::query_long_data
+#[allow(missing_debug_implementations)]
+pub struct LongDataCursor<'s> {
+    cursor: CursorImpl<StatementImpl<'s>>,
+    columns: Vec<OdbcColumnDesc>,
+    descs: Vec<BufferDesc>,
+    // the number of rows fetched so far
+    num_rows: usize,
+}
+
+impl<'s> LongDataCursor<'s> {
+    pub(crate) fn new(
+        cursor: CursorImpl<StatementImpl<'s>>,
+        columns: Vec<OdbcColumnDesc>,
+        options: &Options,
+    ) -> OdbcStdResult<Self> {
+        let descs = buffer_descs(&columns, options)?;
+        Ok(Self {
+            cursor,
+            columns,
+            descs,
+            num_rows: 0,
+        })
+    }
+
+    pub fn columns(&self) -> &[OdbcColumnDesc] {
+        &self.columns
+    }
+
+    /// Fetch the next row, return `None` when all rows have been fetched.
+    pub fn next_row(&mut self) -> OdbcStdResult<Option<LongDataRow<'_>>> {
+        let row = self.cursor.next_row()?;
+        let index = self.num_rows;
+        self.num_rows += usize::from(row.is_some());
+        Ok(row.map(|row| LongDataRow {
+            row,
+            columns: &self.columns,
+            descs: &self.descs,
+            index,
+        }))
+    }
+}
+
+/// The current row of a [`LongDataCursor`].
+///
+/// The columns are read with `SQLGetData`, most drivers require them to be read in increasing
+/// order and each one at most once.
+#[allow(missing_debug_implementations)]
+pub struct LongDataRow<'c> {
+    row: CursorRow<'c>,
+    columns: &'c [OdbcColumnDesc],
+    descs: &'c [BufferDesc],
+    // the row index in the result set, starting at 0
+    index: usize,
+}
+
+impl<'c> LongDataRow<'c> {
+    /// The full value of the column at `index`, starting at 0. A value which can't be
+    /// converted fails with the column name and the row index.
+    pub fn value(&mut self, index: usize) -> OdbcStdResult<Option<OdbcColumnValue>> {
+        let desc = *self.descs.get(index).ok_or_else(|| {
+            OdbcStdError::StringError(format!("column index {index} out of range"))
+        })?;
+        let mut buf = vec![];
+        let (_, value) = get_value(&mut self.row, index as u16 + 1, desc, &mut buf)?;
+        value.map_err(|e| e.at(&self.columns[index].name, self.index).into())
+    }
+
+    /// Stream the column at `index` as bytes, a NULL is empty. Text is read in the encoding
+    /// of the driver.
+    pub fn reader(&mut self, index: usize) -> LongDataReader<'_> {
+        LongDataReader {
+            chunks: Box::new(ColumnChunks {
+                row: &mut self.row,
+                col: index as u16 + 1,
+            }),
+            finished: false,
+        }
+    }
+}
+
+/// The chunks of a long value, see [`LongDataReader`].
+trait Chunks {
+    /// Write the next chunk into `buf`, return the indicator of the value left before the call.
+    fn next_chunk(&mut self, buf: &mut [u8]) -> OdbcStdResult<Indicator>;
+}
+
+/// The chunks of the column `col`, one `SQLGetData` call each.
+struct ColumnChunks<'r, 'c> {
+    row: &'r mut CursorRow<'c>,
+    col: u16,
+}
+
+impl<'r, 'c> Chunks for ColumnChunks<'r, 'c> {
+    fn next_chunk(&mut self, buf: &mut [u8]) -> OdbcStdResult<Indicator> {
+        let mut target = VarBinarySliceMut::from_buffer(buf, Indicator::NoTotal);
+        self.row.get_data(self.col, &mut target)?;
+        Ok(target.indicator())
+    }
+}
+
+/// Read a long value in chunks of the read buffer size, see [`LongDataRow::reader`].
+#[allow(missing_debug_implementations)]
+pub struct LongDataReader<'r> {
+    chunks: Box<dyn Chunks + 'r>,
+    finished: bool,
+}
+
+impl<'r> Read for LongDataReader<'r> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // `SQLGetData` returns `SQL_NO_DATA` once the value is drained, it isn't called again
+        if self.finished || buf.is_empty() {
+            return Ok(0);
+        }
+        let capacity = buf.len();
+        let indicator = self.chunks.next_chunk(buf).map_err(io::Error::other)?;
+        let read = match indicator {
+            Indicator::Null => {
+                self.finished = true;
+                0
+            }
+            Indicator::Length(len) if len <= capacity => {
+                self.finished = true;
+                len
+            }
+            Indicator::Length(_) | Indicator::NoTotal => capacity,
+        };
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::SupportDatabase;
+    use odbc_common::odbc_api::DataType;
+
+    fn column(data_type: DataType) -> OdbcColumnDesc {
+        OdbcColumnDesc::new("C".to_string(), data_type, true)
+    }
+
+    #[test]
+    fn test_use_long_data() {
+        let mut options = Options::new(SupportDatabase::Dameng);
+        let text = vec![
+            column(DataType::Integer),
+            column(DataType::LongVarchar { length: 0 }),
+        ];
+        let varchar = vec![column(DataType::Varchar { length: 10 })];
+        assert!(use_long_data(&text, &options));
+        assert!(use_long_data(
+            &[column(DataType::LongVarbinary { length: 0 })],
+            &options
+        ));
+        assert!(!use_long_data(&varchar, &options));
+
+        options.long_data = false;
+        assert!(!use_long_data(&text, &options));
+    }
+
+    #[test]
+    fn test_value_exceeds_buffer() {
+        let mut options = Options::new(SupportDatabase::Dameng);
+        options.max_str_len = 3;
+        options.max_binary_len = 2;
+        let text = |v: &str| Some(OdbcColumnValue::Text(v.to_string()));
+        assert!(!value_exceeds_buffer(&text("abc"), &options));
+        assert!(value_exceeds_buffer(&text("abcd"), &options));
+        // 9 bytes
+        assert!(value_exceeds_buffer(&text("达梦库"), &options));
+        assert!(value_exceeds_buffer(
+            &Some(OdbcColumnValue::Binary(vec![1, 2, 3])),
+            &options
+        ));
+        assert!(!value_exceeds_buffer(&None, &options));
+    }
+
+    /// Write `value` like a driver: copy as much as fits, the indicator is the length left.
+    struct ValueChunks {
+        value: Option<Vec<u8>>,
+        offset: usize,
+    }
+
+    impl Chunks for ValueChunks {
+        fn next_chunk(&mut self, buf: &mut [u8]) -> OdbcStdResult<Indicator> {
+            let Some(value) = &self.value else {
+                return Ok(Indicator::Null);
+            };
+            let left = &value[self.offset..];
+            let len = left.len().min(buf.len());
+            buf[..len].copy_from_slice(&left[..len]);
+            self.offset += len;
+            Ok(Indicator::Length(left.len()))
+        }
+    }
+
+    fn value_reader(value: Option<Vec<u8>>) -> LongDataReader<'static> {
+        LongDataReader {
+            chunks: Box::new(ValueChunks { value, offset: 0 }),
+            finished: false,
+        }
+    }
+
+    #[test]
+    fn test_long_data_reader() {
+        let value: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let mut read = vec![];
+        let mut buf = [0; 64];
+        let mut reader = value_reader(Some(value.clone()));
+        loop {
+            let len = reader.read(&mut buf).unwrap();
+            if len == 0 {
+                break;
+            }
+            assert!(len == 64 || read.len() + len == value.len());
+            read.extend_from_slice(&buf[..len]);
+        }
+        assert_eq!(read, value);
+
+        let mut read = vec![];
+        value_reader(Some(value.clone()))
+            .read_to_end(&mut read)
+            .unwrap();
+        assert_eq!(read, value);
+
+        let mut read = vec![];
+        assert_eq!(value_reader(None).read_to_end(&mut read).unwrap(), 0);
+        assert_eq!(
+            value_reader(Some(vec![])).read_to_end(&mut read).unwrap(),
+            0
+        );
+    }
+}
@@ -0,0 +1,234 @@
+//! A `ConnectionTrait` without database for the unit tests.
+use crate::executor::bulk::BulkRows;
+use crate::executor::database::{ConnectionTrait, Options};
+use crate::executor::execute::ExecResult;
+use crate::executor::isolation::{AccessMode, IsolationLevel};
+use crate::executor::procedure::{ProcedureParam, ProcedureResult};
+use crate::executor::query::QueryResult;
+use crate::executor::statement::StatementInput;
+use crate::executor::stream::{QueryStream, RowSets};
+use crate::executor::table::TableDescResult;
+use crate::executor::SupportDatabase;
+use crate::extension::odbc::{OdbcColumnDesc, OdbcColumnItem, OdbcColumnType, OdbcColumnValue};
+use crate::TryConvert;
+use odbc_common::error::{OdbcStdError, OdbcStdResult};
+use odbc_common::odbc_api::buffers::{AnyBuffer, ColumnarAnyBuffer, ColumnarBuffer};
+use odbc_common::odbc_api::{DataType, RowSetBuffer};
+use std::cell::{Cell, RefCell};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, ThreadId};
+use std::time::Duration;
+
+pub(crate) type Calls = Arc<Mutex<Vec<(String, ThreadId)>>>;
+
+/// Record every call with the thread it runs on.
+///
+/// `execute` sleeps for the millis given as sql, and fails when the sql starts with `fail`, see
+/// [`MockConnection::fail_on`].
+/// `query_stream` yields the number of rows given as sql in an `ID` column, and records every
+/// fetch of a row set.
+pub(crate) struct MockConnection {
+    calls: Calls,
+    options: Options,
+    isolation_level: Cell<Option<IsolationLevel>>,
+    access_mode: Cell<Option<AccessMode>>,
+    // `execute` fails for the sql with one of the prefixes
+    fail_on: RefCell<Vec<String>>,
+    // the text value `query` returns for the sql, see `MockConnection::query_returns`
+    values: RefCell<Vec<(String, String)>>,
+}
+
+impl MockConnection {
+    pub(crate) fn new() -> (Self, Calls) {
+        Self::with_database(SupportDatabase::Dameng)
+    }
+
+    pub(crate) fn with_database(database: SupportDatabase) -> (Self, Calls) {
+        Self::with_options(Options::new(database))
+    }
+
+    pub(crate) fn with_options(options: Options) -> (Self, Calls) {
+        let calls = Calls::default();
+        let conn = MockConnection {
+            calls: calls.clone(),
+            options,
+            isolation_level: Cell::new(None),
+            access_mode: Cell::new(None),
+            fail_on: RefCell::new(vec!["fail".to_string()]),
+            values: RefCell::new(vec![]),
+        };
+        (conn, calls)
+    }
+
+    /// Make `execute` fail for the sql starting with `prefix`.
+    pub(crate) fn fail_on(&self, prefix: &str) {
+        self.fail_on.borrow_mut().push(prefix.to_string());
+    }
+
+    /// Make `query` return a row with the text `value` for `sql`, the other sql return no row.
+    pub(crate) fn query_returns(&self, sql: &str, value: &str) {
+        self.values
+            .borrow_mut()
+            .push((sql.to_string(), value.to_string()));
+    }
+
+    fn record(&self, call: &str) -> OdbcStdResult<()> {
+        let id = thread::current().id();
+        self.calls.lock().unwrap().push((call.to_string(), id));
+        Ok(())
+    }
+}
+
+/// The row sets `0..rows` of `query_stream`, `batch_size` rows each.
+struct MockRowSets {
+    calls: Calls,
+    rows: i32,
+    batch_size: i32,
+    next: i32,
+    current: Option<ColumnarAnyBuffer>,
+}
+
+impl RowSets for MockRowSets {
+    fn fetch(&mut self) -> OdbcStdResult<Option<&ColumnarAnyBuffer>> {
+        let id = thread::current().id();
+        self.calls.lock().unwrap().push(("fetch".to_string(), id));
+        if self.next >= self.rows {
+            return Ok(None);
+        }
+        let end = self.rows.min(self.next + self.batch_size);
+        let mut row_set =
+            ColumnarBuffer::new(vec![(1, AnyBuffer::I32((self.next..end).collect()))]);
+        *row_set.mut_num_fetch_rows() = (end - self.next) as usize;
+        self.next = end;
+        Ok(Some(self.current.insert(row_set)))
+    }
+}
+
+pub(crate) fn call_names(calls: &Calls) -> Vec<String> {
+    calls.lock().unwrap().iter().map(|c| c.0.clone()).collect()
+}
+
+impl ConnectionTrait for MockConnection {
+    fn execute<S: StatementInput>(&self, stmt: S) -> OdbcStdResult<ExecResult> {
+        let sql = stmt.to_sql();
+        let millis: u64 = sql.parse().unwrap_or_default();
+        thread::sleep(Duration::from_millis(millis));
+        self.record(&format!("execute {sql}"))?;
+        if self
+            .fail_on
+            .borrow()
+            .iter()
+            .any(|p| sql.starts_with(p.as_str()))
+        {
+            return Err(format!("execute {sql} failed").into());
+        }
+        Ok(ExecResult { rows_affected: 1 })
+    }
+
+    fn query<S: StatementInput>(&self, stmt: S) -> OdbcStdResult<QueryResult> {
+        let sql = stmt.to_sql();
+        self.record(&format!("query {sql}"))?;
+        let values = self.values.borrow();
+        let data = values
+            .iter()
+            .filter(|(s, _)| s == sql)
+            .map(|(_, v)| {
+                let value = Some(OdbcColumnValue::Text(v.clone()));
+                vec![OdbcColumnItem::new(OdbcColumnType::Text, value)]
+            })
+            .collect();
+        Ok(QueryResult {
+            data,
+            ..QueryResult::default()
+        })
+    }
+
+    fn query_multi<S: StatementInput>(&self, stmt: S) -> OdbcStdResult<Vec<QueryResult>> {
+        let sql = stmt.to_sql();
+        self.record(&format!("query_multi {sql}"))?;
+        // one result set per statement
+        Ok(sql.split(';').map(|_| QueryResult::default()).collect())
+    }
+
+    fn query_stream<S: StatementInput>(&self, stmt: S) -> OdbcStdResult<QueryStream<'_>> {
+        let sql = stmt.to_sql();
+        self.record(&format!("query_stream {sql}"))?;
+        let row_sets = MockRowSets {
+            calls: self.calls.clone(),
+            rows: sql.parse().unwrap_or_default(),
+            batch_size: self.options.max_batch_size as i32,
+            next: 0,
+            current: None,
+        };
+        let columns = vec![OdbcColumnDesc::new(
+            "ID".to_string(),
+            DataType::Integer,
+            false,
+        )];
+        Ok(QueryStream::from_row_sets(
+            Box::new(row_sets),
+            columns,
+            &self.options,
+        ))
+    }
+
+    fn show_table<S: StatementInput>(&self, _stmt: S) -> OdbcStdResult<TableDescResult> {
+        Err("not supported".into())
+    }
+
+    fn bulk_insert<B>(&self, table: &str, rows: B) -> OdbcStdResult<usize>
+    where
+        B: TryConvert<BulkRows, Error = OdbcStdError>,
+    {
+        let bulk = rows.try_convert()?;
+        self.record(&format!("bulk_insert {table} {}", bulk.rows.len()))?;
+        Ok(bulk.rows.len())
+    }
+
+    fn call_procedure(
+        &self,
+        name: &str,
+        params: Vec<ProcedureParam>,
+    ) -> OdbcStdResult<ProcedureResult> {
+        self.record(&format!("call {name} {}", params.len()))?;
+        Ok(ProcedureResult::default())
+    }
+
+    fn begin(&self) -> OdbcStdResult<()> {
+        self.record("begin")
+    }
+
+    fn finish(&self) -> OdbcStdResult<()> {
+        self.record("finish")
+    }
+
+    fn commit(&self) -> OdbcStdResult<()> {
+        self.record("commit")
+    }
+
+    fn rollback(&self) -> OdbcStdResult<()> {
+        self.record("rollback")
+    }
+
+    fn set_isolation_level(&self, level: IsolationLevel) -> OdbcStdResult<()> {
+        self.isolation_level.set(Some(level));
+        self.record(&format!("isolation {level:?}"))
+    }
+
+    fn isolation_level(&self) -> OdbcStdResult<Option<IsolationLevel>> {
+        Ok(self.isolation_level.get())
+    }
+
+    fn set_access_mode(&self, mode: AccessMode) -> OdbcStdResult<()> {
+        self.access_mode.set(Some(mode));
+        self.record(&format!("access {mode:?}"))
+    }
+
+    fn access_mode(&self) -> OdbcStdResult<Option<AccessMode>> {
+        Ok(self.access_mode.get())
+    }
+
+    fn options(&self) -> Option<&Options> {
+        Some(&self.options)
+    }
+}
@@ -1,14 +1,209 @@
+#[cfg(feature = "async")]
+pub mod asynchronous;
 pub mod batch;
+pub mod bulk;
+pub mod cache;
+pub mod cancel;
+pub mod conversion;
 pub mod database;
+pub mod de;
 pub mod execute;
+pub(crate) mod handle;
+pub mod isolation;
+pub mod long_data;
+#[cfg(test)]
+pub(crate) mod mock;
+pub mod named;
+pub mod pool;
 pub mod prepare;
+pub mod procedure;
 pub mod query;
+pub mod retry;
 pub mod statement;
+pub mod stream;
 pub mod table;
+pub mod transaction;
+pub mod truncation;
 
-#[derive(Debug)]
+use crate::executor::isolation::TransactionOptions;
+use chrono::FixedOffset;
+use odbc_common::error::OdbcStdResult;
+use pg_helper::timetz::parse_offset;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SupportDatabase {
     Dameng,
     Pg,
     Mysql,
 }
+
+impl SupportDatabase {
+    /// The cheapest query to check whether a connection is still alive.
+    pub fn ping_sql(&self) -> &'static str {
+        match self {
+            SupportDatabase::Dameng => "SELECT 1 FROM DUAL",
+            SupportDatabase::Pg | SupportDatabase::Mysql => "SELECT 1",
+        }
+    }
+
+    pub fn savepoint_sql(&self, name: &str) -> String {
+        format!("SAVEPOINT {name}")
+    }
+
+    pub fn rollback_to_savepoint_sql(&self, name: &str) -> String {
+        format!("ROLLBACK TO SAVEPOINT {name}")
+    }
+
+    /// Dameng has no `RELEASE SAVEPOINT`, the savepoint is released by the end of transaction.
+    pub fn release_savepoint_sql(&self, name: &str) -> Option<String> {
+        match self {
+            SupportDatabase::Dameng => None,
+            SupportDatabase::Pg | SupportDatabase::Mysql => {
+                Some(format!("RELEASE SAVEPOINT {name}"))
+            }
+        }
+    }
+
+    /// Set the isolation level and access mode of the transaction which just began, it must run
+    /// before any other statement of the transaction. Dameng takes one setting per statement.
+    pub fn set_transaction_sql(&self, options: TransactionOptions) -> Vec<String> {
+        let settings: Vec<_> = options
+            .isolation_level
+            .map(|level| format!("ISOLATION LEVEL {}", level.sql()))
+            .into_iter()
+            .chain(options.access_mode.map(|mode| mode.sql().to_string()))
+            .collect();
+        match self {
+            SupportDatabase::Dameng => settings
+                .iter()
+                .map(|setting| format!("SET TRANSACTION {setting}"))
+                .collect(),
+            SupportDatabase::Pg | SupportDatabase::Mysql if !settings.is_empty() => {
+                vec![format!("SET TRANSACTION {}", settings.join(", "))]
+            }
+            SupportDatabase::Pg | SupportDatabase::Mysql => vec![],
+        }
+    }
+
+    /// Query the isolation level of the current transaction, read with
+    /// [`ConnectionTrait::current_isolation_level`]. MySQL reads the level of the session, which
+    /// a `SET TRANSACTION` of the next transaction doesn't change.
+    ///
+    /// [`ConnectionTrait::current_isolation_level`]: crate::executor::database::ConnectionTrait::current_isolation_level
+    pub fn isolation_level_sql(&self) -> &'static str {
+        match self {
+            SupportDatabase::Dameng => {
+                "SELECT ISOLATION FROM V$TRX WHERE ID = \
+                 (SELECT TRX_ID FROM V$SESSIONS WHERE SESS_ID = SESSID())"
+            }
+            SupportDatabase::Pg => "SHOW transaction_isolation",
+            SupportDatabase::Mysql => "SELECT @@transaction_isolation",
+        }
+    }
+
+    /// Query the access mode of the current transaction, see
+    /// [`SupportDatabase::isolation_level_sql`].
+    pub fn access_mode_sql(&self) -> &'static str {
+        match self {
+            SupportDatabase::Dameng => {
+                "SELECT READ_ONLY FROM V$TRX WHERE ID = \
+                 (SELECT TRX_ID FROM V$SESSIONS WHERE SESS_ID = SESSID())"
+            }
+            SupportDatabase::Pg => "SHOW transaction_read_only",
+            SupportDatabase::Mysql => "SELECT @@transaction_read_only",
+        }
+    }
+
+    /// Set the time zone of the session, in which a TIMESTAMP WITH LOCAL TIME ZONE is read and
+    /// written. A pg text offset is POSIX style, i.e. east is negative, so it's an interval.
+    pub fn set_time_zone_sql(&self, offset: FixedOffset) -> String {
+        match self {
+            SupportDatabase::Dameng => format!("SET TIME ZONE '{offset}'"),
+            SupportDatabase::Pg => format!("SET TIME ZONE INTERVAL '{offset}' HOUR TO MINUTE"),
+            SupportDatabase::Mysql => format!("SET time_zone = '{offset}'"),
+        }
+    }
+
+    /// Query the time zone of the session as a text UTC offset, e.g. `+08:00`. MySQL has no sign
+    /// for an offset east of UTC, e.g. `08:00`, see [`SupportDatabase::parse_time_zone`].
+    pub fn time_zone_sql(&self) -> &'static str {
+        match self {
+            SupportDatabase::Dameng => "SELECT SESSIONTIMEZONE FROM DUAL",
+            SupportDatabase::Pg => "SELECT to_char(now(), 'TZH:TZM')",
+            SupportDatabase::Mysql => {
+                "SELECT TIME_FORMAT(TIMEDIFF(NOW(), UTC_TIMESTAMP()), '%H:%i')"
+            }
+        }
+    }
+
+    /// Parse the offset queried by [`SupportDatabase::time_zone_sql`].
+    pub fn parse_time_zone(&self, offset: &str) -> OdbcStdResult<FixedOffset> {
+        let offset = offset.trim();
+        match self {
+            SupportDatabase::Mysql if !offset.starts_with('-') => {
+                parse_offset(&format!("+{offset}"))
+            }
+            _ => parse_offset(offset),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::isolation::IsolationLevel;
+
+    #[test]
+    fn test_parse_time_zone() {
+        let offset = |hours: i32, minutes: i32| FixedOffset::east_opt(hours * 3600 + minutes * 60);
+        assert_eq!(
+            SupportDatabase::Dameng.parse_time_zone("+08:00").ok(),
+            offset(8, 0)
+        );
+        assert_eq!(
+            SupportDatabase::Pg.parse_time_zone("-03:30").ok(),
+            offset(-3, -30)
+        );
+        assert!(SupportDatabase::Pg.parse_time_zone("08:00").is_err());
+        // `TIME_FORMAT` of `TIMEDIFF` signs only a negative offset
+        assert_eq!(
+            SupportDatabase::Mysql.parse_time_zone("08:00").ok(),
+            offset(8, 0)
+        );
+        assert_eq!(
+            SupportDatabase::Mysql.parse_time_zone("00:00").ok(),
+            offset(0, 0)
+        );
+        assert_eq!(
+            SupportDatabase::Mysql.parse_time_zone("-05:45").ok(),
+            offset(-5, -45)
+        );
+        assert!(SupportDatabase::Mysql.parse_time_zone("+-05:45").is_err());
+    }
+
+    #[test]
+    fn test_set_transaction_sql() {
+        let options = TransactionOptions::default()
+            .isolation_level(IsolationLevel::Serializable)
+            .read_only();
+        assert_eq!(
+            SupportDatabase::Dameng.set_transaction_sql(options),
+            [
+                "SET TRANSACTION ISOLATION LEVEL SERIALIZABLE",
+                "SET TRANSACTION READ ONLY"
+            ]
+        );
+        assert_eq!(
+            SupportDatabase::Pg.set_transaction_sql(options),
+            ["SET TRANSACTION ISOLATION LEVEL SERIALIZABLE, READ ONLY"]
+        );
+        let options = TransactionOptions::default().isolation_level(IsolationLevel::ReadCommitted);
+        assert_eq!(
+            SupportDatabase::Mysql.set_transaction_sql(options),
+            ["SET TRANSACTION ISOLATION LEVEL READ COMMITTED"]
+        );
+        assert!(SupportDatabase::Pg
+            .set_transaction_sql(TransactionOptions::default())
+            .is_empty());
+    }
+}
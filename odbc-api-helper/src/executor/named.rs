@@ -0,0 +1,296 @@
+//! Named placeholders in sql, see [`Statement::named`].
+//!
+//! `:name`, `@name` and `$1` placeholders are rewritten to `?`, the values are then bound in the
+//! order the placeholders appear. A `$1` placeholder is bound by the name `1`. Placeholders in
+//! string literals, quoted identifiers and comments are kept as is, and so is the Postgres `::`
+//! cast. A Postgres dollar quoted string, e.g. a function body `$$ ... $$`, and a MySQL
+//! backtick quoted identifier are kept too.
+//!
+//! An `@@name` system variable is never a placeholder. On MySQL, an `@name` is a user variable,
+//! use [`Statement::named_for`] with [`SupportDatabase::Mysql`] to keep it.
+//!
+//! [`Statement::named`]: crate::executor::statement::Statement::named
+//! [`Statement::named_for`]: crate::executor::statement::Statement::named_for
+use crate::executor::SupportDatabase;
+use odbc_common::error::{OdbcStdError, OdbcStdResult};
+use std::collections::{BTreeMap, HashMap};
+
+/// The values of named placeholders.
+pub trait NamedParams<T> {
+    fn into_named(self) -> Vec<(String, T)>;
+}
+
+impl<T> NamedParams<T> for Vec<(String, T)> {
+    fn into_named(self) -> Vec<(String, T)> {
+        self
+    }
+}
+
+impl<T> NamedParams<T> for Vec<(&str, T)> {
+    fn into_named(self) -> Vec<(String, T)> {
+        self.into_iter().map(|(k, v)| (k.to_string(), v)).collect()
+    }
+}
+
+impl<T> NamedParams<T> for HashMap<String, T> {
+    fn into_named(self) -> Vec<(String, T)> {
+        self.into_iter().collect()
+    }
+}
+
+impl<T> NamedParams<T> for BTreeMap<String, T> {
+    fn into_named(self) -> Vec<(String, T)> {
+        self.into_iter().collect()
+    }
+}
+
+/// Rewrite the named placeholders of `sql` to `?`, return the sql and the names in order. On
+/// MySQL an `@name` is a user variable and a backslash escapes a quote in a string.
+pub(crate) fn parse_named(
+    sql: &str,
+    database: Option<&SupportDatabase>,
+) -> OdbcStdResult<(String, Vec<String>)> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut rewritten = String::with_capacity(sql.len());
+    let mut names = vec![];
+    let mut positional = false;
+    let mut i = 0;
+
+    let mysql = matches!(database, Some(SupportDatabase::Mysql));
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    // a `$` or `@` right after a name is part of it, e.g. the Dameng view `V$TRX`
+    let after_ident = |i: usize| i > 0 && is_ident(chars[i - 1]);
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match c {
+            // string literal, quoted identifier or MySQL backtick identifier, a doubled quote is
+            // an escaped quote. A backslash escapes too in a pg `E'...'` string and on MySQL
+            '\'' | '"' | '`' => {
+                let backslash = c != '`'
+                    && (mysql
+                        || (c == '\''
+                            && i > 0
+                            && matches!(chars[i - 1], 'E' | 'e')
+                            && !after_ident(i - 1)));
+                let mut end = i + 1;
+                while end < chars.len() {
+                    if backslash && chars[end] == '\\' {
+                        end += 2;
+                        continue;
+                    }
+                    if chars[end] == c {
+                        if chars.get(end + 1) != Some(&c) {
+                            break;
+                        }
+                        end += 1;
+                    }
+                    end += 1;
+                }
+                let end = end.min(chars.len() - 1);
+                rewritten.extend(&chars[i..=end]);
+                i = end + 1;
+            }
+            // a pg dollar quoted string, e.g. the function body `$$ ... $$` or `$fn$ ... $fn$`
+            '$' if !after_ident(i) && dollar_tag_end(&chars, i).is_some() => {
+                let tag_end = dollar_tag_end(&chars, i).unwrap_or(i);
+                let tag = &chars[i..=tag_end];
+                let end = (tag_end + 1..chars.len())
+                    .find(|&j| chars[j..].starts_with(tag))
+                    .map_or(chars.len() - 1, |j| j + tag.len() - 1);
+                rewritten.extend(&chars[i..=end]);
+                i = end + 1;
+            }
+            '-' if next == Some('-') => {
+                let end = (i..chars.len())
+                    .find(|&j| chars[j] == '\n')
+                    .unwrap_or(chars.len() - 1);
+                rewritten.extend(&chars[i..=end]);
+                i = end + 1;
+            }
+            '/' if next == Some('*') => {
+                let end = (i + 2..chars.len())
+                    .find(|&j| chars[j - 1] == '*' && chars[j] == '/')
+                    .unwrap_or(chars.len() - 1);
+                rewritten.extend(&chars[i..=end]);
+                i = end + 1;
+            }
+            // `::` is a cast
+            ':' if next == Some(':') => {
+                rewritten.push_str("::");
+                i += 2;
+            }
+            // a system variable, e.g. `@@session.sql_mode`, or a MySQL user variable
+            '@' if next == Some('@') || mysql => {
+                let end = (i + 1..chars.len())
+                    .find(|&j| !is_ident(chars[j]) && chars[j] != '@')
+                    .unwrap_or(chars.len());
+                rewritten.extend(&chars[i..end]);
+                i = end;
+            }
+            ':' | '@' if next.is_some_and(|n| is_ident(n) && !n.is_ascii_digit()) => {
+                let end = (i + 1..chars.len())
+                    .find(|&j| !is_ident(chars[j]))
+                    .unwrap_or(chars.len());
+                names.push(chars[i + 1..end].iter().collect());
+                rewritten.push('?');
+                i = end;
+            }
+            '$' if !after_ident(i) && next.is_some_and(|n| n.is_ascii_digit()) => {
+                let end = (i + 1..chars.len())
+                    .find(|&j| !chars[j].is_ascii_digit())
+                    .unwrap_or(chars.len());
+                names.push(chars[i + 1..end].iter().collect());
+                rewritten.push('?');
+                i = end;
+            }
+            '?' => {
+                positional = true;
+                rewritten.push(c);
+                i += 1;
+            }
+            _ => {
+                rewritten.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    if positional && !names.is_empty() {
+        return Err(OdbcStdError::SqlParamsError(
+            "can't mix `?` with named parameters".to_string(),
+        ));
+    }
+    Ok((rewritten, names))
+}
+
+/// The index of the closing `$` of the dollar quote tag starting at `start`, e.g. `$$` or
+/// `$fn$`. A tag is a name which doesn't start with a digit, so `$1` isn't one.
+fn dollar_tag_end(chars: &[char], start: usize) -> Option<usize> {
+    match chars.get(start + 1) {
+        Some('$') => Some(start + 1),
+        Some(c) if c.is_alphabetic() || *c == '_' => (start + 2..chars.len())
+            .find(|&j| !(chars[j].is_alphanumeric() || chars[j] == '_'))
+            .filter(|&j| chars[j] == '$'),
+        _ => None,
+    }
+}
+
+/// Order the values by `names`, a value used by several placeholders is cloned.
+pub(crate) fn bind_named<T: Clone>(
+    names: &[String],
+    params: Vec<(String, T)>,
+) -> OdbcStdResult<Vec<T>> {
+    let mut values: HashMap<String, T> = HashMap::with_capacity(params.len());
+    for (name, value) in params {
+        if values.insert(name.clone(), value).is_some() {
+            return Err(OdbcStdError::SqlParamsError(format!(
+                "duplicate parameter `{name}`"
+            )));
+        }
+    }
+
+    let mut bound = Vec::with_capacity(names.len());
+    for name in names {
+        let value = values
+            .get(name)
+            .ok_or_else(|| OdbcStdError::SqlParamsError(format!("missing parameter `{name}`")))?;
+        bound.push(value.clone());
+    }
+
+    let mut unused: Vec<&String> = values.keys().filter(|k| !names.contains(k)).collect();
+    if !unused.is_empty() {
+        unused.sort();
+        return Err(OdbcStdError::SqlParamsError(format!(
+            "unused parameter {}",
+            unused
+                .iter()
+                .map(|k| format!("`{k}`"))
+                .collect::<Vec<_>>()
+                .join(",")
+        )));
+    }
+    Ok(bound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_named() {
+        let (sql, names) =
+            parse_named("SELECT * FROM T WHERE A = :a AND B = @b_1 OR A = :a", None).unwrap();
+        assert_eq!(sql, "SELECT * FROM T WHERE A = ? AND B = ? OR A = ?");
+        assert_eq!(names, vec!["a", "b_1", "a"]);
+
+        let (sql, names) = parse_named("UPDATE T SET A = $2 WHERE ID = $1", None).unwrap();
+        assert_eq!(sql, "UPDATE T SET A = ? WHERE ID = ?");
+        assert_eq!(names, vec!["2", "1"]);
+
+        let (sql, names) = parse_named(
+            "SELECT ':a', \"@b\", 'it''s :c', A::text, '12:30' -- :d\n/* @e */ FROM T WHERE A = :f",
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT ':a', \"@b\", 'it''s :c', A::text, '12:30' -- :d\n/* @e */ FROM T WHERE A = ?"
+        );
+        assert_eq!(names, vec!["f"]);
+
+        assert!(parse_named("SELECT ? FROM T WHERE A = :a", None).is_err());
+
+        // a dollar quoted function body keeps its own parameters
+        let body = "CREATE FUNCTION F(INT) RETURNS INT AS $$ SELECT $1 + :a $$ LANGUAGE SQL";
+        let (sql, names) = parse_named(body, None).unwrap();
+        assert_eq!(sql, body);
+        assert!(names.is_empty());
+        let (sql, names) = parse_named("SELECT $fn$ it's $1 $$ :a $fn$, :b, $2", None).unwrap();
+        assert_eq!(sql, "SELECT $fn$ it's $1 $$ :a $fn$, ?, ?");
+        assert_eq!(names, vec!["b", "2"]);
+
+        // a `$` inside a name, e.g. a Dameng view
+        let (sql, names) = parse_named("SELECT * FROM V$TRX WHERE ID = $1", None).unwrap();
+        assert_eq!(sql, "SELECT * FROM V$TRX WHERE ID = ?");
+        assert_eq!(names, vec!["1"]);
+
+        // a backslash escaped quote of a pg escape string
+        let (sql, names) =
+            parse_named(r"SELECT E'it\'s :a', 'c:\' FROM T WHERE A = :b", None).unwrap();
+        assert_eq!(sql, r"SELECT E'it\'s :a', 'c:\' FROM T WHERE A = ?");
+        assert_eq!(names, vec!["b"]);
+
+        // a MySQL backtick identifier
+        let (sql, names) = parse_named("SELECT `:a`, `x``@b` FROM T WHERE A = :c", None).unwrap();
+        assert_eq!(sql, "SELECT `:a`, `x``@b` FROM T WHERE A = ?");
+        assert_eq!(names, vec!["c"]);
+
+        // a system variable is kept, a MySQL user variable too
+        let (sql, names) = parse_named("SELECT @@session.sql_mode, @a", None).unwrap();
+        assert_eq!(sql, "SELECT @@session.sql_mode, ?");
+        assert_eq!(names, vec!["a"]);
+        let mysql = Some(&SupportDatabase::Mysql);
+        let (sql, names) =
+            parse_named(r"SET @total = :a; SELECT @total, 'it\'s :b' FROM T", mysql).unwrap();
+        assert_eq!(sql, r"SET @total = ?; SELECT @total, 'it\'s :b' FROM T");
+        assert_eq!(names, vec!["a"]);
+    }
+
+    #[test]
+    fn test_bind_named() {
+        let names = vec!["a".to_string(), "b".to_string(), "a".to_string()];
+        let values = bind_named(&names, vec![("b", 2), ("a", 1)].into_named()).unwrap();
+        assert_eq!(values, vec![1, 2, 1]);
+
+        let err = bind_named(&names, vec![("a", 1)].into_named()).unwrap_err();
+        assert!(err.to_string().contains("missing parameter `b`"));
+
+        let err = bind_named(&names, vec![("a", 1), ("b", 2), ("c", 3)].into_named()).unwrap_err();
+        assert!(err.to_string().contains("unused parameter `c`"));
+
+        let err = bind_named(&names, vec![("a", 1), ("a", 2)].into_named()).unwrap_err();
+        assert!(err.to_string().contains("duplicate parameter `a`"));
+    }
+}
@@ -0,0 +1,655 @@
+//! A blocking connection pool of [`OdbcDbConnection`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use odbc_api_helper::executor::database::{ConnectionTrait, Options};
+//! use odbc_api_helper::executor::pool::{OdbcPool, PoolOptions};
+//! use odbc_api_helper::executor::SupportDatabase;
+//! use odbc_api_helper::odbc_common::odbc_api::Environment;
+//!
+//! let env = Environment::new().unwrap();
+//! let pool = OdbcPool::new(
+//!     &env,
+//!     "Driver={DM8};Server=127.0.0.1;UID=SYSDBA;PWD=SYSDBA;",
+//!     Options::new(SupportDatabase::Dameng),
+//!     PoolOptions::default(),
+//! );
+//! let conn = pool.get().unwrap();
+//! let result = conn.query("SELECT * FROM SYSCOLUMNS").unwrap();
+//! println!("{:?}", result);
+//! ```
+use crate::executor::bulk::BulkRows;
+use crate::executor::database::{ConnectionTrait, OdbcDbConnection, Options};
+use crate::executor::execute::ExecResult;
+use crate::executor::isolation::{AccessMode, IsolationLevel, TransactionOptions};
+use crate::executor::procedure::{ProcedureParam, ProcedureResult};
+use crate::executor::query::QueryResult;
+use crate::executor::statement::StatementInput;
+use crate::executor::stream::QueryStream;
+use crate::executor::table::TableDescResult;
+use crate::TryConvert;
+use odbc_common::error::{OdbcStdError, OdbcStdResult};
+use odbc_common::odbc_api::force_send_sync;
+use odbc_common::odbc_api::Environment;
+use odbc_common::state::OdbcState;
+use std::cell::Cell;
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone)]
+pub struct PoolOptions {
+    /// The maximum number of connections, idle and checked out.
+    pub max_size: usize,
+    /// Close a connection which has been idle for longer, `None` keeps it forever.
+    pub idle_timeout: Option<Duration>,
+    /// Close a connection which has been open for longer, `None` keeps it forever.
+    pub max_lifetime: Option<Duration>,
+    /// How long `OdbcPool::get` waits for a connection when the pool is full.
+    pub checkout_timeout: Duration,
+    /// Run the ping query on checkout and discard the connection if it fails.
+    pub test_on_checkout: bool,
+    /// The ping query, default is `SupportDatabase::ping_sql`.
+    pub ping_sql: Option<String>,
+}
+
+impl Default for PoolOptions {
+    fn default() -> Self {
+        PoolOptions {
+            max_size: 10,
+            idle_timeout: Some(Duration::from_secs(10 * 60)),
+            max_lifetime: Some(Duration::from_secs(30 * 60)),
+            checkout_timeout: Duration::from_secs(30),
+            test_on_checkout: true,
+            ping_sql: None,
+        }
+    }
+}
+
+/// Open, check and reset the connections of a [`Pool`].
+trait Manage {
+    type Connection: Send;
+
+    fn connect(&self) -> OdbcStdResult<Self::Connection>;
+
+    fn ping(&self, conn: &Self::Connection) -> OdbcStdResult<()>;
+
+    /// Make a returned connection ready for the next checkout.
+    fn reset(&self, conn: &Self::Connection) -> OdbcStdResult<()>;
+}
+
+struct OdbcManager<'env> {
+    env: &'env Environment,
+    connection_string: String,
+    options: Options,
+    ping_sql: String,
+}
+
+type SendConnection<'env> = force_send_sync::Send<OdbcDbConnection<'env>>;
+
+impl<'env> Manage for OdbcManager<'env> {
+    type Connection = SendConnection<'env>;
+
+    fn connect(&self) -> OdbcStdResult<Self::Connection> {
+        let conn = self
+            .env
+            .connect_with_connection_string(&self.connection_string)?;
+        let conn = OdbcDbConnection::new(conn, self.options.clone())?;
+        // SAFETY: `OdbcDbConnection` isn't `Send` for its ODBC handles and its `Cell`/`RefCell`
+        // fields. The ODBC standard allows a connection and its statements to be used from
+        // another thread as long as one thread at a time uses them, see
+        // `Connection::promote_to_send`. The pool keeps it that way: an idle connection is only
+        // moved in and out of the idle list under the pool lock, and a checked out connection is
+        // owned by a `PooledConnection`, which isn't `Send`. The cells aren't shared with any
+        // other value, so moving them along is fine.
+        Ok(unsafe { force_send_sync::Send::new(conn) })
+    }
+
+    fn ping(&self, conn: &Self::Connection) -> OdbcStdResult<()> {
+        conn.connection().execute(&self.ping_sql, ())?;
+        Ok(())
+    }
+
+    fn reset(&self, conn: &Self::Connection) -> OdbcStdResult<()> {
+        conn.reset_session()
+    }
+}
+
+struct IdleConnection<C> {
+    conn: C,
+    created_at: Instant,
+    idle_since: Instant,
+}
+
+struct PoolState<C> {
+    // the most recently returned connection is checked out first, the others age out by
+    // `PoolOptions.idle_timeout`
+    idle: Vec<IdleConnection<C>>,
+    // idle and checked out connections
+    size: usize,
+}
+
+/// The checkout and check-in of the connections of a `Manage`.
+struct Pool<M: Manage> {
+    manager: M,
+    options: PoolOptions,
+    state: Mutex<PoolState<M::Connection>>,
+    available: Condvar,
+}
+
+impl<M: Manage> Pool<M> {
+    fn new(manager: M, options: PoolOptions) -> Self {
+        Pool {
+            manager,
+            options,
+            state: Mutex::new(PoolState {
+                idle: vec![],
+                size: 0,
+            }),
+            available: Condvar::new(),
+        }
+    }
+
+    /// Check out a connection with its creation time, see [`OdbcPool::get`].
+    fn checkout(&self) -> OdbcStdResult<(M::Connection, Instant)> {
+        let deadline = Instant::now() + self.options.checkout_timeout;
+        let mut state = self.lock();
+        loop {
+            let expired = self.take_expired(&mut state);
+            if !expired.is_empty() {
+                drop(state);
+                self.discard(expired);
+                state = self.lock();
+            }
+
+            if let Some(idle) = state.idle.pop() {
+                drop(state);
+                if !self.options.test_on_checkout || self.manager.ping(&idle.conn).is_ok() {
+                    return Ok((idle.conn, idle.created_at));
+                }
+                warn!("discard odbc connection which failed the ping query");
+                self.discard(vec![idle.conn]);
+                state = self.lock();
+                continue;
+            }
+
+            if state.size < self.options.max_size {
+                state.size += 1;
+                drop(state);
+                return match self.manager.connect() {
+                    Ok(conn) => Ok((conn, Instant::now())),
+                    Err(e) => {
+                        self.release_slots(1);
+                        Err(e)
+                    }
+                };
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(OdbcStdError::StringError(format!(
+                    "timed out after {:?} waiting for a pooled connection",
+                    self.options.checkout_timeout
+                )));
+            }
+            state = self
+                .available
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+
+    /// Return a checked out connection. It's discarded when it's broken, too old or can't be
+    /// reset, e.g. the rollback of an open transaction fails.
+    fn checkin(&self, conn: M::Connection, created_at: Instant, broken: bool) {
+        if broken || self.is_too_old(created_at) {
+            self.discard(vec![conn]);
+            return;
+        }
+        if let Err(e) = self.manager.reset(&conn) {
+            warn!("discard odbc connection which failed to reset:{}", e);
+            self.discard(vec![conn]);
+            return;
+        }
+        let mut state = self.lock();
+        state.idle.push(IdleConnection {
+            conn,
+            created_at,
+            idle_since: Instant::now(),
+        });
+        let expired = self.take_expired(&mut state);
+        drop(state);
+        self.available.notify_one();
+        if !expired.is_empty() {
+            self.discard(expired);
+        }
+    }
+
+    /// Remove the expired idle connections, they are closed by `discard` out of the lock.
+    fn take_expired(&self, state: &mut PoolState<M::Connection>) -> Vec<M::Connection> {
+        let (expired, idle) = std::mem::take(&mut state.idle)
+            .into_iter()
+            .partition::<Vec<_>, _>(|idle| self.is_expired(idle));
+        state.idle = idle;
+        expired.into_iter().map(|idle| idle.conn).collect()
+    }
+
+    /// Close the connections and free their slots.
+    fn discard(&self, conns: Vec<M::Connection>) {
+        let slots = conns.len();
+        drop(conns);
+        self.release_slots(slots);
+    }
+
+    fn release_slots(&self, slots: usize) {
+        self.lock().size -= slots;
+        self.available.notify_all();
+    }
+
+    fn is_expired(&self, idle: &IdleConnection<M::Connection>) -> bool {
+        let idle_timeout = self
+            .options
+            .idle_timeout
+            .is_some_and(|t| idle.idle_since.elapsed() >= t);
+        idle_timeout || self.is_too_old(idle.created_at)
+    }
+
+    fn is_too_old(&self, created_at: Instant) -> bool {
+        self.options
+            .max_lifetime
+            .is_some_and(|t| created_at.elapsed() >= t)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PoolState<M::Connection>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+pub struct OdbcPool<'env> {
+    pool: Pool<OdbcManager<'env>>,
+}
+
+impl<'env> Debug for OdbcPool<'env> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let state = self.pool.lock();
+        f.debug_struct("OdbcPool")
+            .field("options", &self.pool.manager.options)
+            .field("pool_options", &self.pool.options)
+            .field("size", &state.size)
+            .field("idle", &state.idle.len())
+            .finish()
+    }
+}
+
+impl<'env> OdbcPool<'env> {
+    /// Create an empty pool, the connections are opened lazily by [`OdbcPool::get`].
+    pub fn new<S: Into<String>>(
+        env: &'env Environment,
+        connection_string: S,
+        options: Options,
+        pool_options: PoolOptions,
+    ) -> Self {
+        let ping_sql = match &pool_options.ping_sql {
+            Some(sql) => sql.clone(),
+            None => options.database.ping_sql().to_string(),
+        };
+        let manager = OdbcManager {
+            env,
+            connection_string: connection_string.into(),
+            options,
+            ping_sql,
+        };
+        OdbcPool {
+            pool: Pool::new(manager, pool_options),
+        }
+    }
+
+    /// The number of idle and checked out connections.
+    pub fn size(&self) -> usize {
+        self.pool.lock().size
+    }
+
+    pub fn idle(&self) -> usize {
+        self.pool.lock().idle.len()
+    }
+
+    /// Check out a connection: reuse a healthy idle connection, or open a new one when the pool
+    /// isn't full, or wait up to `PoolOptions.checkout_timeout` for a connection to be returned.
+    ///
+    /// The expired idle connections are closed on checkout and on check-in.
+    pub fn get(&self) -> OdbcStdResult<PooledConnection<'_, 'env>> {
+        let (conn, created_at) = self.pool.checkout()?;
+        Ok(PooledConnection {
+            pool: &self.pool,
+            conn: Some(conn),
+            created_at,
+            broken: Cell::new(false),
+            _not_send: PhantomData,
+        })
+    }
+}
+
+/// Whether the error means the connection to the database is lost.
+pub fn is_broken_error(error: &OdbcStdError) -> bool {
+    error.odbc_state() == Some(OdbcState::STMT_COMMUNICATION_ERROR)
+}
+
+/// A checked out connection, it's returned to the pool on drop.
+///
+/// On return, an open transaction is rolled back, autocommit is restored and so are the
+/// isolation level and access mode of `Options`, and its cancel handles are invalidated. A
+/// connection which fails to reset is discarded.
+///
+/// The `ConnectionTrait` methods mark the connection broken when the error maps to
+/// `OdbcState::STMT_COMMUNICATION_ERROR`, and a broken connection is discarded instead of being
+/// returned. Use [`PooledConnection::mark_broken`] when the error comes from the underlying
+/// `OdbcDbConnection` directly.
+pub struct PooledConnection<'p, 'env> {
+    pool: &'p Pool<OdbcManager<'env>>,
+    conn: Option<SendConnection<'env>>,
+    created_at: Instant,
+    broken: Cell<bool>,
+    // used and returned by the thread which checked it out, see `OdbcManager::connect`
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<'p, 'env> Debug for PooledConnection<'p, 'env> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PooledConnection")
+            .field("created_at", &self.created_at)
+            .field("broken", &self.broken.get())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'p, 'env> PooledConnection<'p, 'env> {
+    /// Discard the connection instead of returning it to the pool.
+    pub fn mark_broken(&self) {
+        self.broken.set(true);
+    }
+
+    pub fn is_broken(&self) -> bool {
+        self.broken.get()
+    }
+
+    fn check<T>(&self, result: OdbcStdResult<T>) -> OdbcStdResult<T> {
+        if let Err(e) = &result {
+            if is_broken_error(e) {
+                warn!("odbc connection is broken:{}", e);
+                self.mark_broken();
+            }
+        }
+        result
+    }
+}
+
+impl<'p, 'env> Deref for PooledConnection<'p, 'env> {
+    type Target = OdbcDbConnection<'env>;
+
+    fn deref(&self) -> &Self::Target {
+        self.conn
+            .as_deref()
+            .expect("connection is taken on drop only")
+    }
+}
+
+impl<'p, 'env> Drop for PooledConnection<'p, 'env> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool.checkin(conn, self.created_at, self.broken.get());
+        }
+    }
+}
+
+impl<'p, 'env> ConnectionTrait for PooledConnection<'p, 'env> {
+    fn execute<S>(&self, stmt: S) -> OdbcStdResult<ExecResult>
+    where
+        S: StatementInput,
+    {
+        self.check((**self).execute(stmt))
+    }
+
+    fn query<S>(&self, stmt: S) -> OdbcStdResult<QueryResult>
+    where
+        S: StatementInput,
+    {
+        self.check((**self).query(stmt))
+    }
+
+    fn query_multi<S>(&self, stmt: S) -> OdbcStdResult<Vec<QueryResult>>
+    where
+        S: StatementInput,
+    {
+        self.check((**self).query_multi(stmt))
+    }
+
+    fn query_stream<S>(&self, stmt: S) -> OdbcStdResult<QueryStream<'_>>
+    where
+        S: StatementInput,
+    {
+        self.check((**self).query_stream(stmt))
+    }
+
+    fn show_table<S>(&self, stmt: S) -> OdbcStdResult<TableDescResult>
+    where
+        S: StatementInput,
+    {
+        self.check((**self).show_table(stmt))
+    }
+
+    fn bulk_insert<B>(&self, table: &str, rows: B) -> OdbcStdResult<usize>
+    where
+        B: TryConvert<BulkRows, Error = OdbcStdError>,
+    {
+        self.check((**self).bulk_insert(table, rows))
+    }
+
+    fn call_procedure(
+        &self,
+        name: &str,
+        params: Vec<ProcedureParam>,
+    ) -> OdbcStdResult<ProcedureResult> {
+        self.check((**self).call_procedure(name, params))
+    }
+
+    fn begin(&self) -> OdbcStdResult<()> {
+        self.check((**self).begin())
+    }
+
+    fn begin_with(&self, options: TransactionOptions) -> OdbcStdResult<()> {
+        self.check((**self).begin_with(options))
+    }
+
+    fn finish(&self) -> OdbcStdResult<()> {
+        self.check((**self).finish())
+    }
+
+    fn commit(&self) -> OdbcStdResult<()> {
+        self.check((**self).commit())
+    }
+
+    fn rollback(&self) -> OdbcStdResult<()> {
+        self.check((**self).rollback())
+    }
+
+    fn set_isolation_level(&self, level: IsolationLevel) -> OdbcStdResult<()> {
+        self.check((**self).set_isolation_level(level))
+    }
+
+    fn isolation_level(&self) -> OdbcStdResult<Option<IsolationLevel>> {
+        self.check((**self).isolation_level())
+    }
+
+    fn set_access_mode(&self, mode: AccessMode) -> OdbcStdResult<()> {
+        self.check((**self).set_access_mode(mode))
+    }
+
+    fn access_mode(&self) -> OdbcStdResult<Option<AccessMode>> {
+        self.check((**self).access_mode())
+    }
+
+    fn options(&self) -> Option<&Options> {
+        (**self).options()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::SupportDatabase;
+    use odbc_common::error::odbc_error::{OdbcWrapperError, StatementError};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::mpsc;
+    use std::thread;
+
+    fn statement_error(state: &str) -> OdbcStdError {
+        OdbcStdError::OdbcError(OdbcWrapperError::StatementError(StatementError {
+            state: state.to_string(),
+            error_msg: "".to_string(),
+        }))
+    }
+
+    #[test]
+    fn test_is_broken_error() {
+        assert!(is_broken_error(&statement_error("08S01")));
+        assert!(!is_broken_error(&statement_error("HY000")));
+        assert!(!is_broken_error(&OdbcStdError::StringError("08S01".into())));
+    }
+
+    /// Count the connects and resets, the connections are numbered from 1.
+    #[derive(Default)]
+    struct MockManager {
+        connects: AtomicUsize,
+        resets: AtomicUsize,
+        fail_ping: AtomicBool,
+        fail_reset: AtomicBool,
+    }
+
+    impl Manage for MockManager {
+        type Connection = usize;
+
+        fn connect(&self) -> OdbcStdResult<usize> {
+            Ok(self.connects.fetch_add(1, Ordering::SeqCst) + 1)
+        }
+
+        fn ping(&self, _conn: &usize) -> OdbcStdResult<()> {
+            match self.fail_ping.load(Ordering::SeqCst) {
+                true => Err(statement_error("08S01")),
+                false => Ok(()),
+            }
+        }
+
+        fn reset(&self, _conn: &usize) -> OdbcStdResult<()> {
+            self.resets.fetch_add(1, Ordering::SeqCst);
+            match self.fail_reset.load(Ordering::SeqCst) {
+                true => Err(statement_error("25000")),
+                false => Ok(()),
+            }
+        }
+    }
+
+    fn mock_pool(max_size: usize, checkout_timeout: Duration) -> Pool<MockManager> {
+        let options = PoolOptions {
+            max_size,
+            checkout_timeout,
+            ..PoolOptions::default()
+        };
+        Pool::new(MockManager::default(), options)
+    }
+
+    #[test]
+    fn test_checkout_blocks_at_max_size() {
+        let pool = &mock_pool(1, Duration::from_secs(5));
+        let (conn, created_at) = pool.checkout().unwrap();
+        thread::scope(|scope| {
+            let (sender, receiver) = mpsc::channel();
+            scope.spawn(move || sender.send(pool.checkout().unwrap().0).unwrap());
+            assert!(receiver.recv_timeout(Duration::from_millis(50)).is_err());
+
+            pool.checkin(conn, created_at, false);
+            assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(1));
+        });
+        assert_eq!(pool.manager.connects.load(Ordering::SeqCst), 1);
+        assert_eq!(pool.lock().size, 1);
+    }
+
+    #[test]
+    fn test_checkout_timeout() {
+        let pool = mock_pool(1, Duration::from_millis(20));
+        let _conn = pool.checkout().unwrap();
+        let start = Instant::now();
+        let err = pool.checkout().unwrap_err();
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert!(err.to_string().contains("timed out"), "{err}");
+    }
+
+    #[test]
+    fn test_checkin_resets_connection() {
+        let pool = mock_pool(2, Duration::from_secs(1));
+        let (conn, created_at) = pool.checkout().unwrap();
+        pool.checkin(conn, created_at, false);
+        assert_eq!(pool.manager.resets.load(Ordering::SeqCst), 1);
+        assert_eq!(pool.lock().idle.len(), 1);
+        assert_eq!(pool.checkout().unwrap().0, 1);
+    }
+
+    #[test]
+    fn test_discard_on_error() {
+        let pool = mock_pool(2, Duration::from_secs(1));
+
+        // a broken connection isn't reset
+        let (conn, created_at) = pool.checkout().unwrap();
+        pool.checkin(conn, created_at, true);
+        assert_eq!(pool.manager.resets.load(Ordering::SeqCst), 0);
+        assert_eq!(pool.lock().size, 0);
+
+        pool.manager.fail_reset.store(true, Ordering::SeqCst);
+        let (conn, created_at) = pool.checkout().unwrap();
+        pool.checkin(conn, created_at, false);
+        assert_eq!(pool.lock().size, 0);
+        assert_eq!(pool.lock().idle.len(), 0);
+
+        pool.manager.fail_reset.store(false, Ordering::SeqCst);
+        let (conn, created_at) = pool.checkout().unwrap();
+        pool.checkin(conn, created_at, false);
+        pool.manager.fail_ping.store(true, Ordering::SeqCst);
+        assert_eq!(pool.checkout().unwrap().0, 4);
+        assert_eq!(pool.lock().size, 1);
+    }
+
+    #[test]
+    fn test_idle_connections_age_out() {
+        let mut pool = mock_pool(2, Duration::from_secs(1));
+        pool.options.idle_timeout = Some(Duration::from_millis(40));
+        let (first, first_created) = pool.checkout().unwrap();
+        let (second, second_created) = pool.checkout().unwrap();
+        pool.checkin(first, first_created, false);
+        pool.checkin(second, second_created, false);
+
+        // the most recently returned connection is reused, the other one ages out
+        for _ in 0..5 {
+            thread::sleep(Duration::from_millis(15));
+            let (conn, created_at) = pool.checkout().unwrap();
+            assert_eq!(conn, 2);
+            pool.checkin(conn, created_at, false);
+        }
+        assert_eq!(pool.lock().size, 1);
+        assert_eq!(pool.lock().idle.len(), 1);
+    }
+
+    #[test]
+    fn test_pool_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<OdbcPool<'static>>();
+    }
+
+    #[test]
+    fn test_ping_sql() {
+        assert_eq!(SupportDatabase::Dameng.ping_sql(), "SELECT 1 FROM DUAL");
+        assert_eq!(SupportDatabase::Pg.ping_sql(), "SELECT 1");
+    }
+}
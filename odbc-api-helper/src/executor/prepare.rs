@@ -1,11 +1,110 @@
+use crate::executor::database::Options;
+use crate::executor::execute::ExecResult;
+use crate::executor::query::QueryResult;
+use crate::executor::statement::SqlValue;
+use crate::executor::stream::fetch_query_result;
 use crate::extension::odbc::{OdbcColumnDesc, OdbcParamDesc};
-use odbc_common::odbc_api::Prepared;
+use odbc_common::error::{OdbcStdError, OdbcStdResult, OdbcWrapperError};
+use odbc_common::odbc_api::handles::AsStatementRef;
+use odbc_common::odbc_api::parameter::InputParameter;
+use odbc_common::odbc_api::{DataType, Prepared};
 
+/// The kind of value a parameter holds, to check it against the parameter describe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamKind {
+    Integer,
+    Float,
+    // an exact decimal, bound as text
+    Decimal,
+    Bool,
+    Text,
+    Binary,
+    Date,
+    Time,
+    Timestamp,
+}
+
+impl ParamKind {
+    /// Whether the value can be bound to a parameter of `data_type`.
+    /// Text is accepted by every type and every value by a character type, the driver converts it.
+    pub fn accepts(self, data_type: &DataType) -> bool {
+        matches!(
+            (self, data_type),
+            (_, DataType::Unknown | DataType::Other { .. })
+                | (
+                    _,
+                    DataType::Char { .. }
+                        | DataType::WChar { .. }
+                        | DataType::Varchar { .. }
+                        | DataType::WVarchar { .. }
+                        | DataType::LongVarchar { .. },
+                )
+                | (ParamKind::Text, _)
+                | (
+                    ParamKind::Integer | ParamKind::Float | ParamKind::Decimal | ParamKind::Bool,
+                    DataType::TinyInt
+                        | DataType::SmallInt
+                        | DataType::Integer
+                        | DataType::BigInt
+                        | DataType::Numeric { .. }
+                        | DataType::Decimal { .. }
+                        | DataType::Float { .. }
+                        | DataType::Real
+                        | DataType::Double
+                        | DataType::Bit,
+                )
+                | (
+                    ParamKind::Binary,
+                    DataType::Binary { .. }
+                        | DataType::Varbinary { .. }
+                        | DataType::LongVarbinary { .. },
+                )
+                | (ParamKind::Date, DataType::Date | DataType::Timestamp { .. })
+                | (ParamKind::Time, DataType::Time { .. })
+                | (
+                    ParamKind::Timestamp,
+                    DataType::Timestamp { .. } | DataType::Date
+                )
+        )
+    }
+}
+
+/// A parameter value which can be checked against `params_desc` before execution.
+pub trait CheckParam {
+    fn param_kind(&self) -> ParamKind;
+
+    fn is_null(&self) -> bool;
+}
+
+/// A prepared statement from [`OdbcDbConnection::prepare`], which can be executed many times.
+///
+/// The parameters are checked against the parameter describe before they're sent to the driver.
+///
+/// # Example
+///
+/// ```no_run
+/// use odbc_api_helper::executor::database::OdbcDbConnection;
+/// use odbc_api_helper::extension::pg::PgValueInput;
+/// use odbc_api_helper::odbc_common::error::OdbcStdResult;
+///
+/// fn insert(conn: &OdbcDbConnection, names: &[&str]) -> OdbcStdResult<usize> {
+///     let mut prepared = conn.prepare("INSERT INTO PERSON (NAME) VALUES (?)")?;
+///     let mut rows_affected = 0;
+///     for name in names {
+///         let params = vec![PgValueInput::Varchar(Some(name.to_string()))];
+///         rows_affected += prepared.execute(params)?.rows_affected;
+///     }
+///     Ok(rows_affected)
+/// }
+/// ```
+///
+/// [`OdbcDbConnection::prepare`]: crate::executor::database::OdbcDbConnection::prepare
 #[allow(missing_debug_implementations)]
 pub struct OdbcPrepared<S> {
     pub prepared: Prepared<S>,
     pub result_cols_desc: Vec<OdbcColumnDesc>,
     pub params_desc: Vec<OdbcParamDesc>,
+    options: Options,
 }
 
 impl<S> OdbcPrepared<S> {
@@ -22,11 +121,136 @@ impl<S> OdbcPrepared<S> {
         prepared: Prepared<S>,
         result_cols_des: Vec<OdbcColumnDesc>,
         params_des: Vec<OdbcParamDesc>,
+        options: Options,
     ) -> Self {
         Self {
             prepared,
             result_cols_desc: result_cols_des,
             params_desc: params_des,
+            options,
         }
     }
+
+    /// Check the parameter count and types against `params_desc`.
+    pub fn check_params<T: CheckParam>(&self, params: &[T]) -> OdbcStdResult<()> {
+        check_params(&self.params_desc, params)
+    }
+}
+
+impl<S: AsStatementRef> OdbcPrepared<S> {
+    /// Execute the prepared statement, e.g. INSERT,UPDATE,DELETE.
+    pub fn execute<T>(&mut self, params: Vec<T>) -> OdbcStdResult<ExecResult>
+    where
+        T: SqlValue + CheckParam,
+    {
+        let params = self.input_params(params)?;
+        self.prepared.execute(&params[..])?;
+        let result = self
+            .prepared
+            .row_count()?
+            .map(|r| ExecResult { rows_affected: r })
+            .unwrap_or_default();
+        Ok(result)
+    }
+
+    /// Execute the prepared statement and fetch all rows.
+    pub fn query<T>(&mut self, params: Vec<T>) -> OdbcStdResult<QueryResult>
+    where
+        T: SqlValue + CheckParam,
+    {
+        let params = self.input_params(params)?;
+        let cursor = self.prepared.execute(&params[..])?.ok_or_else(|| {
+            OdbcStdError::OdbcError(OdbcWrapperError::DataHandlerError(
+                "query error".to_string(),
+            ))
+        })?;
+        fetch_query_result(cursor, self.result_cols_desc.clone(), &self.options)
+    }
+
+    fn input_params<T>(&self, params: Vec<T>) -> OdbcStdResult<Vec<Box<dyn InputParameter>>>
+    where
+        T: SqlValue + CheckParam,
+    {
+        self.check_params(&params)?;
+        params
+            .into_iter()
+            .map(|v| {
+                v.to_value().left().ok_or_else(|| {
+                    OdbcStdError::SqlParamsError("value not include empty tuple".into())
+                })
+            })
+            .collect()
+    }
+}
+
+fn check_params<T: CheckParam>(params_desc: &[OdbcParamDesc], params: &[T]) -> OdbcStdResult<()> {
+    if params.len() != params_desc.len() {
+        return Err(OdbcStdError::SqlParamsError(format!(
+            "expect {} parameters, found {}",
+            params_desc.len(),
+            params.len()
+        )));
+    }
+    for (index, (param, desc)) in params.iter().zip(params_desc).enumerate() {
+        let index = index + 1;
+        if param.is_null() {
+            if !desc.nullable {
+                return Err(OdbcStdError::SqlParamsError(format!(
+                    "parameter {index} is not nullable"
+                )));
+            }
+        } else if !param.param_kind().accepts(&desc.data_type) {
+            return Err(OdbcStdError::SqlParamsError(format!(
+                "parameter {index} of type {:?} can't bind to {:?}",
+                param.param_kind(),
+                desc.data_type
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extension::pg::PgValueInput;
+
+    fn desc(data_type: DataType, nullable: bool) -> OdbcParamDesc {
+        OdbcParamDesc {
+            data_type,
+            nullable,
+        }
+    }
+
+    #[test]
+    fn test_check_params() {
+        let params_desc = vec![
+            desc(DataType::Integer, false),
+            desc(DataType::Varchar { length: 10 }, true),
+        ];
+        let ok = vec![PgValueInput::Int4(Some(1)), PgValueInput::Varchar(None)];
+        assert!(check_params(&params_desc, &ok).is_ok());
+
+        let err = check_params(&params_desc, &[PgValueInput::Int4(Some(1))]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            OdbcStdError::SqlParamsError("expect 2 parameters, found 1".to_string()).to_string()
+        );
+
+        let null = vec![PgValueInput::Int4(None), PgValueInput::Varchar(None)];
+        assert!(check_params(&params_desc, &null).is_err());
+
+        let mismatch = vec![
+            PgValueInput::Bytea(Some(vec![1])),
+            PgValueInput::Varchar(None),
+        ];
+        assert!(check_params(&params_desc, &mismatch).is_err());
+
+        // text is converted by the driver
+        let text = vec![
+            PgValueInput::Text(Some("1".to_string())),
+            PgValueInput::Int8(Some(2)),
+        ];
+        assert!(check_params(&params_desc, &text).is_ok());
+    }
 }
@@ -0,0 +1,463 @@
+//! Stored procedure calls with IN, OUT and INOUT parameters, see
+//! [`ConnectionTrait::call_procedure`].
+//!
+//! The type of an OUT parameter is given by a [`PgValueInput`] template, its value is ignored.
+//!
+//! [`ConnectionTrait::call_procedure`]: crate::executor::database::ConnectionTrait::call_procedure
+use crate::executor::database::Options;
+use crate::executor::query::QueryResult;
+use crate::executor::statement::SqlValue;
+use crate::extension::odbc::OdbcColumnValue;
+use crate::extension::pg::{bind_precision, time_parameter, PgValueInput};
+use crate::extension::util::{
+    format_data_time_tz, format_time_tz, parse_to_data_time_tz, parse_to_time_tz,
+};
+use crate::{Convert, TryConvert};
+use chrono::{FixedOffset, NaiveTime, Offset, Timelike, Utc};
+use odbc_common::error::{OdbcStdError, OdbcStdResult};
+use odbc_common::odbc_api::buffers::Indicator;
+use odbc_common::odbc_api::handles::Statement;
+use odbc_common::odbc_api::parameter::{InputParameter, VarBinaryBox, VarCharBox, WithDataType};
+use odbc_common::odbc_api::sys::{Date, ParamType, Timestamp};
+use odbc_common::odbc_api::{Bit, DataType, Error, Nullable, ParameterCollection};
+
+/// A parameter of a stored procedure call.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProcedureParam {
+    In(PgValueInput),
+    /// The value is the type template of the output, e.g. `PgValueInput::Int4(None)`.
+    Out(PgValueInput),
+    InOut(PgValueInput),
+}
+
+/// The result of [`ConnectionTrait::call_procedure`].
+///
+/// [`ConnectionTrait::call_procedure`]: crate::executor::database::ConnectionTrait::call_procedure
+#[derive(Debug, Default)]
+pub struct ProcedureResult {
+    /// The values of the OUT and INOUT parameters in parameter order.
+    pub outputs: Vec<PgValueInput>,
+    /// The result sets produced by the procedure.
+    pub result_sets: Vec<QueryResult>,
+}
+
+impl ProcedureResult {
+    pub fn output(&self, index: usize) -> Option<&PgValueInput> {
+        self.outputs.get(index)
+    }
+
+    /// The output at `index` as `T`, `None` is NULL.
+    pub fn get<T>(&self, index: usize) -> OdbcStdResult<Option<T>>
+    where
+        for<'v> &'v OdbcColumnValue: TryConvert<T, Error = OdbcStdError>,
+    {
+        let output = self.output(index).ok_or_else(|| {
+            OdbcStdError::StringError(format!("no output parameter at index {index}"))
+        })?;
+        let value: Option<OdbcColumnValue> = output.clone().convert();
+        value.as_ref().map(TryConvert::try_convert).transpose()
+    }
+}
+
+/// Build `{call name(?,...)}`, the name is a dot separated identifier.
+pub(crate) fn call_sql(name: &str, params: usize) -> OdbcStdResult<String> {
+    let valid = name.split('.').all(|part| {
+        part.chars()
+            .next()
+            .is_some_and(|c| c.is_alphabetic() || c == '_')
+            && part.chars().all(|c| c.is_alphanumeric() || c == '_')
+    });
+    if !valid {
+        return Err(OdbcStdError::SqlParamsError(format!(
+            "invalid procedure name `{name}`"
+        )));
+    }
+    Ok(format!("{{call {name}({})}}", vec!["?"; params].join(",")))
+}
+
+enum BoundParam {
+    In(Box<dyn InputParameter>),
+    Out {
+        param_type: ParamType,
+        template: PgValueInput,
+        buffer: OutputBuffer,
+    },
+}
+
+/// The parameters of a call, bound to the statement as one parameter set.
+pub(crate) struct ProcedureParams {
+    params: Vec<BoundParam>,
+    // the offset of a time zone aware output without offset
+    offset: FixedOffset,
+}
+
+impl ProcedureParams {
+    pub(crate) fn new(params: Vec<ProcedureParam>, options: &Options) -> OdbcStdResult<Self> {
+        let params: Vec<BoundParam> = params
+            .into_iter()
+            .map(|param| match param {
+                ProcedureParam::In(value) => {
+                    value.to_value().left().map(BoundParam::In).ok_or_else(|| {
+                        OdbcStdError::SqlParamsError("value not include empty tuple".into())
+                    })
+                }
+                ProcedureParam::Out(template) => Ok(BoundParam::Out {
+                    param_type: ParamType::Output,
+                    buffer: OutputBuffer::new(&template, false, options),
+                    template,
+                }),
+                ProcedureParam::InOut(value) => Ok(BoundParam::Out {
+                    param_type: ParamType::InputOutput,
+                    buffer: OutputBuffer::new(&value, true, options),
+                    template: value,
+                }),
+            })
+            .collect::<OdbcStdResult<_>>()?;
+        // only a time zone aware output reads the offset, the others don't require it
+        let time_zone_aware = params.iter().any(|param| match param {
+            BoundParam::Out { template, .. } => {
+                matches!(
+                    template,
+                    PgValueInput::Timez(_) | PgValueInput::Timestampz(_)
+                )
+            }
+            BoundParam::In(_) => false,
+        });
+        let offset = match time_zone_aware {
+            true => options.session_offset()?,
+            false => Utc.fix(),
+        };
+        Ok(Self { params, offset })
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.params.len()
+    }
+
+    /// The values of the OUT and INOUT parameters, read once all results are consumed.
+    pub(crate) fn into_outputs(self) -> OdbcStdResult<Vec<PgValueInput>> {
+        let offset = self.offset;
+        self.params
+            .into_iter()
+            .filter_map(|param| match param {
+                BoundParam::In(_) => None,
+                BoundParam::Out {
+                    template, buffer, ..
+                } => Some(buffer.into_value(template, offset)),
+            })
+            .collect()
+    }
+}
+
+unsafe impl ParameterCollection for ProcedureParams {
+    fn parameter_set_size(&self) -> usize {
+        1
+    }
+
+    unsafe fn bind_parameters_to(&mut self, stmt: &mut impl Statement) -> Result<(), Error> {
+        for (index, param) in self.params.iter_mut().enumerate() {
+            let number = (index + 1) as u16;
+            match param {
+                BoundParam::In(value) => stmt.bind_input_parameter(number, value.as_ref()),
+                BoundParam::Out {
+                    param_type, buffer, ..
+                } => buffer.bind(stmt, number, *param_type),
+            }
+            .into_result(stmt)?;
+        }
+        Ok(())
+    }
+}
+
+/// The buffer of an OUT or INOUT parameter, the driver writes the value into it.
+enum OutputBuffer {
+    I8(Nullable<i8>),
+    I16(Nullable<i16>),
+    I32(Nullable<i32>),
+    I64(Nullable<i64>),
+    F32(Nullable<f32>),
+    F64(Nullable<f64>),
+    Bit(Nullable<Bit>),
+    Date(Nullable<Date>),
+    /// The time struct has no fraction of second, so a time is text declared as TIME, see
+    /// [`time_parameter`].
+    Time(WithDataType<VarCharBox>),
+    Timestamp(WithDataType<Nullable<Timestamp>>),
+    Text(VarCharBox),
+    Binary(VarBinaryBox),
+}
+
+const TIME_FORMAT: &str = "%H:%M:%S%.f";
+
+fn nullable<T: Default>(value: Option<T>) -> Nullable<T> {
+    value.map_or_else(Nullable::null, Nullable::new)
+}
+
+/// A buffer of at least `capacity` bytes holding `value`, text needs one more byte for the
+/// terminating zero.
+fn var_buffer(value: Option<&[u8]>, capacity: usize) -> (Box<[u8]>, Indicator) {
+    let len = value.map_or(0, <[u8]>::len);
+    let mut buffer = vec![0; capacity.max(len) + 1];
+    match value {
+        Some(v) => {
+            buffer[..len].copy_from_slice(v);
+            (buffer.into_boxed_slice(), Indicator::Length(len))
+        }
+        None => (buffer.into_boxed_slice(), Indicator::Null),
+    }
+}
+
+impl OutputBuffer {
+    /// The buffer for `template`, holding its value for an INOUT parameter.
+    fn new(template: &PgValueInput, input: bool, options: &Options) -> Self {
+        let template = if input {
+            template.clone()
+        } else {
+            template.without_value()
+        };
+        match template {
+            PgValueInput::Char(v) => Self::I8(nullable(v)),
+            PgValueInput::Int2(v) => Self::I16(nullable(v)),
+            PgValueInput::Int4(v) => Self::I32(nullable(v)),
+            PgValueInput::Int8(v) => Self::I64(nullable(v)),
+            PgValueInput::Float4(v) => Self::F32(nullable(v)),
+            PgValueInput::Float8(v) => Self::F64(nullable(v)),
+            PgValueInput::Bool(v) => Self::Bit(nullable(v.map(Bit::from_bool))),
+            PgValueInput::Date(v) => Self::Date(nullable(v.as_ref().map(Convert::convert))),
+            PgValueInput::Time(v) => {
+                let (text, data_type) = time_parameter(v);
+                // the length of `HH:MM:SS.fffffffff`
+                let (buffer, indicator) = var_buffer(text.as_ref().map(String::as_bytes), 18);
+                Self::Time(WithDataType {
+                    value: VarCharBox::from_buffer(buffer, indicator),
+                    data_type,
+                })
+            }
+            PgValueInput::Timestamp(v) => Self::Timestamp(WithDataType {
+                value: nullable(v.as_ref().map(Convert::convert)),
+                data_type: DataType::Timestamp {
+                    precision: bind_precision(v.map_or(0, |v| v.nanosecond())),
+                },
+            }),
+            // text keeps the offset, which the time and timestamp structs can't hold
+            PgValueInput::Timez(v) => {
+                let v = v.as_ref().map(format_time_tz);
+                let (buffer, indicator) =
+                    var_buffer(v.as_ref().map(String::as_bytes), options.max_str_len);
+                Self::Text(VarCharBox::from_buffer(buffer, indicator))
+            }
+            PgValueInput::Timestampz(v) => {
+                let v = v.as_ref().map(format_data_time_tz);
+                let (buffer, indicator) =
+                    var_buffer(v.as_ref().map(String::as_bytes), options.max_str_len);
+                Self::Text(VarCharBox::from_buffer(buffer, indicator))
+            }
+            PgValueInput::Varchar(v) | PgValueInput::Text(v) | PgValueInput::Name(v) => {
+                let (buffer, indicator) =
+                    var_buffer(v.as_ref().map(String::as_bytes), options.max_str_len);
+                Self::Text(VarCharBox::from_buffer(buffer, indicator))
+            }
+            PgValueInput::Numeric(v) => {
+                let v = v.map(|v| v.to_string());
+                let (buffer, indicator) =
+                    var_buffer(v.as_ref().map(String::as_bytes), options.max_str_len);
+                Self::Text(VarCharBox::from_buffer(buffer, indicator))
+            }
+            PgValueInput::Bytea(v) => {
+                let (buffer, indicator) = var_buffer(v.as_deref(), options.max_binary_len);
+                Self::Binary(VarBinaryBox::from_buffer(buffer, indicator))
+            }
+        }
+    }
+
+    unsafe fn bind(
+        &mut self,
+        stmt: &mut impl Statement,
+        number: u16,
+        param_type: ParamType,
+    ) -> odbc_common::odbc_api::handles::SqlResult<()> {
+        match self {
+            Self::I8(v) => stmt.bind_parameter(number, param_type, v),
+            Self::I16(v) => stmt.bind_parameter(number, param_type, v),
+            Self::I32(v) => stmt.bind_parameter(number, param_type, v),
+            Self::I64(v) => stmt.bind_parameter(number, param_type, v),
+            Self::F32(v) => stmt.bind_parameter(number, param_type, v),
+            Self::F64(v) => stmt.bind_parameter(number, param_type, v),
+            Self::Bit(v) => stmt.bind_parameter(number, param_type, v),
+            Self::Date(v) => stmt.bind_parameter(number, param_type, v),
+            Self::Time(v) => stmt.bind_parameter(number, param_type, v),
+            Self::Timestamp(v) => stmt.bind_parameter(number, param_type, v),
+            Self::Text(v) => stmt.bind_parameter(number, param_type, v),
+            Self::Binary(v) => stmt.bind_parameter(number, param_type, v),
+        }
+    }
+
+    /// The value written by the driver, in the variant of `template`. A time zone aware value
+    /// without offset is in `offset`.
+    fn into_value(
+        self,
+        template: PgValueInput,
+        offset: FixedOffset,
+    ) -> OdbcStdResult<PgValueInput> {
+        let value = match (template, self) {
+            (PgValueInput::Char(_), Self::I8(v)) => PgValueInput::Char(v.into_opt()),
+            (PgValueInput::Int2(_), Self::I16(v)) => PgValueInput::Int2(v.into_opt()),
+            (PgValueInput::Int4(_), Self::I32(v)) => PgValueInput::Int4(v.into_opt()),
+            (PgValueInput::Int8(_), Self::I64(v)) => PgValueInput::Int8(v.into_opt()),
+            (PgValueInput::Float4(_), Self::F32(v)) => PgValueInput::Float4(v.into_opt()),
+            (PgValueInput::Float8(_), Self::F64(v)) => PgValueInput::Float8(v.into_opt()),
+            (PgValueInput::Bool(_), Self::Bit(v)) => {
+                PgValueInput::Bool(v.into_opt().map(|b| b.as_bool()))
+            }
+            (PgValueInput::Date(_), Self::Date(v)) => {
+                PgValueInput::Date(v.as_opt().map(TryConvert::try_convert).transpose()?)
+            }
+            (PgValueInput::Time(_), Self::Time(v)) => {
+                let time = v
+                    .value
+                    .as_bytes()
+                    .map(|bytes| {
+                        let text = std::str::from_utf8(bytes)
+                            .map_err(|e| OdbcStdError::TypeConversionError(e.to_string()))?;
+                        Ok::<_, OdbcStdError>(NaiveTime::parse_from_str(text, TIME_FORMAT)?)
+                    })
+                    .transpose()?;
+                PgValueInput::Time(time)
+            }
+            (PgValueInput::Timestamp(_), Self::Timestamp(v)) => {
+                PgValueInput::Timestamp(v.value.as_opt().map(TryConvert::try_convert).transpose()?)
+            }
+            (template, Self::Text(v)) => {
+                if !v.is_complete() {
+                    return Err(OdbcStdError::StringError(format!(
+                        "output parameter is truncated, max_str_len:{} is too small",
+                        v.capacity() - 1
+                    )));
+                }
+                let text = v
+                    .as_bytes()
+                    .map(|bytes| String::from_utf8(bytes.to_vec()))
+                    .transpose()
+                    .map_err(|e| OdbcStdError::TypeConversionError(e.to_string()))?;
+                match template {
+                    PgValueInput::Varchar(_) => PgValueInput::Varchar(text),
+                    PgValueInput::Name(_) => PgValueInput::Name(text),
+                    PgValueInput::Numeric(_) => {
+                        PgValueInput::Numeric(text.map(|t| t.parse()).transpose()?)
+                    }
+                    PgValueInput::Timez(_) => {
+                        PgValueInput::Timez(text.map(|t| parse_to_time_tz(&t, offset)).transpose()?)
+                    }
+                    PgValueInput::Timestampz(_) => PgValueInput::Timestampz(
+                        text.map(|t| parse_to_data_time_tz(&t, offset))
+                            .transpose()?,
+                    ),
+                    _ => PgValueInput::Text(text),
+                }
+            }
+            (_, Self::Binary(v)) => {
+                if !v.is_complete() {
+                    return Err(OdbcStdError::StringError(format!(
+                        "output parameter is truncated, max_binary_len:{} is too small",
+                        v.capacity() - 1
+                    )));
+                }
+                PgValueInput::Bytea(v.as_bytes().map(<[u8]>::to_vec))
+            }
+            (template, _) => {
+                return Err(OdbcStdError::TypeConversionError(format!(
+                    "output parameter buffer doesn't match {template:?}"
+                )))
+            }
+        };
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::SupportDatabase;
+    use chrono::{DateTime, NaiveDate};
+
+    #[test]
+    fn test_call_sql() {
+        assert_eq!(call_sql("P_ADD", 3).unwrap(), "{call P_ADD(?,?,?)}");
+        assert_eq!(
+            call_sql("SYSDBA.P_NOW", 0).unwrap(),
+            "{call SYSDBA.P_NOW()}"
+        );
+        assert!(call_sql("P_ADD(1);DROP TABLE T", 0).is_err());
+        assert!(call_sql("A..B", 0).is_err());
+        assert!(call_sql("1P", 0).is_err());
+    }
+
+    #[test]
+    fn test_output_buffer_value() {
+        let mut options = Options::new(SupportDatabase::Dameng);
+        options.time_zone = FixedOffset::east_opt(8 * 3600);
+        let offset = options.session_offset().unwrap();
+        let inout = |value: PgValueInput| {
+            OutputBuffer::new(&value, true, &options)
+                .into_value(value.clone(), offset)
+                .unwrap()
+        };
+
+        let values = vec![
+            PgValueInput::Int4(Some(1)),
+            PgValueInput::Int8(None),
+            PgValueInput::Bool(Some(true)),
+            PgValueInput::Varchar(Some("abc".to_string())),
+            PgValueInput::Bytea(Some(vec![1, 2])),
+            PgValueInput::Date(NaiveDate::from_ymd_opt(2023, 1, 2)),
+            PgValueInput::Time(NaiveTime::from_hms_micro_opt(3, 4, 5, 123_456)),
+            PgValueInput::Time(NaiveTime::from_hms_opt(3, 4, 5)),
+            PgValueInput::Time(None),
+            PgValueInput::Timestamp(
+                NaiveDate::from_ymd_opt(2023, 1, 2).and_then(|d| d.and_hms_opt(3, 4, 5)),
+            ),
+            PgValueInput::Timestampz(Some(
+                DateTime::parse_from_rfc3339("2023-01-02T03:04:05.25+08:00").unwrap(),
+            )),
+            PgValueInput::Timez(Some("03:04:05-05:30".parse().unwrap())),
+        ];
+        for value in values {
+            assert_eq!(inout(value.clone()), value);
+        }
+
+        // the value of an OUT template is not sent
+        let out = OutputBuffer::new(&PgValueInput::Int4(Some(1)), false, &options)
+            .into_value(PgValueInput::Int4(Some(1)), offset)
+            .unwrap();
+        assert_eq!(out, PgValueInput::Int4(None));
+    }
+
+    #[test]
+    fn test_session_offset_required() {
+        let mut options = Options::new(SupportDatabase::Dameng);
+        let params = || {
+            vec![
+                ProcedureParam::In(PgValueInput::Timestampz(None)),
+                ProcedureParam::Out(PgValueInput::Int4(None)),
+            ]
+        };
+        assert!(ProcedureParams::new(params(), &options).is_ok());
+
+        // a time zone aware output requires the session time zone
+        let params = || vec![ProcedureParam::Out(PgValueInput::Timez(None))];
+        assert!(ProcedureParams::new(params(), &options).is_err());
+        options.time_zone = FixedOffset::east_opt(8 * 3600);
+        assert!(ProcedureParams::new(params(), &options).is_ok());
+    }
+
+    #[test]
+    fn test_procedure_result_get() {
+        let result = ProcedureResult {
+            outputs: vec![PgValueInput::Int4(Some(3)), PgValueInput::Varchar(None)],
+            result_sets: vec![],
+        };
+        assert_eq!(result.get::<i64>(0).unwrap(), Some(3));
+        assert_eq!(result.get::<String>(1).unwrap(), None);
+        assert!(result.get::<i64>(2).is_err());
+    }
+}
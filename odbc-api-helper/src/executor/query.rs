@@ -1,3 +1,4 @@
+use crate::executor::truncation::ColumnTruncation;
 use crate::extension::odbc::{OdbcColumnDesc, OdbcColumnItem};
 use odbc_common::error::OdbcStdResult;
 use odbc_common::print_table::Print;
@@ -10,6 +11,16 @@ pub struct QueryResult {
     pub columns: Vec<OdbcColumnDesc>,
     // table columns data
     pub data: Vec<OdbcRow>,
+    // the columns with values longer than `Options.max_str_len`/`Options.max_binary_len`.
+    // They are cut to the buffer size unless fetched in full, see `Options.truncation`
+    pub truncated: Vec<ColumnTruncation>,
+}
+
+impl QueryResult {
+    /// Whether a value is cut to the buffer size.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated.iter().any(|t| !t.fetched_in_full)
+    }
 }
 
 impl Print for QueryResult {
@@ -0,0 +1,575 @@
+//! Retry the calls which failed with a transient error, and reconnect a lost connection.
+//!
+//! A held `OdbcDbConnection` fails with `08S01` forever once the database restarts, a
+//! [`RetryConnection`] opens a new connection instead and retries the call per [`RetryPolicy`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use odbc_api_helper::executor::database::{ConnectionTrait, Options};
+//! use odbc_api_helper::executor::retry::{RetryConnection, RetryPolicy};
+//! use odbc_api_helper::executor::SupportDatabase;
+//! use odbc_api_helper::odbc_common::odbc_api::Environment;
+//!
+//! let env = Environment::new().unwrap();
+//! let conn = RetryConnection::new(
+//!     &env,
+//!     "Driver={DM8};Server=127.0.0.1;UID=SYSDBA;PWD=SYSDBA;",
+//!     Options::new(SupportDatabase::Dameng),
+//!     RetryPolicy::default(),
+//! )
+//! .unwrap();
+//! let result = conn.query("SELECT * FROM SYSCOLUMNS").unwrap();
+//! println!("{:?}", result);
+//! ```
+use crate::executor::bulk::BulkRows;
+use crate::executor::database::{table_desc_args, ConnectionTrait, OdbcDbConnection, Options};
+use crate::executor::execute::ExecResult;
+use crate::executor::isolation::{AccessMode, IsolationLevel, TransactionOptions};
+use crate::executor::procedure::{ProcedureParam, ProcedureResult};
+use crate::executor::query::QueryResult;
+use crate::executor::statement::{BoundStatement, StatementInput};
+use crate::executor::stream::QueryStream;
+use crate::executor::table::TableDescResult;
+use crate::TryConvert;
+use odbc_common::error::{OdbcStdError, OdbcStdResult};
+use odbc_common::odbc_api::Environment;
+use std::cell::{Cell, Ref, RefCell};
+use std::fmt::{Debug, Formatter};
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// The attempts of a call including the first one, 1 disables the retry.
+    pub max_attempts: usize,
+    /// The delay before the first retry, it's doubled by each retry up to `max_backoff`.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// The SQLSTATEs of the errors worth a retry, see `StatementError.state`.
+    pub transient_states: Vec<String>,
+    /// Retry only the calls which are safe to run twice, i.e. the queries. An `execute` which
+    /// lost its connection may have been committed already.
+    pub idempotent_only: bool,
+}
+
+impl RetryPolicy {
+    /// The connection exceptions, the connection timeout and the serialization failure.
+    pub const TRANSIENT_STATES: [&'static str; 6] =
+        ["08S01", "08001", "08003", "08004", "HYT01", "40001"];
+
+    /// Whether the error is reported by the driver with one of `transient_states`.
+    pub fn is_transient(&self, error: &OdbcStdError) -> bool {
+        error
+            .state()
+            .is_some_and(|state| self.transient_states.iter().any(|s| s == state))
+    }
+
+    /// The delay before the retry after `attempt` failed, starting at 1.
+    pub fn backoff(&self, attempt: usize) -> Duration {
+        let factor = 1u32.checked_shl(attempt.saturating_sub(1) as u32);
+        factor
+            .and_then(|f| self.initial_backoff.checked_mul(f))
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
+
+    /// Run `f` until it succeeds, fails with a non-transient error, or `max_attempts` is
+    /// reached. `idempotent` tells whether `f` is safe to run twice, see `idempotent_only`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use odbc_api_helper::executor::retry::RetryPolicy;
+    /// use odbc_common::error::odbc_error::{OdbcWrapperError, StatementError};
+    /// use odbc_common::error::OdbcStdError;
+    /// use std::time::Duration;
+    ///
+    /// let policy = RetryPolicy {
+    ///     initial_backoff: Duration::ZERO,
+    ///     ..RetryPolicy::default()
+    /// };
+    /// let mut attempts = 0;
+    /// let result = policy.run(true, || {
+    ///     attempts += 1;
+    ///     if attempts == 1 {
+    ///         return Err(OdbcStdError::OdbcError(OdbcWrapperError::StatementError(
+    ///             StatementError {
+    ///                 state: "08S01".to_string(),
+    ///                 error_msg: "connection lost".to_string(),
+    ///             },
+    ///         )));
+    ///     }
+    ///     Ok(attempts)
+    /// });
+    /// assert_eq!(result.unwrap(), 2);
+    /// ```
+    pub fn run<R>(
+        &self,
+        idempotent: bool,
+        mut f: impl FnMut() -> OdbcStdResult<R>,
+    ) -> OdbcStdResult<R> {
+        let retry = idempotent || !self.idempotent_only;
+        let mut attempt = 1;
+        loop {
+            let error = match f() {
+                Ok(result) => return Ok(result),
+                Err(e) => e,
+            };
+            if !retry || attempt >= self.max_attempts || !self.is_transient(&error) {
+                return Err(error);
+            }
+            let backoff = self.backoff(attempt);
+            warn!("attempt {attempt} failed:{error}, retry after {backoff:?}");
+            thread::sleep(backoff);
+            attempt += 1;
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+            transient_states: Self::TRANSIENT_STATES.map(String::from).to_vec(),
+            idempotent_only: true,
+        }
+    }
+}
+
+/// Whether the connection is lost, it's the SQLSTATE class `08` of connection exceptions.
+pub fn is_connection_error(error: &OdbcStdError) -> bool {
+    error.state().is_some_and(|state| state.starts_with("08"))
+}
+
+/// Open the connections of a [`Reconnect`].
+trait Connect {
+    type Connection;
+
+    fn connect(&self) -> OdbcStdResult<Self::Connection>;
+}
+
+struct OdbcConnector<'env> {
+    env: &'env Environment,
+    connection_string: String,
+    options: Options,
+}
+
+impl<'env> Connect for OdbcConnector<'env> {
+    type Connection = OdbcDbConnection<'env>;
+
+    fn connect(&self) -> OdbcStdResult<Self::Connection> {
+        let conn = self
+            .env
+            .connect_with_connection_string(&self.connection_string)?;
+        OdbcDbConnection::new(conn, self.options.clone())
+    }
+}
+
+/// The current connection, it's replaced after a connection error.
+struct Reconnect<K: Connect> {
+    connector: K,
+    policy: RetryPolicy,
+    current: RefCell<K::Connection>,
+    broken: Cell<bool>,
+    in_transaction: Cell<bool>,
+}
+
+impl<K: Connect> Reconnect<K> {
+    fn new(connector: K, policy: RetryPolicy) -> OdbcStdResult<Self> {
+        // the database may be starting, so the first connect is retried too
+        let conn = policy.run(true, || connector.connect())?;
+        Ok(Reconnect {
+            connector,
+            policy,
+            current: RefCell::new(conn),
+            broken: Cell::new(false),
+            in_transaction: Cell::new(false),
+        })
+    }
+
+    /// The current connection, reopen it when it's lost.
+    fn connection(&self) -> OdbcStdResult<Ref<'_, K::Connection>> {
+        if self.broken.get() {
+            if self.in_transaction.get() {
+                return Err(OdbcStdError::StringError(
+                    "the connection is lost in a transaction, rollback it first".to_string(),
+                ));
+            }
+            self.reconnect()?;
+        }
+        Ok(self.current.borrow())
+    }
+
+    fn reconnect(&self) -> OdbcStdResult<()> {
+        let mut current = self.current.try_borrow_mut().map_err(|_| {
+            OdbcStdError::StringError(
+                "the lost connection is borrowed by a query stream, drop it first".to_string(),
+            )
+        })?;
+        *current = self.connector.connect()?;
+        self.broken.set(false);
+        info!("odbc connection is reopened");
+        Ok(())
+    }
+
+    /// Run `f` with the current connection per the policy, a connection error marks the
+    /// connection broken.
+    fn run<R>(
+        &self,
+        idempotent: bool,
+        mut f: impl FnMut(&K::Connection) -> OdbcStdResult<R>,
+    ) -> OdbcStdResult<R> {
+        if self.in_transaction.get() {
+            return self.check(self.connection().and_then(|conn| f(&conn)));
+        }
+        self.policy.run(idempotent, || {
+            self.check(self.connection().and_then(|conn| f(&conn)))
+        })
+    }
+
+    fn check<T>(&self, result: OdbcStdResult<T>) -> OdbcStdResult<T> {
+        if let Err(e) = &result {
+            if is_connection_error(e) && !self.broken.replace(true) {
+                warn!("odbc connection is lost:{}", e);
+            }
+        }
+        result
+    }
+}
+
+/// A connection which reconnects after a connection error, and retries the calls per
+/// [`RetryPolicy`].
+///
+/// `query`, `query_multi`, `show_table` and the isolation level and access mode calls are
+/// idempotent. `execute`, `bulk_insert` and `call_procedure` are retried only when
+/// `RetryPolicy.idempotent_only` is unset, a `batch` retries each of its statements. A
+/// `query_stream` isn't retried, as it's consumed by the caller, and the lost connection isn't
+/// reopened until the stream is dropped.
+///
+/// Nothing is retried inside a transaction, and a lost transaction must be rolled back before
+/// the connection is reopened. The settings changed after connect, e.g. by
+/// `set_isolation_level`, aren't restored by a reconnect, set them in `Options` instead.
+pub struct RetryConnection<'env> {
+    inner: Reconnect<OdbcConnector<'env>>,
+}
+
+impl<'env> Debug for RetryConnection<'env> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetryConnection")
+            .field("options", &self.inner.connector.options)
+            .field("policy", &self.inner.policy)
+            .field("broken", &self.inner.broken.get())
+            .field("in_transaction", &self.inner.in_transaction.get())
+            .finish()
+    }
+}
+
+impl<'env> RetryConnection<'env> {
+    pub fn new<S: Into<String>>(
+        env: &'env Environment,
+        connection_string: S,
+        options: Options,
+        policy: RetryPolicy,
+    ) -> OdbcStdResult<Self> {
+        let connector = OdbcConnector {
+            env,
+            connection_string: connection_string.into(),
+            options,
+        };
+        Ok(RetryConnection {
+            inner: Reconnect::new(connector, policy)?,
+        })
+    }
+
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.inner.policy
+    }
+
+    /// Whether the current connection is lost, it's reopened by the next call.
+    pub fn is_broken(&self) -> bool {
+        self.inner.broken.get()
+    }
+
+    /// The current connection, reopen it when it's lost. It isn't reopened while the guard is
+    /// held.
+    pub fn connection(&self) -> OdbcStdResult<Ref<'_, OdbcDbConnection<'env>>> {
+        self.inner.connection()
+    }
+}
+
+impl<'env> ConnectionTrait for RetryConnection<'env> {
+    fn execute<S>(&self, stmt: S) -> OdbcStdResult<ExecResult>
+    where
+        S: StatementInput,
+    {
+        let stmt = BoundStatement::new(stmt)?;
+        self.inner.run(false, |conn| conn.execute_bound(&stmt))
+    }
+
+    fn query<S>(&self, stmt: S) -> OdbcStdResult<QueryResult>
+    where
+        S: StatementInput,
+    {
+        let stmt = BoundStatement::new(stmt)?;
+        self.inner.run(true, |conn| conn.query_bound(&stmt))
+    }
+
+    fn query_multi<S>(&self, stmt: S) -> OdbcStdResult<Vec<QueryResult>>
+    where
+        S: StatementInput,
+    {
+        let stmt = BoundStatement::new(stmt)?;
+        self.inner.run(true, |conn| conn.query_multi_bound(&stmt))
+    }
+
+    fn query_stream<S>(&self, stmt: S) -> OdbcStdResult<QueryStream<'_>>
+    where
+        S: StatementInput,
+    {
+        let guard = self.connection()?;
+        // SAFETY: the connection can't be replaced by a reconnect while the guard is held, and
+        // the stream drops its cursor before the guard.
+        let conn: &OdbcDbConnection<'env> = unsafe { &*(&*guard as *const _) };
+        let stream = self.inner.check(conn.query_stream(stmt))?;
+        Ok(stream.with_guard(guard))
+    }
+
+    fn show_table<S>(&self, stmt: S) -> OdbcStdResult<TableDescResult>
+    where
+        S: StatementInput,
+    {
+        let (db_name, table_names) = table_desc_args(stmt)?;
+        self.inner.run(true, |conn| {
+            conn.table_desc(db_name.clone(), table_names.clone())
+        })
+    }
+
+    fn bulk_insert<B>(&self, table: &str, rows: B) -> OdbcStdResult<usize>
+    where
+        B: TryConvert<BulkRows, Error = OdbcStdError>,
+    {
+        let rows: BulkRows = rows.try_convert()?;
+        self.inner
+            .run(false, |conn| conn.bulk_insert(table, rows.clone()))
+    }
+
+    fn call_procedure(
+        &self,
+        name: &str,
+        params: Vec<ProcedureParam>,
+    ) -> OdbcStdResult<ProcedureResult> {
+        self.inner
+            .run(false, |conn| conn.call_procedure(name, params.clone()))
+    }
+
+    fn begin(&self) -> OdbcStdResult<()> {
+        self.inner.run(true, |conn| conn.begin())?;
+        self.inner.in_transaction.set(true);
+        Ok(())
+    }
+
+    fn begin_with(&self, options: TransactionOptions) -> OdbcStdResult<()> {
+        self.inner.run(true, |conn| conn.begin_with(options))?;
+        self.inner.in_transaction.set(true);
+        Ok(())
+    }
+
+    fn finish(&self) -> OdbcStdResult<()> {
+        self.inner.in_transaction.set(false);
+        if self.inner.broken.get() {
+            // the new connection is opened in autocommit mode
+            return Ok(());
+        }
+        self.inner.check(self.connection()?.finish())
+    }
+
+    fn commit(&self) -> OdbcStdResult<()> {
+        self.inner.check(self.connection()?.commit())
+    }
+
+    fn rollback(&self) -> OdbcStdResult<()> {
+        if self.inner.broken.get() && self.inner.in_transaction.get() {
+            warn!("the transaction is rolled back by the lost connection");
+            return Ok(());
+        }
+        self.inner.check(self.connection()?.rollback())
+    }
+
+    fn set_isolation_level(&self, level: IsolationLevel) -> OdbcStdResult<()> {
+        self.inner.run(true, |conn| conn.set_isolation_level(level))
+    }
+
+    fn isolation_level(&self) -> OdbcStdResult<Option<IsolationLevel>> {
+        self.inner.run(true, |conn| conn.isolation_level())
+    }
+
+    fn set_access_mode(&self, mode: AccessMode) -> OdbcStdResult<()> {
+        self.inner.run(true, |conn| conn.set_access_mode(mode))
+    }
+
+    fn access_mode(&self) -> OdbcStdResult<Option<AccessMode>> {
+        self.inner.run(true, |conn| conn.access_mode())
+    }
+
+    fn options(&self) -> Option<&Options> {
+        Some(&self.inner.connector.options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use odbc_common::error::odbc_error::{OdbcWrapperError, StatementError};
+
+    fn statement_error(state: &str) -> OdbcStdError {
+        OdbcStdError::OdbcError(OdbcWrapperError::StatementError(StatementError {
+            state: state.to_string(),
+            error_msg: "".to_string(),
+        }))
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            initial_backoff: Duration::ZERO,
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// The connections are numbered by connect, starting at 1.
+    #[derive(Default)]
+    struct MockConnector {
+        connects: Cell<usize>,
+    }
+
+    impl Connect for MockConnector {
+        type Connection = usize;
+
+        fn connect(&self) -> OdbcStdResult<usize> {
+            self.connects.set(self.connects.get() + 1);
+            Ok(self.connects.get())
+        }
+    }
+
+    fn reconnect() -> Reconnect<MockConnector> {
+        Reconnect::new(MockConnector::default(), policy()).unwrap()
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(2), Duration::from_millis(400));
+        assert_eq!(policy.backoff(3), Duration::from_millis(800));
+        assert_eq!(policy.backoff(10), Duration::from_secs(5));
+        assert_eq!(policy.backoff(100), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_is_transient() {
+        let policy = RetryPolicy::default();
+        assert!(policy.is_transient(&statement_error("08S01")));
+        assert!(policy.is_transient(&statement_error("40001")));
+        assert!(!policy.is_transient(&statement_error("42S02")));
+        assert!(!policy.is_transient(&OdbcStdError::StringError("08S01".into())));
+
+        assert!(is_connection_error(&statement_error("08003")));
+        assert!(!is_connection_error(&statement_error("40001")));
+    }
+
+    #[test]
+    fn test_run_retry() {
+        let mut attempts = 0;
+        let err = policy()
+            .run(true, || -> OdbcStdResult<()> {
+                attempts += 1;
+                Err(statement_error("08S01"))
+            })
+            .unwrap_err();
+        assert_eq!(err.state(), Some("08S01"));
+        assert_eq!(attempts, 3);
+
+        // a permanent error isn't retried
+        attempts = 0;
+        let _ = policy().run(true, || -> OdbcStdResult<()> {
+            attempts += 1;
+            Err(statement_error("42S02"))
+        });
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn test_run_idempotent_only() {
+        let mut attempts = 0;
+        let _ = policy().run(false, || -> OdbcStdResult<()> {
+            attempts += 1;
+            Err(statement_error("08S01"))
+        });
+        assert_eq!(attempts, 1);
+
+        attempts = 0;
+        let policy = RetryPolicy {
+            idempotent_only: false,
+            ..policy()
+        };
+        let _ = policy.run(false, || -> OdbcStdResult<()> {
+            attempts += 1;
+            Err(statement_error("08S01"))
+        });
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn test_reconnect() {
+        let conn = reconnect();
+        let result = conn.run(true, |conn| match conn {
+            1 => Err(statement_error("08S01")),
+            id => Ok(*id),
+        });
+        assert_eq!(result.unwrap(), 2);
+        assert_eq!(conn.connector.connects.get(), 2);
+        assert!(!conn.broken.get());
+
+        // a permanent error keeps the connection
+        let _ = conn.run(true, |_| -> OdbcStdResult<()> {
+            Err(statement_error("42S02"))
+        });
+        assert_eq!(*conn.connection().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_no_retry_in_transaction() {
+        let conn = reconnect();
+        conn.in_transaction.set(true);
+        let mut attempts = 0;
+        let err = conn
+            .run(true, |_| -> OdbcStdResult<()> {
+                attempts += 1;
+                Err(statement_error("08S01"))
+            })
+            .unwrap_err();
+        assert_eq!(err.state(), Some("08S01"));
+        assert_eq!(attempts, 1);
+        assert!(conn.broken.get());
+
+        // the lost transaction isn't replaced by a new connection
+        assert!(conn.connection().is_err());
+        assert_eq!(conn.connector.connects.get(), 1);
+
+        conn.in_transaction.set(false);
+        assert_eq!(*conn.connection().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_no_reconnect_while_borrowed() {
+        let conn = reconnect();
+        let guard = conn.connection().unwrap();
+        conn.broken.set(true);
+        assert!(conn.connection().is_err());
+        assert_eq!(conn.connector.connects.get(), 1);
+
+        drop(guard);
+        assert_eq!(*conn.connection().unwrap(), 2);
+    }
+}
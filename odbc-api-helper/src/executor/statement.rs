@@ -1,10 +1,13 @@
 use crate::executor::batch::{OdbcOperation, Operation};
+use crate::executor::named::{bind_named, parse_named, NamedParams};
+use crate::executor::SupportDatabase;
 use crate::TryConvert;
 use either::Either;
 use odbc_common::error::{OdbcStdError, OdbcStdResult};
 use odbc_common::odbc_api::parameter::InputParameter;
 use std::any::Any;
 use std::fmt::Debug;
+use std::time::Duration;
 
 pub(crate) type EitherInputParameter = Either<Vec<Box<dyn InputParameter>>, Box<dyn Any>>;
 
@@ -19,6 +22,11 @@ pub trait StatementInput {
         None
     }
 
+    /// The query timeout of the statement, `None` uses the default of `Options.query_timeout`.
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+
     fn input_values(self) -> Result<EitherInputParameter, OdbcStdError>
     where
         Self: Sized,
@@ -28,6 +36,28 @@ pub trait StatementInput {
     }
 }
 
+/// A statement with its parameters converted once, so it can be executed more than once,
+/// e.g. by a retry of [`RetryConnection`](crate::executor::retry::RetryConnection).
+pub(crate) struct BoundStatement {
+    pub(crate) sql: String,
+    // empty when the statement has no parameters
+    pub(crate) params: Vec<Box<dyn InputParameter>>,
+    pub(crate) timeout: Option<Duration>,
+}
+
+impl BoundStatement {
+    pub(crate) fn new<S: StatementInput>(stmt: S) -> OdbcStdResult<Self> {
+        let sql = stmt.to_sql().to_string();
+        let timeout = stmt.timeout();
+        let params = stmt.input_values()?.left().unwrap_or_default();
+        Ok(BoundStatement {
+            sql,
+            params,
+            timeout,
+        })
+    }
+}
+
 pub trait SqlValue {
     fn to_value(self) -> Either<Box<dyn InputParameter>, Box<dyn Any>>;
 }
@@ -43,6 +73,8 @@ where
     pub values: Vec<T>,
     /// odbc-bridge operation,most are ignored, unless the batch operation is used
     pub odbc_operation: Option<O>,
+    /// The query timeout, overrides `Options.query_timeout`
+    pub timeout: Option<Duration>,
 }
 
 impl<T, O> Statement<T, O>
@@ -55,13 +87,65 @@ where
             sql: sql.into(),
             values,
             odbc_operation: None,
+            timeout: None,
         }
     }
 
+    /// Build a statement from sql with named placeholders, see [`named`](crate::executor::named).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use odbc_api_helper::executor::batch::OdbcOperation;
+    /// use odbc_api_helper::executor::statement::Statement;
+    /// use odbc_api_helper::extension::pg::PgValueInput;
+    ///
+    /// let statement: Statement<PgValueInput, OdbcOperation> = Statement::named(
+    ///     "SELECT * FROM USERS WHERE NAME = :name OR ALIAS = :name AND AGE > :age",
+    ///     vec![
+    ///         ("age", PgValueInput::Int2(Some(8))),
+    ///         ("name", PgValueInput::Varchar(Some("foo".into()))),
+    ///     ],
+    /// )
+    /// .unwrap();
+    /// assert_eq!(statement.sql, "SELECT * FROM USERS WHERE NAME = ? OR ALIAS = ? AND AGE > ?");
+    /// assert_eq!(statement.values.len(), 3);
+    /// ```
+    pub fn named<S, P>(sql: S, params: P) -> OdbcStdResult<Self>
+    where
+        S: AsRef<str>,
+        P: NamedParams<T>,
+        T: Clone,
+    {
+        let (sql, names) = parse_named(sql.as_ref(), None)?;
+        let values = bind_named(&names, params.into_named())?;
+        Ok(Statement::new(sql, values))
+    }
+
+    /// Build a statement from sql with named placeholders like [`Statement::named`], with the
+    /// quoting rules of `database`. On MySQL an `@name` is a user variable, not a placeholder.
+    pub fn named_for<S, P>(database: &SupportDatabase, sql: S, params: P) -> OdbcStdResult<Self>
+    where
+        S: AsRef<str>,
+        P: NamedParams<T>,
+        T: Clone,
+    {
+        let (sql, names) = parse_named(sql.as_ref(), Some(database))?;
+        let values = bind_named(&names, params.into_named())?;
+        Ok(Statement::new(sql, values))
+    }
+
     pub fn operation(mut self, opt: O) -> Self {
         self.odbc_operation = Some(opt);
         self
     }
+
+    /// Abort the statement when it runs longer than `timeout`, rounded up to seconds. It fails
+    /// with [`OdbcStdError::Canceled`]. A zero `timeout` waits without limit.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
 }
 
 impl SqlValue for &str {
@@ -101,6 +185,10 @@ where
     fn operation(&self) -> Option<Self::Operation> {
         self.odbc_operation.clone()
     }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
 }
 
 impl StatementInput for &str {
@@ -0,0 +1,427 @@
+use crate::executor::conversion::{resolve_row, Cell, ConversionPolicy};
+use crate::executor::database::{OdbcDbConnection, Options};
+use crate::executor::handle::more_results;
+use crate::executor::long_data::{fetch_rows_by_get_data, use_long_data};
+use crate::executor::query::{OdbcRow, QueryResult};
+use crate::executor::truncation::{check_row_set, ColumnTruncation};
+use crate::extension::odbc::{column_values, OdbcColumnDesc, OdbcColumnItem};
+use crate::TryConvert;
+use odbc_common::error::{OdbcStdError, OdbcStdResult};
+use odbc_common::odbc_api::{
+    buffers::{BufferDesc, ColumnarAnyBuffer},
+    handles::{AsStatementRef, Statement, StatementImpl, StatementRef},
+    BlockCursor, Cursor, CursorImpl,
+};
+use std::mem::ManuallyDrop;
+use std::vec::IntoIter;
+
+pub type OdbcBlockCursor<'s> = BlockCursor<CursorImpl<StatementImpl<'s>>, ColumnarAnyBuffer>;
+
+/// The source of the row sets of a `QueryStream`, a block cursor or the canned row sets of the
+/// mock connection.
+pub(crate) trait RowSets {
+    /// Fetch the next row set, `None` when the result set is exhausted.
+    fn fetch(&mut self) -> OdbcStdResult<Option<&ColumnarAnyBuffer>>;
+}
+
+impl<'s> RowSets for OdbcBlockCursor<'s> {
+    fn fetch(&mut self) -> OdbcStdResult<Option<&ColumnarAnyBuffer>> {
+        Ok(BlockCursor::fetch(self)?)
+    }
+}
+
+/// A cursor-backed query result which fetches rows lazily.
+///
+/// The bound `ColumnarAnyBuffer` is kept across fetches, so at most `Options.max_batch_size`
+/// rows are held in memory at once. The column describe is available before any row is fetched.
+/// A long value is cut to the buffer size, see [`OdbcDbConnection::query_long_data`].
+///
+/// # Example
+///
+/// ```no_run
+/// use odbc_api_helper::executor::database::{ConnectionTrait, OdbcDbConnection};
+///
+/// fn count_rows(conn: &OdbcDbConnection) -> usize {
+///     let stream = conn.query_stream("SELECT * FROM SYSCOLUMNS").unwrap();
+///     println!("columns:{:?}", stream.columns());
+///     stream.rows().map(|row| row.unwrap()).count()
+/// }
+/// ```
+#[allow(missing_debug_implementations)]
+pub struct QueryStream<'s> {
+    columns: Vec<OdbcColumnDesc>,
+    row_sets: Box<dyn RowSets + 's>,
+    finished: bool,
+    options: Options,
+    truncated: Vec<ColumnTruncation>,
+    // the number of rows fetched so far
+    num_rows: usize,
+    // dropped after `row_sets`, e.g. the borrow of the connection of a `RetryConnection`
+    _guard: Option<Box<dyn Guard + 's>>,
+}
+
+/// Any value kept alive by a `QueryStream`, see [`QueryStream::with_guard`].
+pub(crate) trait Guard {}
+
+impl<T> Guard for T {}
+
+impl<'s> QueryStream<'s> {
+    pub fn new(
+        cursor: CursorImpl<StatementImpl<'s>>,
+        columns: Vec<OdbcColumnDesc>,
+        options: &Options,
+    ) -> OdbcStdResult<Self> {
+        let cursor = cursor.bind_buffer(row_set_buffer(&columns, options)?)?;
+        Ok(Self::from_row_sets(Box::new(cursor), columns, options))
+    }
+
+    pub(crate) fn from_row_sets(
+        row_sets: Box<dyn RowSets + 's>,
+        columns: Vec<OdbcColumnDesc>,
+        options: &Options,
+    ) -> Self {
+        Self {
+            columns,
+            row_sets,
+            finished: false,
+            options: options.clone(),
+            truncated: vec![],
+            num_rows: 0,
+            _guard: None,
+        }
+    }
+
+    /// Keep `guard` until the stream is dropped, after its cursor.
+    pub(crate) fn with_guard(mut self, guard: impl Guard + 's) -> Self {
+        self._guard = Some(Box::new(guard));
+        self
+    }
+
+    /// The columns describe of the result set
+    pub fn columns(&self) -> &[OdbcColumnDesc] {
+        &self.columns
+    }
+
+    /// The columns with truncated values in the row sets fetched so far.
+    pub fn truncated(&self) -> &[ColumnTruncation] {
+        &self.truncated
+    }
+
+    /// Fetch the next row set, at most `Options.max_batch_size` rows.
+    /// Return `None` when all row sets have been fetched.
+    pub fn next_batch(&mut self) -> OdbcStdResult<Option<Vec<OdbcRow>>> {
+        if self.finished {
+            return Ok(None);
+        }
+        let fetched = self.row_sets.fetch();
+        match fetched {
+            Ok(Some(row_set)) => {
+                let first_row = self.num_rows;
+                self.num_rows += row_set.num_rows();
+                let rows =
+                    check_row_set(row_set, &self.columns, &self.options, &mut self.truncated)
+                        .and_then(|_| {
+                            rows_from_row_set(
+                                row_set,
+                                &self.columns,
+                                self.options.conversion,
+                                first_row,
+                            )
+                        });
+                if rows.is_err() {
+                    self.finished = true;
+                }
+                rows.map(Some)
+            }
+            Ok(None) => {
+                self.finished = true;
+                Ok(None)
+            }
+            Err(e) => {
+                self.finished = true;
+                Err(e)
+            }
+        }
+    }
+
+    /// Flatten the row sets into an iterator which yields one row at a time.
+    pub fn rows(self) -> QueryRows<'s> {
+        QueryRows {
+            stream: self,
+            current: Vec::new().into_iter(),
+        }
+    }
+
+    /// Fetch all remaining rows into a `QueryResult`.
+    pub fn fetch_all(mut self) -> OdbcStdResult<QueryResult> {
+        let mut data = vec![];
+        while let Some(mut rows) = self.next_batch()? {
+            data.append(&mut rows);
+        }
+        Ok(QueryResult {
+            columns: self.columns,
+            data,
+            truncated: self.truncated,
+        })
+    }
+}
+
+impl<'s> Iterator for QueryStream<'s> {
+    type Item = OdbcStdResult<Vec<OdbcRow>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_batch().transpose()
+    }
+}
+
+/// Row by row iterator over a `QueryStream`, see [`QueryStream::rows`].
+#[allow(missing_debug_implementations)]
+pub struct QueryRows<'s> {
+    stream: QueryStream<'s>,
+    current: IntoIter<OdbcRow>,
+}
+
+impl<'s> QueryRows<'s> {
+    pub fn columns(&self) -> &[OdbcColumnDesc] {
+        self.stream.columns()
+    }
+}
+
+impl<'s> Iterator for QueryRows<'s> {
+    type Item = OdbcStdResult<OdbcRow>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(row) = self.current.next() {
+                return Some(Ok(row));
+            }
+            match self.stream.next_batch() {
+                Ok(Some(rows)) => self.current = rows.into_iter(),
+                Ok(None) => return None,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// A row set buffer of `Options.max_batch_size` rows for the columns.
+fn row_set_buffer(
+    columns: &[OdbcColumnDesc],
+    options: &Options,
+) -> OdbcStdResult<ColumnarAnyBuffer> {
+    let descs = columns
+        .iter()
+        .map(|c| <(&OdbcColumnDesc, &Options) as TryConvert<BufferDesc>>::try_convert((c, options)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(ColumnarAnyBuffer::try_from_descs(
+        options.max_batch_size,
+        descs,
+    )?)
+}
+
+/// Fetch the result set of any cursor, e.g. the cursor of a prepared statement.
+/// The long data columns are fetched in full, see [`use_long_data`].
+pub(crate) fn fetch_query_result<C: Cursor>(
+    mut cursor: C,
+    columns: Vec<OdbcColumnDesc>,
+    options: &Options,
+) -> OdbcStdResult<QueryResult> {
+    if use_long_data(&columns, options) {
+        let (data, truncated) = fetch_rows_by_get_data(&mut cursor, &columns, options)?;
+        return Ok(QueryResult {
+            columns,
+            data,
+            truncated,
+        });
+    }
+    let (data, truncated) = fetch_all_rows(cursor, &columns, options)?;
+    Ok(QueryResult {
+        columns,
+        data,
+        truncated,
+    })
+}
+
+fn fetch_all_rows<C: Cursor>(
+    cursor: C,
+    columns: &[OdbcColumnDesc],
+    options: &Options,
+) -> OdbcStdResult<(Vec<OdbcRow>, Vec<ColumnTruncation>)> {
+    let mut cursor = cursor.bind_buffer(row_set_buffer(columns, options)?)?;
+    let mut rows = vec![];
+    let mut truncated = vec![];
+    let mut num_rows = 0;
+    while let Some(row_set) = cursor.fetch()? {
+        check_row_set(row_set, columns, options, &mut truncated)?;
+        let first_row = num_rows;
+        num_rows += row_set.num_rows();
+        rows.append(&mut rows_from_row_set(
+            row_set,
+            columns,
+            options.conversion,
+            first_row,
+        )?);
+    }
+    Ok((rows, truncated))
+}
+
+/// Fetch every result set of an executed statement, the results without columns (e.g. the row
+/// count of an UPDATE) are skipped.
+///
+/// The cursor is kept open between the result sets, closing it would discard the pending ones.
+pub(crate) fn fetch_result_sets(
+    mut stmt: StatementRef<'_>,
+    options: &Options,
+) -> OdbcStdResult<Vec<QueryResult>> {
+    let mut result_sets = vec![];
+    loop {
+        let num_cols = stmt.num_result_cols().into_result(&stmt)?;
+        if num_cols > 0 {
+            result_sets.push(fetch_open_result_set(&mut stmt, options)?);
+        }
+        if !more_results(&mut stmt)? {
+            return Ok(result_sets);
+        }
+    }
+}
+
+fn fetch_open_result_set(
+    stmt: &mut StatementRef<'_>,
+    options: &Options,
+) -> OdbcStdResult<QueryResult> {
+    // SAFETY: the statement has a result set. The cursor is never dropped, so it isn't closed.
+    let mut cursor = ManuallyDrop::new(unsafe { CursorImpl::new(stmt.as_stmt_ref()) });
+    let columns = OdbcDbConnection::get_cursor_columns(&mut *cursor)?;
+    if use_long_data(&columns, options) {
+        let (data, truncated) = fetch_rows_by_get_data(&mut *cursor, &columns, options)?;
+        return Ok(QueryResult {
+            columns,
+            data,
+            truncated,
+        });
+    }
+    let mut buffer = row_set_buffer(&columns, options)?;
+
+    let fetched = ManuallyDrop::into_inner(cursor)
+        .bind_buffer(&mut buffer)
+        .map_err(OdbcStdError::from)
+        .and_then(|block| {
+            // don't unbind by drop, which also closes the cursor
+            let mut block = ManuallyDrop::new(block);
+            let mut data = vec![];
+            let mut truncated = vec![];
+            let mut num_rows = 0;
+            while let Some(row_set) = block.fetch()? {
+                check_row_set(row_set, &columns, options, &mut truncated)?;
+                let first_row = num_rows;
+                num_rows += row_set.num_rows();
+                data.append(&mut rows_from_row_set(
+                    row_set,
+                    &columns,
+                    options.conversion,
+                    first_row,
+                )?);
+            }
+            Ok((data, truncated))
+        });
+    // SAFETY: unbind `buffer` before it's dropped, on error too
+    unsafe {
+        stmt.unbind_cols().into_result(stmt)?;
+        stmt.set_num_rows_fetched(None).into_result(stmt)?;
+    }
+    let (data, truncated) = fetched?;
+    Ok(QueryResult {
+        columns,
+        data,
+        truncated,
+    })
+}
+
+/// Transpose the columnar row set into rows, the values which can't be converted are resolved
+/// per `policy`. `first_row` is the index of the first row of the row set in the result set.
+pub(crate) fn rows_from_row_set(
+    row_set: &ColumnarAnyBuffer,
+    columns: &[OdbcColumnDesc],
+    policy: ConversionPolicy,
+    first_row: usize,
+) -> OdbcStdResult<Vec<OdbcRow>> {
+    let mut rows: Vec<Vec<Cell<OdbcColumnItem>>> = (0..row_set.num_rows())
+        .map(|_| Vec::with_capacity(columns.len()))
+        .collect();
+    for (index, column) in columns.iter().enumerate() {
+        let (odbc_type, values) = column_values(row_set.column(index));
+        for (i, (row, value)) in rows.iter_mut().zip(values).enumerate() {
+            row.push(match value {
+                Ok(value) => {
+                    Cell::Valid(OdbcColumnItem::new(odbc_type, column.with_precision(value)))
+                }
+                Err(e) => Cell::Invalid(
+                    e.at(&column.name, first_row + i),
+                    OdbcColumnItem::new(odbc_type, None),
+                ),
+            });
+        }
+    }
+    let mut data = Vec::with_capacity(rows.len());
+    for row in rows {
+        if let Some(row) = resolve_row(row, policy)? {
+            data.push(row);
+        }
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::executor::database::{ConnectionTrait, Options};
+    use crate::executor::mock::{call_names, MockConnection};
+    use crate::executor::SupportDatabase;
+    use crate::extension::odbc::OdbcColumnValue;
+
+    #[test]
+    fn test_stream_fetches_lazily() {
+        let mut options = Options::new(SupportDatabase::Dameng);
+        options.max_batch_size = 2;
+        let (conn, calls) = MockConnection::with_options(options);
+
+        let mut stream = conn.query_stream("5").unwrap();
+        assert_eq!(stream.columns()[0].name, "ID");
+        assert_eq!(call_names(&calls), ["query_stream 5"]);
+
+        let mut ids = vec![];
+        let mut batches = 0;
+        while let Some(rows) = stream.next_batch().unwrap() {
+            batches += 1;
+            assert_eq!(call_names(&calls).len(), 1 + batches);
+            for row in rows {
+                match row[0].value {
+                    Some(OdbcColumnValue::I32(id)) => ids.push(id),
+                    ref v => panic!("unexpected value {v:?}"),
+                }
+            }
+        }
+        assert_eq!(batches, 3);
+        assert_eq!(ids, [0, 1, 2, 3, 4]);
+
+        // the end of the stream is fetched once
+        assert!(stream.next_batch().unwrap().is_none());
+        assert!(stream.next().is_none());
+        assert_eq!(
+            call_names(&calls).iter().filter(|c| *c == "fetch").count(),
+            4
+        );
+    }
+
+    #[test]
+    fn test_stream_rows() {
+        let mut options = Options::new(SupportDatabase::Dameng);
+        options.max_batch_size = 2;
+        let (conn, _) = MockConnection::with_options(options);
+        let rows = conn.query_stream("3").unwrap().rows();
+        let rows = rows.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(rows.len(), 3);
+
+        let result = conn.query_stream("0").unwrap().fetch_all().unwrap();
+        assert!(result.data.is_empty());
+    }
+}
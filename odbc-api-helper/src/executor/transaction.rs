@@ -0,0 +1,368 @@
+use crate::executor::bulk::BulkRows;
+use crate::executor::database::ConnectionTrait;
+use crate::executor::execute::ExecResult;
+use crate::executor::isolation::TransactionOptions;
+use crate::executor::procedure::{ProcedureParam, ProcedureResult};
+use crate::executor::query::QueryResult;
+use crate::executor::statement::StatementInput;
+use crate::executor::stream::QueryStream;
+use crate::executor::table::TableDescResult;
+use crate::TryConvert;
+use odbc_common::error::{OdbcStdError, OdbcStdResult};
+use std::fmt::{Debug, Formatter};
+
+/// A transaction guard returned by [`ConnectionTrait::transaction`].
+///
+/// The transaction is rolled back on drop unless [`Transaction::commit`] was called, and the
+/// connection is switched back to autocommit in both cases.
+///
+/// A nested transaction from [`Transaction::transaction`] is mapped onto a savepoint: its commit
+/// releases the savepoint, its rollback only undoes the work done since the savepoint.
+///
+/// # Example
+///
+/// ```no_run
+/// use odbc_api_helper::executor::database::{ConnectionTrait, OdbcDbConnection};
+/// use odbc_api_helper::odbc_common::error::OdbcStdResult;
+///
+/// fn transfer(conn: &OdbcDbConnection) -> OdbcStdResult<()> {
+///     let tx = conn.transaction()?;
+///     tx.execute("UPDATE ACCOUNT SET BALANCE = BALANCE - 10 WHERE ID = 1")?;
+///     tx.execute("UPDATE ACCOUNT SET BALANCE = BALANCE + 10 WHERE ID = 2")?;
+///     tx.commit()
+/// }
+/// ```
+pub struct Transaction<'conn, C: ConnectionTrait> {
+    conn: &'conn C,
+    // the savepoint of a nested transaction
+    savepoint: Option<String>,
+    depth: usize,
+    finished: bool,
+}
+
+impl<'conn, C: ConnectionTrait> Debug for Transaction<'conn, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Transaction")
+            .field("savepoint", &self.savepoint)
+            .field("depth", &self.depth)
+            .field("finished", &self.finished)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'conn, C: ConnectionTrait> Transaction<'conn, C> {
+    pub(crate) fn begin(conn: &'conn C) -> OdbcStdResult<Self> {
+        Self::begin_with(conn, TransactionOptions::default())
+    }
+
+    pub(crate) fn begin_with(conn: &'conn C, options: TransactionOptions) -> OdbcStdResult<Self> {
+        conn.begin_with(options)?;
+        Ok(Transaction {
+            conn,
+            savepoint: None,
+            depth: 0,
+            finished: false,
+        })
+    }
+
+    /// Begin a nested transaction, backed by a savepoint.
+    pub fn transaction(&self) -> OdbcStdResult<Transaction<'_, C>> {
+        let depth = self.depth + 1;
+        let name = format!("odbc_bridge_sp_{depth}");
+        self.conn.savepoint(&name)?;
+        Ok(Transaction {
+            conn: self.conn,
+            savepoint: Some(name),
+            depth,
+            finished: false,
+        })
+    }
+
+    /// Run `f` inside a nested transaction: release the savepoint when `f` returns `Ok`,
+    /// otherwise rollback to it.
+    pub fn in_transaction<R, F>(&self, f: F) -> OdbcStdResult<R>
+    where
+        F: FnOnce(&Transaction<'_, C>) -> OdbcStdResult<R>,
+    {
+        let tx = self.transaction()?;
+        let result = f(&tx)?;
+        tx.commit()?;
+        Ok(result)
+    }
+
+    pub fn savepoint(&self, name: &str) -> OdbcStdResult<()> {
+        self.conn.savepoint(name)
+    }
+
+    pub fn rollback_to(&self, name: &str) -> OdbcStdResult<()> {
+        self.conn.rollback_to(name)
+    }
+
+    pub fn release(&self, name: &str) -> OdbcStdResult<()> {
+        self.conn.release(name)
+    }
+
+    /// The connection the transaction runs on.
+    pub fn connection(&self) -> &'conn C {
+        self.conn
+    }
+
+    pub fn commit(mut self) -> OdbcStdResult<()> {
+        self.end(true)
+    }
+
+    pub fn rollback(mut self) -> OdbcStdResult<()> {
+        self.end(false)
+    }
+
+    /// Commit or rollback, then restore autocommit. A failed commit is rolled back.
+    fn end(&mut self, commit: bool) -> OdbcStdResult<()> {
+        self.finished = true;
+        if let Some(name) = &self.savepoint {
+            if !commit {
+                self.conn.rollback_to(name)?;
+            }
+            return self.conn.release(name);
+        }
+        let result = if commit {
+            self.conn.commit().map_err(|e| {
+                if let Err(rollback) = self.conn.rollback() {
+                    error!("rollback after commit error:{}", rollback);
+                }
+                e
+            })
+        } else {
+            self.conn.rollback()
+        };
+        let finish = self.conn.finish();
+        result.and(finish)
+    }
+
+    pub fn execute<S>(&self, stmt: S) -> OdbcStdResult<ExecResult>
+    where
+        S: StatementInput,
+    {
+        self.conn.execute(stmt)
+    }
+
+    pub fn query<S>(&self, stmt: S) -> OdbcStdResult<QueryResult>
+    where
+        S: StatementInput,
+    {
+        self.conn.query(stmt)
+    }
+
+    pub fn query_multi<S>(&self, stmt: S) -> OdbcStdResult<Vec<QueryResult>>
+    where
+        S: StatementInput,
+    {
+        self.conn.query_multi(stmt)
+    }
+
+    pub fn query_stream<S>(&self, stmt: S) -> OdbcStdResult<QueryStream<'conn>>
+    where
+        S: StatementInput,
+    {
+        self.conn.query_stream(stmt)
+    }
+
+    pub fn bulk_insert<B>(&self, table: &str, rows: B) -> OdbcStdResult<usize>
+    where
+        B: TryConvert<BulkRows, Error = OdbcStdError>,
+    {
+        self.conn.bulk_insert(table, rows)
+    }
+
+    pub fn call_procedure(
+        &self,
+        name: &str,
+        params: Vec<ProcedureParam>,
+    ) -> OdbcStdResult<ProcedureResult> {
+        self.conn.call_procedure(name, params)
+    }
+
+    pub fn show_table<S>(&self, stmt: S) -> OdbcStdResult<TableDescResult>
+    where
+        S: StatementInput,
+    {
+        self.conn.show_table(stmt)
+    }
+}
+
+impl<'conn, C: ConnectionTrait> Drop for Transaction<'conn, C> {
+    fn drop(&mut self) {
+        if !self.finished {
+            if let Err(e) = self.end(false) {
+                error!("rollback transaction on drop error:{}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::executor::database::ConnectionTrait;
+    use crate::executor::isolation::{AccessMode, IsolationLevel, TransactionOptions};
+    use crate::executor::mock::{call_names, MockConnection};
+    use crate::executor::SupportDatabase;
+    use odbc_common::error::OdbcStdResult;
+
+    #[test]
+    fn test_transaction_commit() {
+        let (conn, calls) = MockConnection::new();
+        let tx = conn.transaction().unwrap();
+        tx.execute("0").unwrap();
+        tx.commit().unwrap();
+        assert_eq!(
+            call_names(&calls),
+            vec!["begin", "execute 0", "commit", "finish"]
+        );
+    }
+
+    #[test]
+    fn test_transaction_rollback_on_drop() {
+        let (conn, calls) = MockConnection::new();
+        {
+            let tx = conn.transaction().unwrap();
+            assert!(tx.execute("fail").is_err());
+        }
+        assert_eq!(
+            call_names(&calls),
+            vec!["begin", "execute fail", "rollback", "finish"]
+        );
+    }
+
+    #[test]
+    fn test_in_transaction() {
+        let (conn, calls) = MockConnection::new();
+        let rows = conn
+            .in_transaction(|tx| Ok(tx.execute("0")?.rows_affected))
+            .unwrap();
+        assert_eq!(rows, 1);
+
+        let result: OdbcStdResult<()> = conn.in_transaction(|tx| {
+            tx.execute("fail")?;
+            tx.execute("0")?;
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(
+            call_names(&calls)[4..],
+            ["begin", "execute fail", "rollback", "finish"]
+        );
+    }
+
+    #[test]
+    fn test_nested_transaction() {
+        let (conn, calls) = MockConnection::with_database(SupportDatabase::Pg);
+        conn.in_transaction(|tx| {
+            tx.execute("0")?;
+            let nested: OdbcStdResult<()> = tx.in_transaction(|nested| {
+                nested.in_transaction(|_| Ok(()))?;
+                nested.execute("fail")?;
+                Ok(())
+            });
+            assert!(nested.is_err());
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(
+            call_names(&calls),
+            vec![
+                "begin",
+                "execute 0",
+                "execute SAVEPOINT odbc_bridge_sp_1",
+                "execute SAVEPOINT odbc_bridge_sp_2",
+                "execute RELEASE SAVEPOINT odbc_bridge_sp_2",
+                "execute fail",
+                "execute ROLLBACK TO SAVEPOINT odbc_bridge_sp_1",
+                "execute RELEASE SAVEPOINT odbc_bridge_sp_1",
+                "commit",
+                "finish",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_savepoint_sql() {
+        let (conn, calls) = MockConnection::new();
+        conn.savepoint("sp").unwrap();
+        conn.rollback_to("sp").unwrap();
+        conn.release("sp").unwrap();
+        assert!(conn.savepoint("sp; DROP TABLE T").is_err());
+        assert_eq!(
+            call_names(&calls),
+            vec!["execute SAVEPOINT sp", "execute ROLLBACK TO SAVEPOINT sp"]
+        );
+    }
+
+    #[test]
+    fn test_transaction_with_options() {
+        let (conn, calls) = MockConnection::new();
+        conn.set_isolation_level(IsolationLevel::ReadCommitted)
+            .unwrap();
+        let options = TransactionOptions::default()
+            .isolation_level(IsolationLevel::Serializable)
+            .read_only();
+        drop(conn.transaction_with(options).unwrap());
+        // the connection setting is kept
+        assert_eq!(
+            conn.isolation_level().unwrap(),
+            Some(IsolationLevel::ReadCommitted)
+        );
+        assert_eq!(conn.access_mode().unwrap(), None);
+        assert_eq!(
+            call_names(&calls),
+            vec![
+                "isolation ReadCommitted",
+                "begin",
+                "execute SET TRANSACTION ISOLATION LEVEL SERIALIZABLE",
+                "execute SET TRANSACTION READ ONLY",
+                "rollback",
+                "finish",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_current_isolation_level() {
+        let (conn, calls) = MockConnection::with_database(SupportDatabase::Pg);
+        conn.query_returns("SHOW transaction_isolation", "serializable");
+        conn.query_returns("SHOW transaction_read_only", "on");
+        assert_eq!(
+            conn.current_isolation_level().unwrap(),
+            IsolationLevel::Serializable
+        );
+        assert_eq!(conn.current_access_mode().unwrap(), AccessMode::ReadOnly);
+        assert_eq!(
+            call_names(&calls),
+            vec![
+                "query SHOW transaction_isolation",
+                "query SHOW transaction_read_only"
+            ]
+        );
+
+        // nothing read back is an error, not the setting kept by the connection
+        let (conn, _) = MockConnection::new();
+        conn.set_isolation_level(IsolationLevel::ReadCommitted)
+            .unwrap();
+        assert!(conn.current_isolation_level().is_err());
+    }
+
+    #[test]
+    fn test_set_transaction_error() {
+        let (conn, calls) = MockConnection::with_database(SupportDatabase::Pg);
+        conn.fail_on("SET TRANSACTION");
+        let options = TransactionOptions::default().read_only();
+        assert!(conn.transaction_with(options).is_err());
+        assert_eq!(
+            call_names(&calls),
+            vec![
+                "begin",
+                "execute SET TRANSACTION READ ONLY",
+                "rollback",
+                "finish"
+            ]
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! Values cut to the bound buffer size, see `Options.truncation`.
+use crate::executor::database::Options;
+use crate::extension::odbc::OdbcColumnDesc;
+use crate::TryConvert;
+use odbc_common::error::odbc_error::StatementError;
+use odbc_common::error::{OdbcStdError, OdbcStdResult, OdbcWrapperError};
+use odbc_common::odbc_api::buffers::{AnySlice, BufferDesc, ColumnarAnyBuffer};
+use odbc_common::state::OdbcState;
+
+/// How a query handles a value longer than `Options.max_str_len`/`Options.max_binary_len`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TruncationPolicy {
+    /// Keep the truncated value, the column is still recorded in the result.
+    #[default]
+    Ignore,
+    /// Keep the truncated value and log a warning once per column.
+    Warn,
+    /// Fail the query with [`OdbcState::STMT_TRUNCATED`].
+    Fail,
+}
+
+/// A column of which at least one value is longer than the buffer size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnTruncation {
+    // the column index, starting at 0
+    pub index: usize,
+    pub name: String,
+    // the values are fetched in full with `SQLGetData` (see `Options.long_data`), so nothing is
+    // lost. false means they are cut to the buffer size
+    pub fetched_in_full: bool,
+}
+
+/// Check the text and binary columns of a fetched row set, the newly truncated columns are
+/// pushed to `truncated` and handled per `Options.truncation`.
+pub(crate) fn check_row_set(
+    row_set: &ColumnarAnyBuffer,
+    columns: &[OdbcColumnDesc],
+    options: &Options,
+    truncated: &mut Vec<ColumnTruncation>,
+) -> OdbcStdResult<()> {
+    for (index, column) in columns.iter().enumerate() {
+        // `has_truncated_values` also counts a NULL, so a truncated value must fill the buffer.
+        // The views don't expose the indicators, a value which fits the buffer exactly next to a
+        // NULL is reported too
+        let is_truncated = match row_set.column(index) {
+            AnySlice::Text(view) => {
+                view.has_truncated_values()
+                    && view.iter().flatten().any(|v| v.len() == view.max_len())
+            }
+            AnySlice::WText(view) => {
+                view.has_truncated_values()
+                    && view.iter().flatten().any(|v| v.len() == view.max_len())
+            }
+            AnySlice::Binary(view) => {
+                view.has_truncated_values() && {
+                    let max_len = binary_len(column, options)?;
+                    view.iter().flatten().any(|v| v.len() == max_len)
+                }
+            }
+            _ => false,
+        };
+        if is_truncated {
+            record(index, column, options.truncation, truncated)?;
+        }
+    }
+    Ok(())
+}
+
+/// Record a column of which a value longer than the buffer size is fetched in full.
+pub(crate) fn record_fetched_in_full(
+    index: usize,
+    column: &OdbcColumnDesc,
+    truncated: &mut Vec<ColumnTruncation>,
+) {
+    if truncated.iter().all(|t| t.index != index) {
+        truncated.push(ColumnTruncation {
+            index,
+            name: column.name.clone(),
+            fetched_in_full: true,
+        });
+    }
+}
+
+/// The length of the binary buffer bound for `column`.
+fn binary_len(column: &OdbcColumnDesc, options: &Options) -> OdbcStdResult<usize> {
+    match <(&OdbcColumnDesc, &Options) as TryConvert<BufferDesc>>::try_convert((column, options))? {
+        BufferDesc::Binary { length } => Ok(length),
+        _ => Ok(options.max_binary_len),
+    }
+}
+
+fn record(
+    index: usize,
+    column: &OdbcColumnDesc,
+    policy: TruncationPolicy,
+    truncated: &mut Vec<ColumnTruncation>,
+) -> OdbcStdResult<()> {
+    if truncated.iter().any(|t| t.index == index) {
+        return Ok(());
+    }
+    match policy {
+        TruncationPolicy::Ignore => {}
+        TruncationPolicy::Warn => warn!(
+            "column `{}` is truncated, increase max_str_len or max_binary_len of Options",
+            column.name
+        ),
+        TruncationPolicy::Fail => return Err(truncation_error(column)),
+    }
+    truncated.push(ColumnTruncation {
+        index,
+        name: column.name.clone(),
+        fetched_in_full: false,
+    });
+    Ok(())
+}
+
+fn truncation_error(column: &OdbcColumnDesc) -> OdbcStdError {
+    OdbcStdError::OdbcError(OdbcWrapperError::StatementError(StatementError {
+        state: OdbcState::STMT_TRUNCATED.to_string(),
+        error_msg: format!("string data right truncated, column `{}`", column.name),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::SupportDatabase;
+    use odbc_common::odbc_api::buffers::{AnyBuffer, BinColumn, ColumnarBuffer, TextColumn};
+    use odbc_common::odbc_api::{DataType, RowSetBuffer};
+
+    /// A row set of a text and a binary column with buffers of 2 bytes, `truncate` writes the
+    /// values to longer buffers first so that their indicators exceed the buffer size.
+    fn row_set(
+        text: &[Option<&str>],
+        binary: &[Option<&[u8]>],
+        truncate: bool,
+    ) -> ColumnarAnyBuffer {
+        let len = if truncate { 4 } else { 2 };
+        let mut text_column = TextColumn::new(text.len(), len);
+        for (i, v) in text.iter().enumerate() {
+            text_column.set_value(i, v.map(str::as_bytes));
+        }
+        text_column.resize_max_str(2, text.len());
+        let mut bin_column = BinColumn::new(binary.len(), len);
+        for (i, v) in binary.iter().enumerate() {
+            bin_column.set_value(i, *v);
+        }
+        bin_column.resize_max_element_length(2, binary.len());
+        let mut row_set = ColumnarBuffer::new(vec![
+            (1, AnyBuffer::Text(text_column)),
+            (2, AnyBuffer::Binary(bin_column)),
+        ]);
+        *row_set.mut_num_fetch_rows() = text.len();
+        row_set
+    }
+
+    #[test]
+    fn test_check_row_set() {
+        let columns = vec![
+            OdbcColumnDesc::new("NAME".to_string(), DataType::Varchar { length: 2 }, true),
+            OdbcColumnDesc::new("DATA".to_string(), DataType::Varbinary { length: 2 }, true),
+        ];
+        let mut options = Options::new(SupportDatabase::Dameng);
+
+        // NULL and values which fit aren't truncated
+        let mut truncated = vec![];
+        let fit = row_set(&[None, Some("a")], &[None, Some(&[1])], false);
+        check_row_set(&fit, &columns, &options, &mut truncated).unwrap();
+        let full = row_set(&[Some("ab")], &[Some(&[1, 2])], false);
+        check_row_set(&full, &columns, &options, &mut truncated).unwrap();
+        assert!(truncated.is_empty());
+
+        let cut = row_set(&[None, Some("abcd")], &[Some(&[1, 2, 3]), None], true);
+        check_row_set(&cut, &columns, &options, &mut truncated).unwrap();
+        assert_eq!(
+            truncated
+                .iter()
+                .map(|t| t.name.as_str())
+                .collect::<Vec<_>>(),
+            ["NAME", "DATA"]
+        );
+        assert!(truncated.iter().all(|t| !t.fetched_in_full));
+
+        options.truncation = TruncationPolicy::Fail;
+        let err = check_row_set(&cut, &columns, &options, &mut vec![]).unwrap_err();
+        assert_eq!(err.odbc_state(), Some(OdbcState::STMT_TRUNCATED));
+    }
+
+    #[test]
+    fn test_record_truncation() {
+        let column = OdbcColumnDesc::new("NAME".to_string(), DataType::Varchar { length: 1 }, true);
+        let mut truncated = vec![];
+        record(1, &column, TruncationPolicy::Ignore, &mut truncated).unwrap();
+        record(1, &column, TruncationPolicy::Warn, &mut truncated).unwrap();
+        assert_eq!(
+            truncated,
+            vec![ColumnTruncation {
+                index: 1,
+                name: "NAME".to_string(),
+                fetched_in_full: false,
+            }]
+        );
+
+        let err = record(2, &column, TruncationPolicy::Fail, &mut truncated).unwrap_err();
+        assert_eq!(err.odbc_state(), Some(OdbcState::STMT_TRUNCATED));
+        assert_eq!(truncated.len(), 1);
+    }
+}
@@ -1,14 +1,23 @@
+use crate::executor::conversion::ConversionError;
 use crate::executor::database::Options;
+use crate::extension::util::{
+    parse_to_bool, parse_to_data_time, parse_to_data_time_tz, parse_to_date, parse_to_float4,
+    parse_to_float8, parse_to_time, parse_to_time_tz, truncate_fraction, with_offset,
+};
 use crate::{Convert, TryConvert};
-use bytes::BytesMut;
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use odbc_common::error::OdbcStdError;
 use odbc_common::odbc_api::handles::ParameterDescription;
 use odbc_common::odbc_api::{
     buffers::{AnySlice, BufferDesc},
-    sys::{Date, Time, Timestamp, NULL_DATA},
+    sys::{Date, Time, Timestamp},
     DataType,
 };
 use odbc_common::odbc_api::{ColumnDescription, Nullability};
+use pg_helper::numeric::Numeric;
+use pg_helper::timetz::TimeTz;
 use std::cmp::min;
+use std::fmt::{Display, Formatter};
 
 #[derive(Debug, Clone)]
 pub struct OdbcColumnDesc {
@@ -31,6 +40,34 @@ impl OdbcColumnDesc {
             nullable,
         }
     }
+
+    /// LONGVARCHAR/LONGVARBINARY, e.g. Dameng TEXT/CLOB and BLOB/IMAGE, which can be longer
+    /// than any bound buffer.
+    pub fn is_long_data(&self) -> bool {
+        matches!(
+            self.data_type,
+            DataType::LongVarchar { .. } | DataType::LongVarbinary { .. }
+        )
+    }
+
+    /// The fractional second digits of a TIME/TIMESTAMP column.
+    pub fn fraction_precision(&self) -> Option<u16> {
+        match self.data_type {
+            DataType::Time { precision } | DataType::Timestamp { precision } => {
+                u16::try_from(precision).ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// Cut the fractional seconds of a value to the column precision, see
+    /// [`OdbcColumnDesc::fraction_precision`].
+    pub(crate) fn with_precision(&self, value: Option<OdbcColumnValue>) -> Option<OdbcColumnValue> {
+        match self.fraction_precision() {
+            Some(precision) => value.map(|v| v.truncate_fraction(precision)),
+            None => value,
+        }
+    }
 }
 
 impl TryFrom<ParameterDescription> for OdbcParamDesc {
@@ -67,8 +104,15 @@ impl TryConvert<BufferDesc> for (&OdbcColumnDesc, &Options) {
     fn try_convert(self) -> Result<BufferDesc, Self::Error> {
         let c = self.0;
         let option = self.1;
-        let mut desc = BufferDesc::from_data_type(c.data_type, c.nullable)
-            .ok_or_else(|| format!("covert DataType:{:?} to BufferDesc error", c.data_type))?;
+        let mut desc = match c.data_type {
+            // a driver specific type, e.g. a Dameng DATETIME WITH TIME ZONE, is read as text
+            // which keeps its offset
+            DataType::Other { .. } => BufferDesc::WText {
+                max_str_len: option.max_str_len,
+            },
+            data_type => BufferDesc::from_data_type(data_type, c.nullable)
+                .ok_or_else(|| format!("covert DataType:{:?} to BufferDesc error", data_type))?,
+        };
 
         // When use `BufferKind::from_data_type` get result with `BufferKind::Text`
         // It's maybe caused panic,it need use `Option.max_str_len` to readjust size.
@@ -98,13 +142,13 @@ impl TryConvert<BufferDesc> for (&OdbcColumnDesc, &Options) {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct OdbcColumnItem {
     pub odbc_type: OdbcColumnType,
-    pub value: Option<BytesMut>,
+    pub value: Option<OdbcColumnValue>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OdbcColumnType {
     Text,
     WText,
@@ -122,426 +166,494 @@ pub enum OdbcColumnType {
     Bit,
 }
 
-impl ToString for OdbcColumnItem {
-    fn to_string(&self) -> String {
-        format!("{self:?}")
+/// The typed value of a column, fetched from the odbc buffer without text round-trip.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OdbcColumnValue {
+    Text(String),
+    Binary(Vec<u8>),
+    Date(NaiveDate),
+    Time(NaiveTime),
+    Timestamp(NaiveDateTime),
+    F64(f64),
+    F32(f32),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    U8(u8),
+    Bit(bool),
+}
+
+impl OdbcColumnItem {
+    pub fn new(odbc_type: OdbcColumnType, value: Option<OdbcColumnValue>) -> Self {
+        Self { odbc_type, value }
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.value.is_none()
     }
 }
 
-impl Convert<Vec<OdbcColumnItem>> for AnySlice<'_> {
-    fn convert(self) -> Vec<OdbcColumnItem> {
+impl Display for OdbcColumnItem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.value {
+            Some(v) => write!(f, "{v}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl OdbcColumnValue {
+    /// Cut the fractional seconds of a time or timestamp to `precision` digits, the other
+    /// values are kept.
+    pub fn truncate_fraction(self, precision: u16) -> Self {
         match self {
-            AnySlice::Text(view) => {
-                let mut buffer = Vec::with_capacity(view.len());
-                for v in view.iter() {
-                    if let Some(x) = v {
-                        buffer.push(OdbcColumnItem {
-                            odbc_type: OdbcColumnType::Text,
-                            value: Some(BytesMut::from(x)),
-                        });
-                    } else {
-                        buffer.push(OdbcColumnItem {
-                            odbc_type: OdbcColumnType::Text,
-                            value: None,
-                        })
-                    }
-                }
-                buffer
-            }
-            AnySlice::WText(view) => {
-                let mut buffer = Vec::with_capacity(view.len());
-                for v in view.iter() {
-                    if let Some(x) = v {
-                        buffer.push(OdbcColumnItem {
-                            odbc_type: OdbcColumnType::WText,
-                            value: Some(BytesMut::from(x.to_string().unwrap().as_bytes())),
-                        });
-                    } else {
-                        buffer.push(OdbcColumnItem {
-                            odbc_type: OdbcColumnType::WText,
-                            value: None,
-                        })
-                    }
-                }
-                buffer
-            }
-            AnySlice::Binary(view) => {
-                let mut buffer = vec![];
-                for value in view.iter() {
-                    if let Some(bytes) = value {
-                        buffer.push(OdbcColumnItem {
-                            odbc_type: OdbcColumnType::Binary,
-                            value: Some(BytesMut::from(bytes)),
-                        })
-                    } else {
-                        buffer.push(OdbcColumnItem {
-                            odbc_type: OdbcColumnType::Binary,
-                            value: None,
-                        })
-                    }
-                }
-                buffer
-            }
-            AnySlice::Date(view) => {
-                let mut buffer = vec![];
-                for value in view.iter() {
-                    let val = value.try_convert().unwrap();
-                    buffer.push(OdbcColumnItem {
-                        odbc_type: OdbcColumnType::Date,
-                        value: Some(BytesMut::from(val.to_string().as_bytes())),
-                    })
-                }
-                buffer
-            }
-            AnySlice::Timestamp(view) => {
-                let mut buffer = vec![];
-                for value in view.iter() {
-                    let val: time::PrimitiveDateTime = value.try_convert().unwrap();
-                    buffer.push(OdbcColumnItem {
-                        odbc_type: OdbcColumnType::Timestamp,
-                        value: Some(BytesMut::from(val.to_string().as_bytes())),
-                    })
-                }
-                buffer
-            }
-            AnySlice::Time(view) => {
-                let mut buffer = vec![];
-                for value in view.iter() {
-                    let val = value.try_convert().unwrap();
-                    buffer.push(OdbcColumnItem {
-                        odbc_type: OdbcColumnType::Time,
-                        value: Some(BytesMut::from(val.to_string().as_bytes())),
-                    })
-                }
-                buffer
-            }
-            AnySlice::I32(view) => {
-                let mut buffer = vec![];
-                for value in view.iter() {
-                    buffer.push(OdbcColumnItem {
-                        odbc_type: OdbcColumnType::I32,
-                        value: Some(BytesMut::from(value.to_string().as_bytes())),
-                    })
-                }
-                buffer
-            }
-            AnySlice::Bit(view) => {
-                let mut buffer = vec![];
-                for value in view.iter() {
-                    buffer.push(OdbcColumnItem {
-                        odbc_type: OdbcColumnType::Bit,
-                        value: Some(BytesMut::from(value.as_bool().to_string().as_bytes())),
-                    })
-                }
-                buffer
+            OdbcColumnValue::Time(v) => OdbcColumnValue::Time(truncate_fraction(v, precision)),
+            OdbcColumnValue::Timestamp(v) => {
+                OdbcColumnValue::Timestamp(truncate_fraction(v, precision))
             }
+            v => v,
+        }
+    }
+}
 
-            AnySlice::F64(view) => {
-                let mut buffer = vec![];
-                for value in view.iter() {
-                    buffer.push(OdbcColumnItem {
-                        odbc_type: OdbcColumnType::F64,
-                        value: Some(BytesMut::from(value.to_string().as_bytes())),
-                    })
-                }
-                buffer
-            }
-            AnySlice::F32(view) => {
-                let mut buffer = vec![];
-                for value in view.iter() {
-                    buffer.push(OdbcColumnItem {
-                        odbc_type: OdbcColumnType::F32,
-                        value: Some(BytesMut::from(value.to_string().as_bytes())),
-                    })
-                }
-                buffer
-            }
-            AnySlice::I8(view) => {
-                let mut buffer = vec![];
-                for value in view.iter() {
-                    buffer.push(OdbcColumnItem {
-                        odbc_type: OdbcColumnType::I8,
-                        value: Some(BytesMut::from(value.to_string().as_bytes())),
-                    })
-                }
-                buffer
-            }
-            AnySlice::I16(view) => {
-                let mut buffer = vec![];
-                for value in view.iter() {
-                    buffer.push(OdbcColumnItem {
-                        odbc_type: OdbcColumnType::I16,
-                        value: Some(BytesMut::from(value.to_string().as_bytes())),
-                    })
-                }
-                buffer
-            }
-            AnySlice::I64(view) => {
-                let mut buffer = vec![];
-                for value in view.iter() {
-                    buffer.push(OdbcColumnItem {
-                        odbc_type: OdbcColumnType::I64,
-                        value: Some(BytesMut::from(value.to_string().as_bytes())),
-                    })
-                }
-                buffer
-            }
-            AnySlice::U8(view) => {
-                let mut buffer = vec![];
+impl Display for OdbcColumnValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OdbcColumnValue::Text(v) => write!(f, "{v}"),
+            OdbcColumnValue::Binary(v) => write!(f, "{}", String::from_utf8_lossy(v)),
+            OdbcColumnValue::Date(v) => write!(f, "{v}"),
+            OdbcColumnValue::Time(v) => write!(f, "{v}"),
+            OdbcColumnValue::Timestamp(v) => write!(f, "{v}"),
+            OdbcColumnValue::F64(v) => write!(f, "{v}"),
+            OdbcColumnValue::F32(v) => write!(f, "{v}"),
+            OdbcColumnValue::I8(v) => write!(f, "{v}"),
+            OdbcColumnValue::I16(v) => write!(f, "{v}"),
+            OdbcColumnValue::I32(v) => write!(f, "{v}"),
+            OdbcColumnValue::I64(v) => write!(f, "{v}"),
+            OdbcColumnValue::U8(v) => write!(f, "{v}"),
+            OdbcColumnValue::Bit(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+/// A value of a column which can't be converted, see [`column_values`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct InvalidValue {
+    // the value as read from the buffer
+    pub raw: String,
+    pub cause: String,
+}
+
+impl InvalidValue {
+    pub(crate) fn new<T: std::fmt::Debug>(raw: &T, cause: OdbcStdError) -> Self {
+        Self {
+            raw: format!("{raw:?}"),
+            cause: cause.to_string(),
+        }
+    }
+
+    /// The error of the value in the column `column` of the row `row`.
+    pub(crate) fn at(self, column: &str, row: usize) -> ConversionError {
+        ConversionError {
+            column: column.to_string(),
+            row,
+            raw: self.raw,
+            cause: self.cause,
+        }
+    }
+}
 
-                for value in view.iter() {
-                    buffer.push(OdbcColumnItem {
-                        odbc_type: OdbcColumnType::U8,
-                        value: Some(BytesMut::from(vec![*value].as_slice())),
+pub(crate) type ColumnValue = Result<Option<OdbcColumnValue>, InvalidValue>;
+
+/// Text which isn't valid UTF-8 is an invalid value instead of being replaced.
+pub(crate) fn utf8_text(v: &[u8]) -> Result<OdbcColumnValue, InvalidValue> {
+    String::from_utf8(v.to_vec())
+        .map(OdbcColumnValue::Text)
+        .map_err(|e| {
+            InvalidValue::new(
+                &String::from_utf8_lossy(v),
+                OdbcStdError::TypeConversionError(e.to_string()),
+            )
+        })
+}
+
+/// Convert the values of a column, a value which can't be converted is kept as an error, so the
+/// caller decides per `Options.conversion`.
+pub(crate) fn column_values(slice: AnySlice<'_>) -> (OdbcColumnType, Vec<ColumnValue>) {
+    macro_rules! values {
+        ($view:expr, $odbc_type:ident, |$v:ident| $value:expr) => {{
+            let values = $view.iter().map(|$v| Ok(Some($value))).collect();
+            (OdbcColumnType::$odbc_type, values)
+        }};
+    }
+
+    macro_rules! nullable_values {
+        ($view:expr, $odbc_type:ident, |$v:ident| $value:expr) => {{
+            let values = $view.map(|v| Ok(v.map(|$v| $value))).collect();
+            (OdbcColumnType::$odbc_type, values)
+        }};
+    }
+
+    macro_rules! try_values {
+        ($view:expr, $odbc_type:ident) => {{
+            let values = $view
+                .map(|v| match v {
+                    Some(v) => v
+                        .try_convert()
+                        .map(|v| Some(OdbcColumnValue::$odbc_type(v)))
+                        .map_err(|e| InvalidValue::new(v, e)),
+                    None => Ok(None),
+                })
+                .collect();
+            (OdbcColumnType::$odbc_type, values)
+        }};
+    }
+
+    match slice {
+        AnySlice::Text(view) => {
+            let values = view.iter().map(|v| v.map(utf8_text).transpose()).collect();
+            (OdbcColumnType::Text, values)
+        }
+        AnySlice::WText(view) => {
+            let values = view
+                .iter()
+                .map(|v| {
+                    v.map(|v| {
+                        String::from_utf16(v.as_slice())
+                            .map(OdbcColumnValue::Text)
+                            .map_err(|e| {
+                                InvalidValue::new(
+                                    &v.to_string_lossy(),
+                                    OdbcStdError::TypeConversionError(e.to_string()),
+                                )
+                            })
                     })
+                    .transpose()
+                })
+                .collect();
+            (OdbcColumnType::WText, values)
+        }
+        AnySlice::Binary(view) => nullable_values!(view.iter(), Binary, |v| {
+            OdbcColumnValue::Binary(v.to_vec())
+        }),
+        AnySlice::Date(view) => try_values!(view.iter().map(Some), Date),
+        AnySlice::Time(view) => try_values!(view.iter().map(Some), Time),
+        AnySlice::Timestamp(view) => try_values!(view.iter().map(Some), Timestamp),
+        AnySlice::F64(view) => values!(view, F64, |v| OdbcColumnValue::F64(*v)),
+        AnySlice::F32(view) => values!(view, F32, |v| OdbcColumnValue::F32(*v)),
+        AnySlice::I8(view) => values!(view, I8, |v| OdbcColumnValue::I8(*v)),
+        AnySlice::I16(view) => values!(view, I16, |v| OdbcColumnValue::I16(*v)),
+        AnySlice::I32(view) => values!(view, I32, |v| OdbcColumnValue::I32(*v)),
+        AnySlice::I64(view) => values!(view, I64, |v| OdbcColumnValue::I64(*v)),
+        AnySlice::U8(view) => values!(view, U8, |v| OdbcColumnValue::U8(*v)),
+        AnySlice::Bit(view) => values!(view, Bit, |v| OdbcColumnValue::Bit(v.as_bool())),
+        AnySlice::NullableDate(view) => try_values!(view, Date),
+        AnySlice::NullableTime(view) => try_values!(view, Time),
+        AnySlice::NullableTimestamp(view) => try_values!(view, Timestamp),
+        AnySlice::NullableF64(view) => nullable_values!(view, F64, |v| OdbcColumnValue::F64(*v)),
+        AnySlice::NullableF32(view) => nullable_values!(view, F32, |v| OdbcColumnValue::F32(*v)),
+        AnySlice::NullableI8(view) => nullable_values!(view, I8, |v| OdbcColumnValue::I8(*v)),
+        AnySlice::NullableI16(view) => nullable_values!(view, I16, |v| OdbcColumnValue::I16(*v)),
+        AnySlice::NullableI32(view) => nullable_values!(view, I32, |v| OdbcColumnValue::I32(*v)),
+        AnySlice::NullableI64(view) => nullable_values!(view, I64, |v| OdbcColumnValue::I64(*v)),
+        AnySlice::NullableU8(view) => nullable_values!(view, U8, |v| OdbcColumnValue::U8(*v)),
+        AnySlice::NullableBit(view) => {
+            nullable_values!(view, Bit, |v| OdbcColumnValue::Bit(v.as_bool()))
+        }
+    }
+}
+
+/// Fail on the first value which can't be converted, e.g. a date out of range.
+impl TryConvert<Vec<OdbcColumnItem>> for AnySlice<'_> {
+    type Error = OdbcStdError;
+
+    fn try_convert(self) -> Result<Vec<OdbcColumnItem>, Self::Error> {
+        let (odbc_type, values) = column_values(self);
+        values
+            .into_iter()
+            .enumerate()
+            .map(|(row, value)| match value {
+                Ok(value) => Ok(OdbcColumnItem::new(odbc_type, value)),
+                Err(e) => Err(OdbcStdError::TypeConversionError(format!(
+                    "convert row {row} from `{}` error:{}",
+                    e.raw, e.cause
+                ))),
+            })
+            .collect()
+    }
+}
+
+macro_rules! try_convert_integer {
+    ($($t:ty),+) => {
+        $(
+            impl TryConvert<$t> for &OdbcColumnValue {
+                type Error = OdbcStdError;
+
+                fn try_convert(self) -> Result<$t, Self::Error> {
+                    let value = match self {
+                        OdbcColumnValue::I8(v) => <$t>::try_from(*v).map_err(|_| value_conversion_error(self, stringify!($t)))?,
+                        OdbcColumnValue::I16(v) => <$t>::try_from(*v).map_err(|_| value_conversion_error(self, stringify!($t)))?,
+                        OdbcColumnValue::I32(v) => <$t>::try_from(*v).map_err(|_| value_conversion_error(self, stringify!($t)))?,
+                        OdbcColumnValue::I64(v) => <$t>::try_from(*v).map_err(|_| value_conversion_error(self, stringify!($t)))?,
+                        OdbcColumnValue::U8(v) => <$t>::try_from(*v).map_err(|_| value_conversion_error(self, stringify!($t)))?,
+                        OdbcColumnValue::Bit(v) => <$t>::from(*v),
+                        OdbcColumnValue::Text(v) => v.trim().parse::<$t>()?,
+                        _ => return Err(value_conversion_error(self, stringify!($t))),
+                    };
+                    Ok(value)
                 }
-                buffer
             }
-            AnySlice::NullableDate(view) => {
-                let (values, indicators) = view.raw_values();
-                let values = values.to_vec();
-
-                values
-                    .iter()
-                    .enumerate()
-                    .map(|(index, value)| {
-                        if indicators[index] != NULL_DATA {
-                            let val = value.try_convert().unwrap();
-                            OdbcColumnItem {
-                                odbc_type: OdbcColumnType::Date,
-                                value: Some(BytesMut::from(val.to_string().as_bytes())),
-                            }
-                        } else {
-                            OdbcColumnItem {
-                                odbc_type: OdbcColumnType::Date,
-                                value: None,
-                            }
-                        }
-                    })
-                    .collect()
-            }
-            AnySlice::NullableTime(view) => {
-                let (values, indicators) = view.raw_values();
-                let values = values.to_vec();
-
-                values
-                    .iter()
-                    .enumerate()
-                    .map(|(index, value)| {
-                        if indicators[index] != NULL_DATA {
-                            let val = value.try_convert().unwrap();
-                            OdbcColumnItem {
-                                odbc_type: OdbcColumnType::Time,
-                                value: Some(BytesMut::from(val.to_string().as_bytes())),
-                            }
-                        } else {
-                            OdbcColumnItem {
-                                odbc_type: OdbcColumnType::Time,
-                                value: None,
-                            }
-                        }
-                    })
-                    .collect()
-            }
-            AnySlice::NullableTimestamp(view) => {
-                let (values, indicators) = view.raw_values();
-                let values = values.to_vec();
-
-                values
-                    .iter()
-                    .enumerate()
-                    .map(|(index, value)| {
-                        if indicators[index] != NULL_DATA {
-                            let val: time::PrimitiveDateTime = value.try_convert().unwrap();
-                            OdbcColumnItem {
-                                odbc_type: OdbcColumnType::Timestamp,
-                                value: Some(BytesMut::from(val.to_string().as_bytes())),
-                            }
-                        } else {
-                            OdbcColumnItem {
-                                odbc_type: OdbcColumnType::Timestamp,
-                                value: None,
-                            }
-                        }
-                    })
-                    .collect()
-            }
-            AnySlice::NullableF64(view) => {
-                let (values, indicators) = view.raw_values();
-                let values = values.to_vec();
-
-                values
-                    .iter()
-                    .enumerate()
-                    .map(|(index, value)| {
-                        if indicators[index] != NULL_DATA {
-                            OdbcColumnItem {
-                                odbc_type: OdbcColumnType::F64,
-                                value: Some(BytesMut::from(value.to_string().as_bytes())),
-                            }
-                        } else {
-                            OdbcColumnItem {
-                                odbc_type: OdbcColumnType::F64,
-                                value: None,
-                            }
-                        }
-                    })
-                    .collect()
-            }
-            AnySlice::NullableF32(view) => {
-                let (values, indicators) = view.raw_values();
-                let values = values.to_vec();
-
-                values
-                    .iter()
-                    .enumerate()
-                    .map(|(index, value)| {
-                        if indicators[index] != NULL_DATA {
-                            OdbcColumnItem {
-                                odbc_type: OdbcColumnType::F32,
-                                value: Some(BytesMut::from(value.to_string().as_bytes())),
-                            }
-                        } else {
-                            OdbcColumnItem {
-                                odbc_type: OdbcColumnType::F32,
-                                value: None,
-                            }
-                        }
-                    })
-                    .collect()
-            }
-            AnySlice::NullableI8(view) => {
-                let (values, indicators) = view.raw_values();
-                let values = values.to_vec();
-
-                values
-                    .iter()
-                    .enumerate()
-                    .map(|(index, value)| {
-                        if indicators[index] != NULL_DATA {
-                            OdbcColumnItem {
-                                odbc_type: OdbcColumnType::I8,
-                                value: Some(BytesMut::from(value.to_string().as_bytes())),
-                            }
-                        } else {
-                            OdbcColumnItem {
-                                odbc_type: OdbcColumnType::I8,
-                                value: None,
-                            }
-                        }
-                    })
-                    .collect()
-            }
-            AnySlice::NullableI16(view) => {
-                let (values, indicators) = view.raw_values();
-                let values = values.to_vec();
-
-                values
-                    .iter()
-                    .enumerate()
-                    .map(|(index, value)| {
-                        if indicators[index] != NULL_DATA {
-                            OdbcColumnItem {
-                                odbc_type: OdbcColumnType::I16,
-                                value: Some(BytesMut::from(value.to_string().as_bytes())),
-                            }
-                        } else {
-                            OdbcColumnItem {
-                                odbc_type: OdbcColumnType::I16,
-                                value: None,
-                            }
-                        }
-                    })
-                    .collect()
-            }
-            AnySlice::NullableI32(view) => {
-                let (values, indicators) = view.raw_values();
-                let values = values.to_vec();
-
-                values
-                    .iter()
-                    .enumerate()
-                    .map(|(index, value)| {
-                        if indicators[index] != NULL_DATA {
-                            OdbcColumnItem {
-                                odbc_type: OdbcColumnType::I32,
-                                value: Some(BytesMut::from(value.to_string().as_bytes())),
-                            }
-                        } else {
-                            OdbcColumnItem {
-                                odbc_type: OdbcColumnType::I32,
-                                value: None,
-                            }
-                        }
-                    })
-                    .collect()
-            }
-            AnySlice::NullableI64(view) => {
-                let (values, indicators) = view.raw_values();
-                let values = values.to_vec();
-
-                values
-                    .iter()
-                    .enumerate()
-                    .map(|(index, value)| {
-                        if indicators[index] != NULL_DATA {
-                            OdbcColumnItem {
-                                odbc_type: OdbcColumnType::I64,
-                                value: Some(BytesMut::from(value.to_string().as_bytes())),
-                            }
-                        } else {
-                            OdbcColumnItem {
-                                odbc_type: OdbcColumnType::I64,
-                                value: None,
-                            }
-                        }
-                    })
-                    .collect()
-            }
-            AnySlice::NullableU8(view) => {
-                let (values, indicators) = view.raw_values();
-                let values = values.to_vec();
-
-                values
-                    .iter()
-                    .enumerate()
-                    .map(|(index, value)| {
-                        if indicators[index] != NULL_DATA {
-                            OdbcColumnItem {
-                                odbc_type: OdbcColumnType::U8,
-                                value: Some(BytesMut::from(vec![*value].as_slice())),
-                            }
-                        } else {
-                            OdbcColumnItem {
-                                odbc_type: OdbcColumnType::U8,
-                                value: None,
-                            }
-                        }
-                    })
-                    .collect()
-            }
-            AnySlice::NullableBit(view) => {
-                let (values, indicators) = view.raw_values();
-                let values = values.to_vec();
-
-                values
-                    .iter()
-                    .enumerate()
-                    .map(|(index, value)| {
-                        if indicators[index] != NULL_DATA {
-                            OdbcColumnItem {
-                                odbc_type: OdbcColumnType::Bit,
-                                value: Some(BytesMut::from(value.as_bool().to_string().as_bytes())),
-                            }
-                        } else {
-                            OdbcColumnItem {
-                                odbc_type: OdbcColumnType::Bit,
-                                value: None,
-                            }
-                        }
-                    })
-                    .collect()
+        )+
+    };
+}
+
+try_convert_integer!(i8, i16, i32, i64);
+
+impl TryConvert<f32> for &OdbcColumnValue {
+    type Error = OdbcStdError;
+
+    fn try_convert(self) -> Result<f32, Self::Error> {
+        let value = match self {
+            OdbcColumnValue::F32(v) => *v,
+            OdbcColumnValue::I8(v) => f32::from(*v),
+            OdbcColumnValue::I16(v) => f32::from(*v),
+            OdbcColumnValue::U8(v) => f32::from(*v),
+            OdbcColumnValue::Text(v) => parse_to_float4(v)?,
+            _ => return Err(value_conversion_error(self, "f32")),
+        };
+        Ok(value)
+    }
+}
+
+impl TryConvert<f64> for &OdbcColumnValue {
+    type Error = OdbcStdError;
+
+    fn try_convert(self) -> Result<f64, Self::Error> {
+        let value = match self {
+            OdbcColumnValue::F64(v) => *v,
+            OdbcColumnValue::F32(v) => f64::from(*v),
+            OdbcColumnValue::I8(v) => f64::from(*v),
+            OdbcColumnValue::I16(v) => f64::from(*v),
+            OdbcColumnValue::I32(v) => f64::from(*v),
+            OdbcColumnValue::U8(v) => f64::from(*v),
+            OdbcColumnValue::Text(v) => parse_to_float8(v)?,
+            _ => return Err(value_conversion_error(self, "f64")),
+        };
+        Ok(value)
+    }
+}
+
+impl TryConvert<Numeric> for &OdbcColumnValue {
+    type Error = OdbcStdError;
+
+    fn try_convert(self) -> Result<Numeric, Self::Error> {
+        let value = match self {
+            OdbcColumnValue::Text(v) => v.parse()?,
+            OdbcColumnValue::I8(v) => Numeric::from(*v),
+            OdbcColumnValue::I16(v) => Numeric::from(*v),
+            OdbcColumnValue::I32(v) => Numeric::from(*v),
+            OdbcColumnValue::I64(v) => Numeric::from(*v),
+            OdbcColumnValue::U8(v) => Numeric::from(*v),
+            // the shortest text which reads back to the same float
+            OdbcColumnValue::F64(v) => v.to_string().parse()?,
+            OdbcColumnValue::F32(v) => v.to_string().parse()?,
+            _ => return Err(value_conversion_error(self, "numeric")),
+        };
+        Ok(value)
+    }
+}
+
+impl TryConvert<bool> for &OdbcColumnValue {
+    type Error = OdbcStdError;
+
+    fn try_convert(self) -> Result<bool, Self::Error> {
+        let value = match self {
+            OdbcColumnValue::Bit(v) => *v,
+            OdbcColumnValue::I8(v) => *v != 0,
+            OdbcColumnValue::I16(v) => *v != 0,
+            OdbcColumnValue::I32(v) => *v != 0,
+            OdbcColumnValue::I64(v) => *v != 0,
+            OdbcColumnValue::U8(v) => *v != 0,
+            OdbcColumnValue::Text(v) => parse_to_bool(v)?,
+            _ => return Err(value_conversion_error(self, "bool")),
+        };
+        Ok(value)
+    }
+}
+
+impl TryConvert<String> for &OdbcColumnValue {
+    type Error = OdbcStdError;
+
+    fn try_convert(self) -> Result<String, Self::Error> {
+        match self {
+            OdbcColumnValue::Text(v) => Ok(v.clone()),
+            other => Ok(other.to_string()),
+        }
+    }
+}
+
+impl TryConvert<Vec<u8>> for &OdbcColumnValue {
+    type Error = OdbcStdError;
+
+    fn try_convert(self) -> Result<Vec<u8>, Self::Error> {
+        match self {
+            OdbcColumnValue::Binary(v) => Ok(v.clone()),
+            OdbcColumnValue::Text(v) => Ok(v.as_bytes().to_vec()),
+            _ => Err(value_conversion_error(self, "bytes")),
+        }
+    }
+}
+
+impl TryConvert<NaiveDate> for &OdbcColumnValue {
+    type Error = OdbcStdError;
+
+    fn try_convert(self) -> Result<NaiveDate, Self::Error> {
+        match self {
+            OdbcColumnValue::Date(v) => Ok(*v),
+            OdbcColumnValue::Timestamp(v) => Ok(v.date()),
+            OdbcColumnValue::Text(v) => parse_to_date(v),
+            _ => Err(value_conversion_error(self, "date")),
+        }
+    }
+}
+
+impl TryConvert<NaiveTime> for &OdbcColumnValue {
+    type Error = OdbcStdError;
+
+    fn try_convert(self) -> Result<NaiveTime, Self::Error> {
+        match self {
+            OdbcColumnValue::Time(v) => Ok(*v),
+            OdbcColumnValue::Timestamp(v) => Ok(v.time()),
+            OdbcColumnValue::Text(v) => parse_to_time(v),
+            _ => Err(value_conversion_error(self, "time")),
+        }
+    }
+}
+
+impl TryConvert<NaiveDateTime> for &OdbcColumnValue {
+    type Error = OdbcStdError;
+
+    fn try_convert(self) -> Result<NaiveDateTime, Self::Error> {
+        match self {
+            OdbcColumnValue::Timestamp(v) => Ok(*v),
+            OdbcColumnValue::Date(v) => Ok(v.and_time(NaiveTime::MIN)),
+            OdbcColumnValue::Text(v) => parse_to_data_time(v),
+            _ => Err(value_conversion_error(self, "timestamp")),
+        }
+    }
+}
+
+/// A timestamp with time zone, a value without offset is in the given session offset.
+impl TryConvert<DateTime<FixedOffset>> for (&OdbcColumnValue, FixedOffset) {
+    type Error = OdbcStdError;
+
+    fn try_convert(self) -> Result<DateTime<FixedOffset>, Self::Error> {
+        let (value, offset) = self;
+        match value {
+            OdbcColumnValue::Text(v) => parse_to_data_time_tz(v, offset),
+            OdbcColumnValue::Timestamp(_) | OdbcColumnValue::Date(_) => {
+                with_offset(value.try_convert()?, offset)
             }
+            _ => Err(value_conversion_error(value, "timestamp with time zone")),
+        }
+    }
+}
+
+/// A time with time zone, a value without offset is in the given session offset.
+impl TryConvert<TimeTz> for (&OdbcColumnValue, FixedOffset) {
+    type Error = OdbcStdError;
+
+    fn try_convert(self) -> Result<TimeTz, Self::Error> {
+        let (value, offset) = self;
+        match value {
+            OdbcColumnValue::Text(v) => parse_to_time_tz(v, offset),
+            OdbcColumnValue::Time(v) => Ok(TimeTz::new(*v, offset)),
+            OdbcColumnValue::Timestamp(v) => Ok(TimeTz::new(v.time(), offset)),
+            _ => Err(value_conversion_error(value, "time with time zone")),
+        }
+    }
+}
+
+fn value_conversion_error(value: &OdbcColumnValue, target: &str) -> OdbcStdError {
+    OdbcStdError::TypeConversionError(format!("{target} from value:{value:?}"))
+}
+
+/// Convert `odbc_api::sys::Date` to `chrono::NaiveDate`
+impl TryConvert<NaiveDate> for &Date {
+    type Error = OdbcStdError;
+
+    fn try_convert(self) -> Result<NaiveDate, Self::Error> {
+        NaiveDate::from_ymd_opt(self.year as i32, self.month as u32, self.day as u32)
+            .ok_or_else(|| OdbcStdError::TypeConversionError(format!("date from:{self:?}")))
+    }
+}
+
+/// Convert `odbc_api::sys::Time` to `chrono::NaiveTime`
+impl TryConvert<NaiveTime> for &Time {
+    type Error = OdbcStdError;
+
+    fn try_convert(self) -> Result<NaiveTime, Self::Error> {
+        NaiveTime::from_hms_opt(self.hour as u32, self.minute as u32, self.second as u32)
+            .ok_or_else(|| OdbcStdError::TypeConversionError(format!("time from:{self:?}")))
+    }
+}
+
+/// Convert `odbc_api::sys::Timestamp` to `chrono::NaiveDateTime`
+impl TryConvert<NaiveDateTime> for &Timestamp {
+    type Error = OdbcStdError;
+
+    fn try_convert(self) -> Result<NaiveDateTime, Self::Error> {
+        NaiveDate::from_ymd_opt(self.year as i32, self.month as u32, self.day as u32)
+            .and_then(|date| {
+                date.and_hms_nano_opt(
+                    self.hour as u32,
+                    self.minute as u32,
+                    self.second as u32,
+                    self.fraction,
+                )
+            })
+            .ok_or_else(|| OdbcStdError::TypeConversionError(format!("timestamp from:{self:?}")))
+    }
+}
+
+/// Convert `chrono::NaiveDate` to `odbc_api::sys::Date`
+impl Convert<Date> for &NaiveDate {
+    fn convert(self) -> Date {
+        Date {
+            year: self.year() as i16,
+            month: self.month() as u16,
+            day: self.day() as u16,
+        }
+    }
+}
+
+/// The least fractional second digits of a bound time or timestamp, the microseconds of pg and
+/// Dameng, a value with more digits is bound with them.
+pub(crate) const TIMESTAMP_PRECISION: u16 = 6;
+
+/// Convert `chrono::NaiveTime` to `odbc_api::sys::Time`, the fraction of second is dropped.
+impl Convert<Time> for &NaiveTime {
+    fn convert(self) -> Time {
+        Time {
+            hour: self.hour() as u16,
+            minute: self.minute() as u16,
+            second: self.second() as u16,
+        }
+    }
+}
+
+/// Convert `chrono::NaiveDateTime` to `odbc_api::sys::Timestamp`, the fraction is in nanoseconds.
+impl Convert<Timestamp> for &NaiveDateTime {
+    fn convert(self) -> Timestamp {
+        Timestamp {
+            year: self.year() as i16,
+            month: self.month() as u16,
+            day: self.day() as u16,
+            hour: self.hour() as u16,
+            minute: self.minute() as u16,
+            second: self.second() as u16,
+            fraction: self.nanosecond(),
         }
     }
 }
@@ -574,7 +686,8 @@ impl TryConvert<time::Date> for Date {
     }
 }
 
-/// Convert `odbc_api::sys::Time` to `time::Time`
+/// Convert `odbc_api::sys::Time` to `time::Time`, the struct has no fraction of second, see
+/// `TryConvert<time::Time> for (Time, u32)`.
 ///
 /// # Example
 ///
@@ -601,6 +714,7 @@ impl TryConvert<time::Time> for Time {
     }
 }
 
+/// Convert `odbc_api::sys::Time` and its nanoseconds to `time::Time`.
 impl TryConvert<time::Time> for (Time, u32) {
     type Error = time::Error;
     fn try_convert(self) -> Result<time::Time, Self::Error> {
@@ -645,3 +759,155 @@ impl TryConvert<time::PrimitiveDateTime> for Timestamp {
         Ok(time::PrimitiveDateTime::new(date, time))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extension::util::fraction_digits;
+    use odbc_common::odbc_api::buffers::{AnyBuffer, ColumnBuffer, TextColumn};
+
+    #[test]
+    fn test_any_slice_try_convert() {
+        let dates = [
+            Date {
+                year: 2022,
+                month: 10,
+                day: 1,
+            },
+            Date {
+                year: 2022,
+                month: 13,
+                day: 1,
+            },
+        ];
+        let items: Vec<OdbcColumnItem> = AnySlice::Date(&dates[..1]).try_convert().unwrap();
+        assert_eq!(
+            items[0].value,
+            Some(OdbcColumnValue::Date(
+                NaiveDate::from_ymd_opt(2022, 10, 1).unwrap()
+            ))
+        );
+        // an invalid value is an error instead of a panic
+        let err =
+            TryConvert::<Vec<OdbcColumnItem>>::try_convert(AnySlice::Date(&dates)).unwrap_err();
+        assert!(err.to_string().contains("convert row 1"), "{err}");
+
+        let mut text = TextColumn::new(2, 4);
+        text.set_value(0, Some(b"abc"));
+        text.set_value(1, Some(&[0x61, 0xff]));
+        let buffer = AnyBuffer::Text(text);
+        let (_, values) = column_values(buffer.view(2));
+        assert_eq!(
+            values[0],
+            Ok(Some(OdbcColumnValue::Text("abc".to_string())))
+        );
+        assert!(values[1].is_err());
+    }
+
+    #[test]
+    fn test_column_value_try_convert() {
+        let v = OdbcColumnValue::I32(42);
+        assert_eq!(TryConvert::<i64>::try_convert(&v).unwrap(), 42);
+        assert_eq!(TryConvert::<i16>::try_convert(&v).unwrap(), 42);
+        assert!(TryConvert::<i8>::try_convert(&OdbcColumnValue::I32(300)).is_err());
+        assert!(TryConvert::<NaiveDate>::try_convert(&v).is_err());
+
+        let v = OdbcColumnValue::Text(" 12 ".to_string());
+        assert_eq!(TryConvert::<i32>::try_convert(&v).unwrap(), 12);
+        assert!(TryConvert::<i32>::try_convert(&OdbcColumnValue::Text("12.5".into())).is_err());
+
+        let date = NaiveDate::from_ymd_opt(2022, 10, 1).unwrap();
+        let v = OdbcColumnValue::Timestamp(date.and_hms_opt(8, 0, 0).unwrap());
+        assert_eq!(TryConvert::<NaiveDate>::try_convert(&v).unwrap(), date);
+        assert_eq!(
+            TryConvert::<String>::try_convert(&v).unwrap(),
+            "2022-10-01 08:00:00"
+        );
+    }
+
+    #[test]
+    fn test_time_zone_try_convert() {
+        let offset =
+            |hours: i32, minutes: i32| FixedOffset::east_opt(hours * 3600 + minutes * 60).unwrap();
+        let session = offset(8, 0);
+
+        let text = |v: &str| OdbcColumnValue::Text(v.to_string());
+        let timestamp =
+            |v: &OdbcColumnValue| -> DateTime<FixedOffset> { (v, session).try_convert().unwrap() };
+        let ts = timestamp(&text("2022-10-24 17:28:26.308000 -03:30"));
+        assert_eq!(ts.to_rfc3339(), "2022-10-24T17:28:26.308-03:30");
+        assert_eq!(
+            crate::extension::util::format_data_time_tz(&ts),
+            "2022-10-24 17:28:26.308 -03:30"
+        );
+        // without offset, e.g. a TIMESTAMP WITH LOCAL TIME ZONE, it's in the session time zone
+        let ts = timestamp(&text("2022-10-24 17:28:26"));
+        assert_eq!(ts.to_rfc3339(), "2022-10-24T17:28:26+08:00");
+        let naive = NaiveDate::from_ymd_opt(2022, 10, 24)
+            .unwrap()
+            .and_hms_opt(17, 28, 26)
+            .unwrap();
+        assert_eq!(timestamp(&OdbcColumnValue::Timestamp(naive)), ts);
+
+        let time = |v: &OdbcColumnValue| -> TimeTz { (v, session).try_convert().unwrap() };
+        let t = time(&text("17:28:26 +05:30"));
+        assert_eq!(t.offset(), offset(5, 30));
+        assert_eq!(t.to_string(), "17:28:26+05:30");
+        let t = time(&OdbcColumnValue::Time(naive.time()));
+        assert_eq!(t.offset(), session);
+        assert_eq!(t.time(), naive.time());
+
+        let v = OdbcColumnValue::I32(1);
+        assert!(TryConvert::<TimeTz>::try_convert((&v, session)).is_err());
+        assert!(
+            TryConvert::<DateTime<FixedOffset>>::try_convert((&text("17:28"), session)).is_err()
+        );
+    }
+
+    #[test]
+    fn test_sys_timestamp_try_convert() {
+        let ts = Timestamp {
+            year: 2022,
+            month: 2,
+            day: 30,
+            hour: 0,
+            minute: 0,
+            second: 0,
+            fraction: 0,
+        };
+        assert!(TryConvert::<NaiveDateTime>::try_convert(&ts).is_err());
+    }
+
+    #[test]
+    fn test_fraction_precision() {
+        let ts = Timestamp {
+            year: 2022,
+            month: 10,
+            day: 24,
+            hour: 17,
+            minute: 28,
+            second: 26,
+            fraction: 308_123_456,
+        };
+        let naive: NaiveDateTime = (&ts).try_convert().unwrap();
+        let value = OdbcColumnValue::Timestamp(naive).truncate_fraction(3);
+        assert_eq!(value.to_string(), "2022-10-24 17:28:26.308");
+        let value = OdbcColumnValue::Timestamp(naive).truncate_fraction(6);
+        assert_eq!(value.to_string(), "2022-10-24 17:28:26.308123");
+        assert_eq!(fraction_digits(ts.fraction), 9);
+        assert_eq!(fraction_digits(308_000_000), 3);
+        assert_eq!(fraction_digits(0), 0);
+
+        let column =
+            OdbcColumnDesc::new("C1".to_string(), DataType::Timestamp { precision: 0 }, true);
+        assert_eq!(column.fraction_precision(), Some(0));
+        let value = column.with_precision(Some(OdbcColumnValue::Timestamp(naive)));
+        assert_eq!(value.unwrap().to_string(), "2022-10-24 17:28:26");
+
+        // the fraction of other columns is kept
+        let column = OdbcColumnDesc::new("C2".to_string(), DataType::Varchar { length: 30 }, true);
+        assert_eq!(column.fraction_precision(), None);
+        let value = column.with_precision(Some(OdbcColumnValue::Timestamp(naive)));
+        assert_eq!(value, Some(OdbcColumnValue::Timestamp(naive)));
+    }
+}
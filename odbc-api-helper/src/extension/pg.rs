@@ -1,31 +1,37 @@
+use crate::executor::conversion::{resolve_row, Cell, ConversionError, ConversionPolicy};
 use crate::executor::database::Options;
+use crate::executor::prepare::{CheckParam, ParamKind};
 use crate::executor::query::QueryResult;
 use crate::executor::statement::SqlValue;
-use crate::extension::odbc::{OdbcColumnDesc, OdbcColumnItem, OdbcColumnType};
+use crate::extension::odbc::{
+    OdbcColumnDesc, OdbcColumnItem, OdbcColumnValue, TIMESTAMP_PRECISION,
+};
+use crate::extension::util::{
+    format_data_time_tz, format_time, format_time_tz, fraction_digits, truncate_fraction,
+};
 use crate::{Convert, TryConvert};
-use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Offset, Timelike, Utc};
 use dameng_helper::odbc_api::DataType;
 use either::Either;
 use odbc_common::error::{OdbcStdError, OdbcStdResult};
 use odbc_common::odbc_api::buffers::BufferDesc;
-use odbc_common::odbc_api::parameter::InputParameter;
+use odbc_common::odbc_api::parameter::{InputParameter, WithDataType};
+use odbc_common::odbc_api::sys::Date;
 use odbc_common::odbc_api::Bit;
-use odbc_common::odbc_api::IntoParameter;
+use odbc_common::odbc_api::{IntoParameter, Nullable};
+use pg_helper::numeric::Numeric;
 use pg_helper::table::PgTableItem;
+use pg_helper::timetz::TimeTz;
 use postgres_types::{Oid, Type as PgType};
 use std::any::Any;
 use std::collections::BTreeMap;
 
 use crate::executor::table::TableDescResult;
 use crate::executor::SupportDatabase;
-use crate::extension::util::{
-    parse_to_bool, parse_to_data_time, parse_to_date, parse_to_float4, parse_to_float8,
-    parse_to_i8, parse_to_int2, parse_to_int4, parse_to_int8, parse_to_string, parse_to_time,
-};
 use dameng_helper::table::DmTableDesc;
 use pg_helper::table::PgTableDesc;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum PgValueInput {
     Int2(Option<i16>),
     Int4(Option<i32>),
@@ -38,14 +44,55 @@ pub enum PgValueInput {
     Bool(Option<bool>),
     Bytea(Option<Vec<u8>>),
     Time(Option<NaiveTime>),
-    Timez(Option<NaiveTime>),
+    Timez(Option<TimeTz>),
     Timestamp(Option<NaiveDateTime>),
-    Timestampz(Option<NaiveDateTime>),
+    Timestampz(Option<DateTime<FixedOffset>>),
     Date(Option<NaiveDate>),
-    Numeric(Option<i32>),
+    Numeric(Option<Numeric>),
     Name(Option<String>),
 }
 
+impl PgValueInput {
+    /// The same variant with a NULL value, e.g. the type template of an OUT parameter.
+    pub fn without_value(&self) -> Self {
+        match self {
+            Self::Int2(_) => Self::Int2(None),
+            Self::Int4(_) => Self::Int4(None),
+            Self::Int8(_) => Self::Int8(None),
+            Self::Float4(_) => Self::Float4(None),
+            Self::Float8(_) => Self::Float8(None),
+            Self::Char(_) => Self::Char(None),
+            Self::Varchar(_) => Self::Varchar(None),
+            Self::Text(_) => Self::Text(None),
+            Self::Bool(_) => Self::Bool(None),
+            Self::Bytea(_) => Self::Bytea(None),
+            Self::Time(_) => Self::Time(None),
+            Self::Timez(_) => Self::Timez(None),
+            Self::Timestamp(_) => Self::Timestamp(None),
+            Self::Timestampz(_) => Self::Timestampz(None),
+            Self::Date(_) => Self::Date(None),
+            Self::Numeric(_) => Self::Numeric(None),
+            Self::Name(_) => Self::Name(None),
+        }
+    }
+}
+
+impl PgValueInput {
+    /// Cut the fractional seconds of a time or timestamp to `precision` digits, the other
+    /// values are kept.
+    pub fn truncate_fraction(self, precision: u16) -> Self {
+        match self {
+            Self::Time(v) => Self::Time(v.map(|v| truncate_fraction(v, precision))),
+            Self::Timez(v) => Self::Timez(
+                v.map(|v| TimeTz::new(truncate_fraction(v.time(), precision), v.offset())),
+            ),
+            Self::Timestamp(v) => Self::Timestamp(v.map(|v| truncate_fraction(v, precision))),
+            Self::Timestampz(v) => Self::Timestampz(v.map(|v| truncate_fraction(v, precision))),
+            v => v,
+        }
+    }
+}
+
 impl SqlValue for PgValueInput {
     fn to_value(self) -> Either<Box<dyn InputParameter>, Box<dyn Any>> {
         macro_rules! left_param {
@@ -56,7 +103,9 @@ impl SqlValue for PgValueInput {
 
         match self {
             Self::Int2(i) => left_param!(i.into_parameter()),
-            Self::Int4(i) | Self::Numeric(i) => left_param!(i.into_parameter()),
+            Self::Int4(i) => left_param!(i.into_parameter()),
+            // bound as text, the driver converts it without losing precision
+            Self::Numeric(i) => left_param!(i.map(|i| i.to_string()).into_parameter()),
             Self::Int8(i) => left_param!(i.into_parameter()),
             Self::Float4(i) => left_param!(i.into_parameter()),
             Self::Float8(i) => left_param!(i.into_parameter()),
@@ -64,13 +113,79 @@ impl SqlValue for PgValueInput {
             Self::Varchar(i) | Self::Text(i) | Self::Name(i) => left_param!(i.into_parameter()),
             Self::Bool(i) => left_param!(i.map(Bit::from_bool).into_parameter()),
             Self::Bytea(bytes) => left_param!(bytes.into_parameter()),
-            Self::Time(i) | Self::Timez(i) => {
-                left_param!(i.map(|i| i.to_string()).into_parameter())
+            // the time struct has no fraction of second, a time with one is bound from the
+            // timestamp struct, whose date the driver ignores
+            Self::Time(i) => {
+                let (text, data_type) = time_parameter(i);
+                left_param!(WithDataType {
+                    value: text.into_parameter(),
+                    data_type,
+                })
             }
-            Self::Timestamp(i) | Self::Timestampz(i) => {
-                left_param!(i.map(|i| i.to_string()).into_parameter())
+            // bound as text with the offset, the database converts it to the column time zone
+            Self::Timez(i) => left_param!(i.as_ref().map(format_time_tz).into_parameter()),
+            Self::Timestamp(i) => left_param!(WithDataType {
+                value: i
+                    .as_ref()
+                    .map_or_else(Nullable::null, |i| Nullable::new(i.convert())),
+                data_type: DataType::Timestamp {
+                    precision: bind_precision(i.map_or(0, |i| i.nanosecond())),
+                },
+            }),
+            Self::Timestampz(i) => {
+                left_param!(i.as_ref().map(format_data_time_tz).into_parameter())
             }
-            Self::Date(i) => left_param!(i.map(|i| i.to_string()).into_parameter()),
+            Self::Date(i) => left_param!(i.as_ref().map(Convert::<Date>::convert).into_parameter()),
+        }
+    }
+}
+
+/// The precision of a bound time or timestamp, at least [`TIMESTAMP_PRECISION`] and every
+/// fractional second digit of the value, e.g. 9 for the nanoseconds of a TIMESTAMP(9).
+pub(crate) fn bind_precision(nanos: u32) -> i16 {
+    TIMESTAMP_PRECISION.max(fraction_digits(nanos)) as i16
+}
+
+/// A time is bound as text declared as TIME with the [`bind_precision`] of its fraction, the
+/// time struct has no fraction of second.
+pub(crate) fn time_parameter(v: Option<NaiveTime>) -> (Option<String>, DataType) {
+    let precision = bind_precision(v.map_or(0, |v| v.nanosecond()));
+    let text = v.map(|v| format_time(&v, precision as u16));
+    (text, DataType::Time { precision })
+}
+
+impl CheckParam for PgValueInput {
+    fn param_kind(&self) -> ParamKind {
+        match self {
+            Self::Int2(_) | Self::Int4(_) | Self::Int8(_) | Self::Char(_) => ParamKind::Integer,
+            Self::Numeric(_) => ParamKind::Decimal,
+            Self::Float4(_) | Self::Float8(_) => ParamKind::Float,
+            Self::Varchar(_) | Self::Text(_) | Self::Name(_) => ParamKind::Text,
+            Self::Bool(_) => ParamKind::Bool,
+            Self::Bytea(_) => ParamKind::Binary,
+            Self::Time(_) | Self::Timez(_) => ParamKind::Time,
+            Self::Timestamp(_) | Self::Timestampz(_) => ParamKind::Timestamp,
+            Self::Date(_) => ParamKind::Date,
+        }
+    }
+
+    fn is_null(&self) -> bool {
+        match self {
+            Self::Int2(v) => v.is_none(),
+            Self::Int4(v) => v.is_none(),
+            Self::Numeric(v) => v.is_none(),
+            Self::Int8(v) => v.is_none(),
+            Self::Float4(v) => v.is_none(),
+            Self::Float8(v) => v.is_none(),
+            Self::Char(v) => v.is_none(),
+            Self::Varchar(v) | Self::Text(v) | Self::Name(v) => v.is_none(),
+            Self::Bool(v) => v.is_none(),
+            Self::Bytea(v) => v.is_none(),
+            Self::Time(v) => v.is_none(),
+            Self::Timez(v) => v.is_none(),
+            Self::Timestamp(v) => v.is_none(),
+            Self::Timestampz(v) => v.is_none(),
+            Self::Date(v) => v.is_none(),
         }
     }
 }
@@ -87,6 +202,26 @@ pub struct PgColumn {
     pub pg_type: PgType,
     pub oid: Oid,
     pub nullable: bool,
+    // the scale of a NUMERIC column with a declared precision, the values are padded to it
+    pub scale: Option<u16>,
+    // the fractional second digits of a TIME/TIMETZ/TIMESTAMP/TIMESTAMPTZ column, the values are
+    // cut to it
+    pub precision: Option<u16>,
+}
+
+impl PgColumn {
+    /// A TIMETZ/TIMESTAMPTZ column, see [`Options::session_offset`].
+    pub fn is_time_zone_aware(&self) -> bool {
+        matches!(self.pg_type, PgType::TIMETZ | PgType::TIMESTAMPTZ)
+    }
+
+    /// Cut the fractional seconds of a time or timestamp to the precision of the column.
+    fn with_precision<T: Timelike>(&self, v: T) -> T {
+        match self.precision {
+            Some(precision) => truncate_fraction(v, precision),
+            None => v,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -100,23 +235,48 @@ impl PgColumnItem {
     }
 }
 
-impl Convert<PgColumn> for OdbcColumnDesc {
-    fn convert(self) -> PgColumn {
-        let pg_type: PgType = (self.data_type, self.nullable).convert();
+impl TryConvert<PgColumn> for OdbcColumnDesc {
+    type Error = OdbcStdError;
+
+    fn try_convert(self) -> OdbcStdResult<PgColumn, Self::Error> {
+        let pg_type: PgType = (self.data_type, self.nullable).try_convert().map_err(|_| {
+            OdbcStdError::TypeConversionError(format!(
+                "pg type from column `{}` of {:?}",
+                self.name, self.data_type
+            ))
+        })?;
         let oid = pg_type.oid();
-        PgColumn {
+        let scale = match self.data_type {
+            DataType::Numeric { scale, .. } | DataType::Decimal { scale, .. } => {
+                u16::try_from(scale).ok()
+            }
+            _ => None,
+        };
+        Ok(PgColumn {
+            precision: self.fraction_precision(),
             name: self.name,
             pg_type,
             oid,
             nullable: self.nullable,
-        }
+            scale,
+        })
     }
 }
 
-impl Convert<PgType> for (DataType, bool) {
-    fn convert(self) -> PgType {
-        let desc = BufferDesc::from_data_type(self.0, self.1).unwrap();
-        match desc {
+impl TryConvert<PgType> for (DataType, bool) {
+    type Error = OdbcStdError;
+
+    fn try_convert(self) -> OdbcStdResult<PgType, Self::Error> {
+        match self.0 {
+            DataType::Numeric { .. } | DataType::Decimal { .. } => return Ok(PgType::NUMERIC),
+            // read as text, see `TryConvert<BufferDesc> for (&OdbcColumnDesc, &Options)`
+            DataType::Other { .. } => return Ok(PgType::TEXT),
+            _ => {}
+        }
+        let desc = BufferDesc::from_data_type(self.0, self.1).ok_or_else(|| {
+            OdbcStdError::TypeConversionError(format!("pg type from data type:{:?}", self.0))
+        })?;
+        let pg_type = match desc {
             BufferDesc::Binary { .. } => PgType::BYTEA,
             BufferDesc::Text { .. } => PgType::TEXT,
             BufferDesc::WText { .. } => PgType::TEXT,
@@ -129,84 +289,166 @@ impl Convert<PgType> for (DataType, bool) {
             BufferDesc::I16 { .. } => PgType::INT2,
             BufferDesc::I32 { .. } => PgType::INT4,
             BufferDesc::I64 { .. } => PgType::INT8,
-            BufferDesc::U8 { .. } => {
-                panic!("not coverage U8");
-            }
+            BufferDesc::U8 { .. } => PgType::INT2,
             BufferDesc::Bit { .. } => PgType::BOOL,
-        }
+        };
+        Ok(pg_type)
     }
 }
 
 /// referring to link:`<https://docs.rs/postgres-protocol/0.6.4/postgres_protocol/types/index.html#functions>`
+impl Convert<PgValueInput> for OdbcColumnValue {
+    fn convert(self) -> PgValueInput {
+        match self {
+            OdbcColumnValue::Text(v) => PgValueInput::Text(Some(v)),
+            OdbcColumnValue::Binary(v) => PgValueInput::Bytea(Some(v)),
+            OdbcColumnValue::Date(v) => PgValueInput::Date(Some(v)),
+            OdbcColumnValue::Time(v) => PgValueInput::Time(Some(v)),
+            OdbcColumnValue::Timestamp(v) => PgValueInput::Timestamp(Some(v)),
+            OdbcColumnValue::F64(v) => PgValueInput::Float8(Some(v)),
+            OdbcColumnValue::F32(v) => PgValueInput::Float4(Some(v)),
+            OdbcColumnValue::I8(v) => PgValueInput::Char(Some(v)),
+            OdbcColumnValue::I16(v) => PgValueInput::Int2(Some(v)),
+            OdbcColumnValue::I32(v) => PgValueInput::Int4(Some(v)),
+            OdbcColumnValue::I64(v) => PgValueInput::Int8(Some(v)),
+            OdbcColumnValue::U8(v) => PgValueInput::Int2(Some(i16::from(v))),
+            OdbcColumnValue::Bit(v) => PgValueInput::Bool(Some(v)),
+        }
+    }
+}
+
+/// The field types of a derived `ToParams`, see [`orm`](crate::orm).
+macro_rules! pg_value_input_from {
+    ($($t:ty => $variant:ident),+ $(,)?) => {
+        $(
+            impl From<$t> for PgValueInput {
+                fn from(value: $t) -> Self {
+                    PgValueInput::$variant(Some(value))
+                }
+            }
+
+            impl From<Option<$t>> for PgValueInput {
+                fn from(value: Option<$t>) -> Self {
+                    PgValueInput::$variant(value)
+                }
+            }
+        )+
+    };
+}
+
+pg_value_input_from!(
+    i8 => Char,
+    i16 => Int2,
+    i32 => Int4,
+    i64 => Int8,
+    f32 => Float4,
+    f64 => Float8,
+    bool => Bool,
+    String => Varchar,
+    Vec<u8> => Bytea,
+    NaiveDate => Date,
+    NaiveTime => Time,
+    NaiveDateTime => Timestamp,
+    TimeTz => Timez,
+    DateTime<FixedOffset> => Timestampz,
+    Numeric => Numeric,
+);
+
 impl Convert<PgColumnItem> for OdbcColumnItem {
     fn convert(self) -> PgColumnItem {
-        let value = match self.odbc_type {
-            OdbcColumnType::Text | OdbcColumnType::WText => self
-                .value
-                .map(|x| PgValueInput::Text(Some(parse_to_string(x)))),
-            OdbcColumnType::Binary => self.value.map(|x| PgValueInput::Bytea(Some(x.to_vec()))),
-            OdbcColumnType::Date => self
-                .value
-                .map(|x| PgValueInput::Date(Some(parse_to_date(x).unwrap()))),
-            OdbcColumnType::Time => self
-                .value
-                .map(|x| PgValueInput::Time(Some(parse_to_time(x).unwrap()))),
-            OdbcColumnType::Timestamp => self
-                .value
-                .map(|x| PgValueInput::Timestamp(Some(parse_to_data_time(x).unwrap()))),
-            OdbcColumnType::F64 => self
-                .value
-                .map(|x| PgValueInput::Float8(Some(parse_to_float8(x).unwrap()))),
-            OdbcColumnType::F32 => self
-                .value
-                .map(|x| PgValueInput::Float4(Some(parse_to_float4(x).unwrap()))),
-            OdbcColumnType::I8 | OdbcColumnType::U8 => self
-                .value
-                .map(|x| PgValueInput::Char(Some(parse_to_i8(x).unwrap()))),
-            OdbcColumnType::I16 => self
-                .value
-                .map(|x| PgValueInput::Int2(Some(parse_to_int2(x).unwrap()))),
-            OdbcColumnType::I32 => self
-                .value
-                .map(|x| PgValueInput::Int4(Some(parse_to_int4(x).unwrap()))),
-            OdbcColumnType::I64 => self
-                .value
-                .map(|x| PgValueInput::Int8(Some(parse_to_int8(x).unwrap()))),
-            OdbcColumnType::Bit => self
-                .value
-                .map(|x| PgValueInput::Bool(Some(parse_to_bool(x).unwrap()))),
-        };
-        PgColumnItem::new(value)
+        PgColumnItem::new(self.value.map(|v| v.convert()))
     }
 }
 
-impl From<QueryResult> for PgQueryResult {
-    fn from(result: QueryResult) -> Self {
-        PgQueryResult {
-            columns: result.columns.into_iter().map(|x| x.convert()).collect(),
-            data: result
-                .data
-                .into_iter()
-                .map(|x| x.into_iter().map(|x| x.convert()).collect())
-                .collect(),
+/// Fail on a value which can't be converted, i.e. `ConversionPolicy::Fail`. Convert
+/// `(QueryResult, &Options)` to follow `Options.conversion` instead.
+impl TryFrom<QueryResult> for PgQueryResult {
+    type Error = OdbcStdError;
+
+    fn try_from(result: QueryResult) -> Result<Self, Self::Error> {
+        pg_query_result(result, ConversionPolicy::Fail)
+    }
+}
+
+/// A value which can't be converted is handled per `Options.conversion`.
+impl TryConvert<PgQueryResult> for (QueryResult, &Options) {
+    type Error = OdbcStdError;
+
+    fn try_convert(self) -> OdbcStdResult<PgQueryResult, Self::Error> {
+        pg_query_result(self.0, self.1.conversion)
+    }
+}
+
+/// Convert the columns by their odbc type, the invalid values are resolved per `policy`.
+fn pg_query_result(result: QueryResult, policy: ConversionPolicy) -> OdbcStdResult<PgQueryResult> {
+    let columns = result
+        .columns
+        .into_iter()
+        .map(TryConvert::try_convert)
+        .collect::<OdbcStdResult<Vec<PgColumn>>>()?;
+    let mut data = Vec::with_capacity(result.data.len());
+    for (row_index, row) in result.data.into_iter().enumerate() {
+        let row = row
+            .into_iter()
+            .enumerate()
+            .map(|(i, item)| pg_column_item(item, columns.get(i), row_index))
+            .collect();
+        if let Some(row) = resolve_row(row, policy)? {
+            data.push(row);
         }
     }
+    Ok(PgQueryResult { columns, data })
 }
 
-impl Convert<PgType> for Oid {
-    fn convert(self) -> PgType {
-        PgType::from_oid(self).unwrap()
+/// Convert a NUMERIC value exactly per the column, the others by their odbc type with the
+/// fraction of second cut to the column precision. A NUMERIC value which can't be parsed is
+/// invalid, see `Options.conversion`.
+fn pg_column_item(
+    item: OdbcColumnItem,
+    column: Option<&PgColumn>,
+    row: usize,
+) -> Cell<PgColumnItem> {
+    if let Some(column) = column.filter(|c| c.pg_type == PgType::NUMERIC) {
+        let value = item.value.as_ref().map(|v| pg_numeric(v, column));
+        return match value.transpose() {
+            Ok(value) => Cell::Valid(PgColumnItem::new(value)),
+            Err(e) => Cell::Invalid(
+                ConversionError {
+                    column: column.name.clone(),
+                    row,
+                    raw: item.to_string(),
+                    cause: e.to_string(),
+                },
+                PgColumnItem::new(None),
+            ),
+        };
+    }
+    let mut item: PgColumnItem = item.convert();
+    if let Some(precision) = column.and_then(|c| c.precision) {
+        item.data = item.data.map(|v| v.truncate_fraction(precision));
+    }
+    Cell::Valid(item)
+}
+
+impl TryConvert<PgType> for Oid {
+    type Error = OdbcStdError;
+
+    fn try_convert(self) -> OdbcStdResult<PgType, Self::Error> {
+        PgType::from_oid(self)
+            .ok_or_else(|| OdbcStdError::TypeConversionError(format!("pg type from oid:{self}")))
     }
 }
 
-impl Convert<PgType> for PgType {
-    fn convert(self) -> PgType {
-        self
+impl TryConvert<PgType> for PgType {
+    type Error = OdbcStdError;
+
+    fn try_convert(self) -> OdbcStdResult<PgType, Self::Error> {
+        Ok(self)
     }
 }
 
-pub fn oid_typlen<C: Convert<PgType>>(c: C) -> i16 {
-    let pg_type = c.convert();
+pub fn oid_typlen<C: TryConvert<PgType, Error = OdbcStdError>>(c: C) -> OdbcStdResult<i16> {
+    let pg_type = c.try_convert()?;
     pg_helper::oid_typlen(pg_type)
 }
 
@@ -234,55 +476,72 @@ impl TryConvert<PgTableDesc> for (TableDescResult, &Options) {
     }
 }
 
-impl TryConvert<PgColumnItem> for (&OdbcColumnItem, &PgColumn) {
+/// A time zone aware value without offset is in the given session offset, see
+/// [`Options::session_offset`].
+impl TryConvert<PgColumnItem> for (&OdbcColumnItem, &PgColumn, FixedOffset) {
     type Error = OdbcStdError;
 
     fn try_convert(self) -> OdbcStdResult<PgColumnItem, Self::Error> {
-        let odbc_data = self.0.value.clone();
         let pg_column = self.1;
+        let offset = self.2;
+        let odbc_data = match &self.0.value {
+            Some(v) => v,
+            None => return Ok(PgColumnItem::new(None)),
+        };
 
         let value = match pg_column.pg_type {
-            PgType::TEXT => odbc_data.map(|v| PgValueInput::Text(Some(parse_to_string(v)))),
-            PgType::VARCHAR => odbc_data.map(|v| PgValueInput::Varchar(Some(parse_to_string(v)))),
-            PgType::BYTEA => odbc_data.map(|v| PgValueInput::Bytea(Some(v.to_vec()))),
-            PgType::DATE => odbc_data.map(|v| PgValueInput::Date(Some(parse_to_date(v).unwrap()))),
-            PgType::TIME => odbc_data.map(|v| PgValueInput::Time(Some(parse_to_time(v).unwrap()))),
+            PgType::TEXT => PgValueInput::Text(Some(odbc_data.try_convert()?)),
+            PgType::VARCHAR => PgValueInput::Varchar(Some(odbc_data.try_convert()?)),
+            PgType::BYTEA => PgValueInput::Bytea(Some(odbc_data.try_convert()?)),
+            PgType::DATE => PgValueInput::Date(Some(odbc_data.try_convert()?)),
+            PgType::TIME => {
+                let time: NaiveTime = odbc_data.try_convert()?;
+                PgValueInput::Time(Some(pg_column.with_precision(time)))
+            }
             PgType::TIMETZ => {
-                odbc_data.map(|v| PgValueInput::Timez(Some(parse_to_time(v).unwrap())))
+                let time: TimeTz = (odbc_data, offset).try_convert()?;
+                let local = pg_column.with_precision(time.time());
+                PgValueInput::Timez(Some(TimeTz::new(local, time.offset())))
             }
             PgType::TIMESTAMP => {
-                odbc_data.map(|v| PgValueInput::Timestamp(Some(parse_to_data_time(v).unwrap())))
+                let timestamp: NaiveDateTime = odbc_data.try_convert()?;
+                PgValueInput::Timestamp(Some(pg_column.with_precision(timestamp)))
             }
             PgType::TIMESTAMPTZ => {
-                odbc_data.map(|v| PgValueInput::Timestampz(Some(parse_to_data_time(v).unwrap())))
-            }
-            PgType::FLOAT8 => {
-                odbc_data.map(|v| PgValueInput::Float8(Some(parse_to_float8(v).unwrap())))
-            }
-            PgType::FLOAT4 => {
-                odbc_data.map(|v| PgValueInput::Float4(Some(parse_to_float4(v).unwrap())))
+                let timestamp: DateTime<FixedOffset> = (odbc_data, offset).try_convert()?;
+                PgValueInput::Timestampz(Some(pg_column.with_precision(timestamp)))
             }
-            PgType::CHAR => odbc_data.map(|v| PgValueInput::Char(Some(parse_to_i8(v).unwrap()))),
-            PgType::INT2 => odbc_data.map(|v| PgValueInput::Int2(Some(parse_to_int2(v).unwrap()))),
-            PgType::INT4 => odbc_data.map(|v| PgValueInput::Int4(Some(parse_to_int4(v).unwrap()))),
-            PgType::NUMERIC => {
-                odbc_data.map(|v| PgValueInput::Numeric(Some(parse_to_int4(v).unwrap())))
-            }
-            PgType::INT8 => odbc_data.map(|v| PgValueInput::Int8(Some(parse_to_int8(v).unwrap()))),
-            PgType::BOOL => odbc_data.map(|v| PgValueInput::Bool(Some(parse_to_bool(v).unwrap()))),
+            PgType::FLOAT8 => PgValueInput::Float8(Some(odbc_data.try_convert()?)),
+            PgType::FLOAT4 => PgValueInput::Float4(Some(odbc_data.try_convert()?)),
+            PgType::CHAR => PgValueInput::Char(Some(odbc_data.try_convert()?)),
+            PgType::INT2 => PgValueInput::Int2(Some(odbc_data.try_convert()?)),
+            PgType::INT4 => PgValueInput::Int4(Some(odbc_data.try_convert()?)),
+            PgType::NUMERIC => pg_numeric(odbc_data, pg_column)?,
+            PgType::INT8 => PgValueInput::Int8(Some(odbc_data.try_convert()?)),
+            PgType::BOOL => PgValueInput::Bool(Some(odbc_data.try_convert()?)),
             _ => {
                 error!(
                     "There is no adaptation for this type, {}",
                     pg_column.pg_type
                 );
-                odbc_data.map(|v| PgValueInput::Text(Some(parse_to_string(v))))
+                PgValueInput::Text(Some(odbc_data.try_convert()?))
             }
         };
 
-        Ok(PgColumnItem::new(value))
+        Ok(PgColumnItem::new(Some(value)))
     }
 }
 
+/// The exact value of a NUMERIC column, padded or rounded to the scale of the column.
+fn pg_numeric(value: &OdbcColumnValue, column: &PgColumn) -> OdbcStdResult<PgValueInput> {
+    let numeric: Numeric = value.try_convert()?;
+    let numeric = match column.scale {
+        Some(scale) => numeric.with_scale(scale),
+        None => numeric,
+    };
+    Ok(PgValueInput::Numeric(Some(numeric)))
+}
+
 impl TryConvert<PgQueryResult> for (QueryResult, &Vec<PgTableItem>, &Options) {
     type Error = OdbcStdError;
 
@@ -291,24 +550,40 @@ impl TryConvert<PgQueryResult> for (QueryResult, &Vec<PgTableItem>, &Options) {
         let pg_all_columns = self.1;
         let options = self.2;
         let mut result = PgQueryResult::default();
-        if let Ok(cols) = (&res.columns, pg_all_columns, options).try_convert() {
-            let cols: Vec<PgColumn> = cols;
-            result.columns = cols;
-
-            // if column name is count(*),but this name not exist Vec<PgTableItem>
-            // So,could find result.columns is empty.
-            if result.columns.is_empty() {
-                return Ok(PgQueryResult::from(res));
-            }
+        let cols: Vec<PgColumn> = (&res.columns, pg_all_columns, options).try_convert()?;
+        result.columns = cols;
+
+        // if column name is count(*),but this name not exist Vec<PgTableItem>
+        // So,could find result.columns is empty.
+        if result.columns.is_empty() {
+            return (res, options).try_convert();
+        }
 
-            if let crate::executor::SupportDatabase::Dameng = options.database {
-                for v in res.data.iter() {
-                    let mut row: Vec<PgColumnItem> = vec![];
-                    for (index, odbc_item) in v.iter().enumerate() {
-                        if let Some(col) = result.columns.get(index) {
-                            row.push((odbc_item, col).try_convert().unwrap());
-                        }
+        if let crate::executor::SupportDatabase::Dameng = options.database {
+            // only a time zone aware column reads the offset, the others don't require it
+            let offset = match result.columns.iter().any(PgColumn::is_time_zone_aware) {
+                true => options.session_offset()?,
+                false => Utc.fix(),
+            };
+            for (row_index, v) in res.data.iter().enumerate() {
+                let mut row = vec![];
+                for (index, odbc_item) in v.iter().enumerate() {
+                    if let Some(col) = result.columns.get(index) {
+                        row.push(match (odbc_item, col, offset).try_convert() {
+                            Ok(item) => Cell::Valid(item),
+                            Err(e) => Cell::Invalid(
+                                ConversionError {
+                                    column: col.name.clone(),
+                                    row: row_index,
+                                    raw: odbc_item.to_string(),
+                                    cause: e.to_string(),
+                                },
+                                PgColumnItem::new(None),
+                            ),
+                        });
                     }
+                }
+                if let Some(row) = resolve_row(row, options.conversion)? {
                     result.data.push(row);
                 }
             }
@@ -340,9 +615,15 @@ impl TryConvert<Vec<PgColumn>> for (&Vec<OdbcColumnDesc>, &Vec<PgTableItem>, &Op
                     pg_type: pg.r#type.clone(),
                     oid: pg.r#type.oid(),
                     nullable: pg.nullable,
+                    scale: match pg.r#type {
+                        // a NUMERIC without precision, i.e. length 0, keeps the scale of each value
+                        PgType::NUMERIC if pg.length > 0 => u16::try_from(pg.scale).ok(),
+                        _ => None,
+                    },
+                    precision: pg.precision.and_then(|p| u16::try_from(p).ok()),
                 });
             } else {
-                result.push(v.clone().convert());
+                result.push(v.clone().try_convert()?);
             }
         }
 
@@ -353,8 +634,29 @@ impl TryConvert<Vec<PgColumn>> for (&Vec<OdbcColumnDesc>, &Vec<PgTableItem>, &Op
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::executor::conversion::ConversionPolicy;
+    use crate::extension::odbc::OdbcColumnType;
+    use odbc_common::odbc_api::sys::{CDataType, NULL_DATA};
     use odbc_common::odbc_api::DataType;
 
+    fn table_item(name: &str, r#type: PgType, length: usize) -> PgTableItem {
+        PgTableItem {
+            name: name.to_string(),
+            table_id: 0,
+            col_index: 0,
+            r#type,
+            length,
+            scale: 0,
+            precision: None,
+            nullable: true,
+            is_identity: false,
+            default_val: None,
+            table_name: "".to_string(),
+            create_time: "".to_string(),
+            subtype: None,
+        }
+    }
+
     #[test]
     fn test_query_result_convert() {
         let column = OdbcColumnDesc {
@@ -369,28 +671,15 @@ mod tests {
                 odbc_type: OdbcColumnType::Text,
                 value: None,
             }]],
+            truncated: vec![],
         };
 
-        let pg_table_item = PgTableItem {
-            name: "trace_id".to_string(),
-            table_id: 0,
-            col_index: 0,
-            r#type: PgType::VARCHAR,
-            length: 255,
-            scale: 0,
-            nullable: true,
-            is_identity: false,
-            default_val: None,
-            table_name: "".to_string(),
-            create_time: "".to_string(),
-            subtype: None,
-        };
+        let pg_table_item = table_item("trace_id", PgType::VARCHAR, 255);
         let options = Options {
-            database: SupportDatabase::Dameng,
             max_batch_size: 1024,
             max_str_len: 1024,
             max_binary_len: 1024,
-            case_sensitive: false,
+            ..Options::new(SupportDatabase::Dameng)
         };
         let result: PgQueryResult = (query_result, &vec![pg_table_item], &options)
             .try_convert()
@@ -403,9 +692,270 @@ mod tests {
                     pg_type: PgType::VARCHAR,
                     oid: 1043,
                     nullable: true,
+                    scale: None,
+                    precision: None,
                 }],
                 data: vec![vec![PgColumnItem { data: None }]],
             }
         );
     }
+
+    #[test]
+    fn test_numeric_convert() {
+        let column = OdbcColumnDesc {
+            name: "PRICE".to_string(),
+            data_type: DataType::Decimal {
+                precision: 40,
+                scale: 2,
+            },
+            nullable: true,
+        };
+        let item = |v: OdbcColumnValue| OdbcColumnItem {
+            odbc_type: OdbcColumnType::WText,
+            value: Some(v),
+        };
+        let query_result = QueryResult {
+            columns: vec![column],
+            data: vec![
+                vec![item(OdbcColumnValue::Text("12.5".to_string()))],
+                vec![item(OdbcColumnValue::Text(
+                    "12345678901234567890123456789.01".to_string(),
+                ))],
+                vec![item(OdbcColumnValue::I64(-7))],
+            ],
+            truncated: vec![],
+        };
+        let result = PgQueryResult::try_from(query_result).unwrap();
+        assert_eq!(result.columns[0].pg_type, PgType::NUMERIC);
+        assert_eq!(result.columns[0].scale, Some(2));
+        let values: Vec<String> = result
+            .data
+            .iter()
+            .map(|row| match &row[0].data {
+                Some(PgValueInput::Numeric(Some(v))) => v.to_string(),
+                other => panic!("expect numeric:{other:?}"),
+            })
+            .collect();
+        assert_eq!(
+            values,
+            vec!["12.50", "12345678901234567890123456789.01", "-7.00"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_numeric() {
+        let query_result = || QueryResult {
+            columns: vec![OdbcColumnDesc::new(
+                "PRICE".to_string(),
+                DataType::Decimal {
+                    precision: 10,
+                    scale: 2,
+                },
+                true,
+            )],
+            data: vec![vec![OdbcColumnItem::new(
+                OdbcColumnType::WText,
+                Some(OdbcColumnValue::Text("1.2.3".to_string())),
+            )]],
+            truncated: vec![],
+        };
+        let err = PgQueryResult::try_from(query_result()).unwrap_err();
+        assert!(matches!(err, OdbcStdError::TypeConversionError(_)));
+        assert!(
+            err.to_string().contains("`PRICE` of row 0 from `1.2.3`"),
+            "{err}"
+        );
+
+        let mut options = Options::new(SupportDatabase::Dameng);
+        options.conversion = ConversionPolicy::Null;
+        let result: PgQueryResult = (query_result(), &options).try_convert().unwrap();
+        assert_eq!(result.data, vec![vec![PgColumnItem::new(None)]]);
+    }
+
+    #[test]
+    fn test_pg_type_try_convert() {
+        let pg_type: PgType = (DataType::Integer, true).try_convert().unwrap();
+        assert_eq!(pg_type, PgType::INT4);
+        assert!(TryConvert::<PgType>::try_convert((DataType::Unknown, true)).is_err());
+        assert!(TryConvert::<PgType>::try_convert(0 as Oid).is_err());
+        assert_eq!(oid_typlen(PgType::INT8.oid()).unwrap(), 8);
+        assert!(oid_typlen(PgType::JSON).is_err());
+
+        let column = OdbcColumnDesc::new("C1".to_string(), DataType::Unknown, true);
+        let err = TryConvert::<PgColumn>::try_convert(column).unwrap_err();
+        assert!(err.to_string().contains("`C1`"), "{err}");
+    }
+
+    #[test]
+    fn test_unmapped_column_type() {
+        let query_result = QueryResult {
+            columns: vec![
+                OdbcColumnDesc::new("ID".to_string(), DataType::Integer, true),
+                OdbcColumnDesc::new("C1".to_string(), DataType::Unknown, true),
+            ],
+            data: vec![],
+            truncated: vec![],
+        };
+        let pg_table_items = vec![table_item("ID", PgType::INT4, 4)];
+        let options = Options::new(SupportDatabase::Dameng);
+        let err =
+            TryConvert::<PgQueryResult>::try_convert((query_result, &pg_table_items, &options))
+                .unwrap_err();
+        assert!(err.to_string().contains("`C1`"), "{err}");
+    }
+
+    #[test]
+    fn test_conversion_policy() {
+        let query_result = || QueryResult {
+            columns: vec![OdbcColumnDesc::new(
+                "ID".to_string(),
+                DataType::Varchar { length: 10 },
+                true,
+            )],
+            data: vec!["1", "x"]
+                .into_iter()
+                .map(|v| {
+                    vec![OdbcColumnItem::new(
+                        OdbcColumnType::Text,
+                        Some(OdbcColumnValue::Text(v.to_string())),
+                    )]
+                })
+                .collect(),
+            truncated: vec![],
+        };
+        let pg_table_items = vec![table_item("ID", PgType::INT4, 4)];
+        let mut options = Options::new(SupportDatabase::Dameng);
+        let convert = |options: &Options| -> OdbcStdResult<PgQueryResult> {
+            (query_result(), &pg_table_items, options).try_convert()
+        };
+
+        let err = convert(&options).unwrap_err().to_string();
+        assert!(err.contains("`ID` of row 1 from `x`"), "{err}");
+
+        options.conversion = ConversionPolicy::Null;
+        let data = convert(&options).unwrap().data;
+        assert_eq!(
+            data,
+            vec![
+                vec![PgColumnItem::new(Some(PgValueInput::Int4(Some(1))))],
+                vec![PgColumnItem::new(None)],
+            ]
+        );
+
+        options.conversion = ConversionPolicy::SkipRow;
+        let data = convert(&options).unwrap().data;
+        assert_eq!(
+            data,
+            vec![vec![PgColumnItem::new(Some(PgValueInput::Int4(Some(1))))]]
+        );
+    }
+
+    #[test]
+    fn test_time_precision() {
+        let ts = NaiveDate::from_ymd_opt(2022, 10, 24)
+            .and_then(|d| d.and_hms_nano_opt(17, 28, 26, 308_123_456))
+            .unwrap();
+        let query_result = || QueryResult {
+            columns: vec![OdbcColumnDesc::new(
+                "C1".to_string(),
+                DataType::Timestamp { precision: 6 },
+                true,
+            )],
+            data: vec![vec![OdbcColumnItem::new(
+                OdbcColumnType::Timestamp,
+                Some(OdbcColumnValue::Timestamp(ts)),
+            )]],
+            truncated: vec![],
+        };
+        let convert = |precision: usize| -> PgQueryResult {
+            let pg_table_items = vec![PgTableItem {
+                precision: Some(precision),
+                ..table_item("C1", PgType::TIMESTAMP, 8)
+            }];
+            let options = Options::new(SupportDatabase::Dameng);
+            (query_result(), &pg_table_items, &options)
+                .try_convert()
+                .unwrap()
+        };
+        let value = |result: &PgQueryResult| match &result.data[0][0].data {
+            Some(PgValueInput::Timestamp(Some(v))) => v.to_string(),
+            other => panic!("expect timestamp:{other:?}"),
+        };
+
+        let result = convert(3);
+        assert_eq!(result.columns[0].precision, Some(3));
+        assert_eq!(result.columns[0].scale, None);
+        assert_eq!(value(&result), "2022-10-24 17:28:26.308");
+        assert_eq!(value(&convert(0)), "2022-10-24 17:28:26");
+
+        // without a table item the precision is the one of the odbc column
+        let result = PgQueryResult::try_from(query_result()).unwrap();
+        assert_eq!(result.columns[0].precision, Some(6));
+        assert_eq!(value(&result), "2022-10-24 17:28:26.308123");
+    }
+
+    #[test]
+    fn test_time_bind_parameter() {
+        // the c type, the bound text and the sql type of a parameter
+        let bind = |value: PgValueInput| match value.to_value() {
+            Either::Left(param) => {
+                let text = match unsafe { *param.indicator_ptr() } {
+                    NULL_DATA => None,
+                    len => {
+                        let bytes = unsafe {
+                            std::slice::from_raw_parts(param.value_ptr() as *const u8, len as usize)
+                        };
+                        Some(String::from_utf8(bytes.to_vec()).unwrap())
+                    }
+                };
+                (param.cdata_type(), text, param.data_type())
+            }
+            Either::Right(_) => panic!("expect input parameter"),
+        };
+        let text = |v: &str| (CDataType::Char, Some(v.to_string()));
+
+        // a time is text with every digit of its fraction, at least of a TIME(6)
+        let time = |precision: i16| DataType::Time { precision };
+        let cases = vec![
+            (NaiveTime::from_hms_opt(17, 28, 26), "17:28:26.000000", 6),
+            (
+                NaiveTime::from_hms_milli_opt(17, 28, 26, 308),
+                "17:28:26.308000",
+                6,
+            ),
+            (
+                NaiveTime::from_hms_nano_opt(17, 28, 26, 308_123_456),
+                "17:28:26.308123456",
+                9,
+            ),
+        ];
+        for (value, expect, precision) in cases {
+            let (c_type, bound, data_type) = bind(PgValueInput::Time(value));
+            assert_eq!((c_type, bound), text(expect));
+            assert_eq!(data_type, time(precision));
+        }
+        assert_eq!(
+            bind(PgValueInput::Time(None)),
+            (CDataType::Char, None, time(6))
+        );
+
+        let data_type = |value: PgValueInput| bind(value).2;
+        let ts = NaiveDate::from_ymd_opt(2022, 10, 24).and_then(|d| d.and_hms_opt(17, 28, 26));
+        assert_eq!(
+            data_type(PgValueInput::Timestamp(ts)),
+            DataType::Timestamp { precision: 6 }
+        );
+        assert_eq!(
+            data_type(PgValueInput::Timestamp(None)),
+            DataType::Timestamp { precision: 6 }
+        );
+        assert_eq!(data_type(PgValueInput::Date(None)), DataType::Date);
+        // the nanoseconds of a TIMESTAMP(9) are kept
+        let ts = NaiveDate::from_ymd_opt(2022, 10, 24)
+            .and_then(|d| d.and_hms_nano_opt(17, 28, 26, 308_123_456));
+        assert_eq!(
+            data_type(PgValueInput::Timestamp(ts)),
+            DataType::Timestamp { precision: 9 }
+        );
+    }
 }
@@ -1,64 +1,48 @@
-use bytes::BytesMut;
-use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
-use odbc_common::error::OdbcStdResult;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike};
+use odbc_common::error::{OdbcStdError, OdbcStdResult};
+use pg_helper::timetz::{parse_offset, split_offset, TimeTz};
 
-pub fn parse_to_bool(v: BytesMut) -> OdbcStdResult<bool> {
-    Ok(String::from_utf8_lossy(v.as_ref())
-        .to_string()
-        .parse::<bool>()?)
+pub fn parse_to_bool(v: &str) -> OdbcStdResult<bool> {
+    match v.trim() {
+        "1" => Ok(true),
+        "0" => Ok(false),
+        v => Ok(v.to_lowercase().parse::<bool>()?),
+    }
 }
 
-pub fn parse_to_i8(v: BytesMut) -> OdbcStdResult<i8> {
-    Ok(String::from_utf8_lossy(v.as_ref())
-        .to_string()
-        .parse::<i8>()?)
+pub fn parse_to_i8(v: &str) -> OdbcStdResult<i8> {
+    Ok(v.trim().parse::<i8>()?)
 }
 
-pub fn parse_to_int2(v: BytesMut) -> OdbcStdResult<i16> {
-    Ok(String::from_utf8_lossy(v.as_ref())
-        .to_string()
-        .parse::<i16>()?)
+pub fn parse_to_int2(v: &str) -> OdbcStdResult<i16> {
+    Ok(v.trim().parse::<i16>()?)
 }
 
-pub fn parse_to_int4(v: BytesMut) -> OdbcStdResult<i32> {
-    Ok(String::from_utf8_lossy(v.as_ref())
-        .to_string()
-        .parse::<i32>()?)
+pub fn parse_to_int4(v: &str) -> OdbcStdResult<i32> {
+    Ok(v.trim().parse::<i32>()?)
 }
 
-pub fn parse_to_int8(v: BytesMut) -> OdbcStdResult<i64> {
-    Ok(String::from_utf8_lossy(v.as_ref())
-        .to_string()
-        .parse::<i64>()?)
+pub fn parse_to_int8(v: &str) -> OdbcStdResult<i64> {
+    Ok(v.trim().parse::<i64>()?)
 }
 
-pub fn parse_to_float4(v: BytesMut) -> OdbcStdResult<f32> {
-    Ok(String::from_utf8_lossy(v.as_ref())
-        .to_string()
-        .parse::<f32>()?)
+pub fn parse_to_float4(v: &str) -> OdbcStdResult<f32> {
+    Ok(v.trim().parse::<f32>()?)
 }
 
-pub fn parse_to_float8(v: BytesMut) -> OdbcStdResult<f64> {
-    Ok(String::from_utf8_lossy(v.as_ref())
-        .to_string()
-        .parse::<f64>()?)
+pub fn parse_to_float8(v: &str) -> OdbcStdResult<f64> {
+    Ok(v.trim().parse::<f64>()?)
 }
 
-pub fn parse_to_string(v: BytesMut) -> String {
-    String::from_utf8_lossy(v.as_ref()).to_string()
-}
-
-pub fn parse_to_date(v: BytesMut) -> OdbcStdResult<NaiveDate> {
-    let val = String::from_utf8_lossy(v.as_ref()).to_string();
-    let date = NaiveDate::parse_from_str(val.as_str(), "%Y-%m-%d")?;
+pub fn parse_to_date(v: &str) -> OdbcStdResult<NaiveDate> {
+    let date = NaiveDate::parse_from_str(v, "%Y-%m-%d")?;
     Ok(date)
 }
 
-pub fn parse_to_time(v: BytesMut) -> OdbcStdResult<NaiveTime> {
-    let val = String::from_utf8_lossy(v.as_ref()).to_string();
+pub fn parse_to_time(v: &str) -> OdbcStdResult<NaiveTime> {
     let time = NaiveTime::parse_from_str(
-        val.as_str(),
-        if val.contains('+') {
+        v,
+        if v.contains('+') {
             "%H:%M:%S%.f%#z"
         } else {
             "%H:%M:%S%.f"
@@ -67,11 +51,10 @@ pub fn parse_to_time(v: BytesMut) -> OdbcStdResult<NaiveTime> {
     Ok(time)
 }
 
-pub fn parse_to_data_time(v: BytesMut) -> OdbcStdResult<NaiveDateTime> {
-    let val = String::from_utf8_lossy(v.as_ref()).to_string();
+pub fn parse_to_data_time(v: &str) -> OdbcStdResult<NaiveDateTime> {
     let date_time = NaiveDateTime::parse_from_str(
-        val.as_str(),
-        if val.contains('+') {
+        v,
+        if v.contains('+') {
             "%Y-%m-%d %H:%M:%S%.f%#z"
         } else {
             "%Y-%m-%d %H:%M:%S%.f"
@@ -79,3 +62,91 @@ pub fn parse_to_data_time(v: BytesMut) -> OdbcStdResult<NaiveDateTime> {
     )?;
     Ok(date_time)
 }
+
+/// Parse a timestamp with an optional UTC offset, e.g. `2022-10-24 17:28:26.308 +08:00`,
+/// a timestamp without offset is in `time_zone`.
+pub fn parse_to_data_time_tz(
+    v: &str,
+    time_zone: FixedOffset,
+) -> OdbcStdResult<DateTime<FixedOffset>> {
+    let (date_time, offset) = split_offset(v);
+    let offset = offset.map(parse_offset).transpose()?.unwrap_or(time_zone);
+    let date_time = NaiveDateTime::parse_from_str(date_time, "%Y-%m-%d %H:%M:%S%.f")?;
+    with_offset(date_time, offset)
+}
+
+/// Parse a time with an optional UTC offset, e.g. `17:28:26 +08:00`, a time without offset is
+/// in `time_zone`.
+pub fn parse_to_time_tz(v: &str, time_zone: FixedOffset) -> OdbcStdResult<TimeTz> {
+    let (time, offset) = split_offset(v);
+    let offset = offset.map(parse_offset).transpose()?.unwrap_or(time_zone);
+    let time = NaiveTime::parse_from_str(time, "%H:%M:%S%.f")?;
+    Ok(TimeTz::new(time, offset))
+}
+
+/// The local `date_time` in `offset`.
+pub fn with_offset(
+    date_time: NaiveDateTime,
+    offset: FixedOffset,
+) -> OdbcStdResult<DateTime<FixedOffset>> {
+    offset
+        .from_local_datetime(&date_time)
+        .single()
+        .ok_or_else(|| {
+            OdbcStdError::TypeConversionError(format!("timestamp {date_time} in {offset}"))
+        })
+}
+
+/// The text of a timestamp with time zone bound to a parameter, e.g.
+/// `2022-10-24 17:28:26.308 +08:00`, which Dameng and pg both accept.
+pub fn format_data_time_tz(v: &DateTime<FixedOffset>) -> String {
+    v.format("%Y-%m-%d %H:%M:%S%.f %:z").to_string()
+}
+
+/// The text of a time with time zone bound to a parameter, e.g. `17:28:26.308 +08:00`.
+pub fn format_time_tz(v: &TimeTz) -> String {
+    format!("{} {}", v.time().format("%H:%M:%S%.f"), v.offset())
+}
+
+/// The text of a time bound to a parameter with `precision` fractional second digits, e.g.
+/// `17:28:26.308000` for 6.
+pub fn format_time(v: &NaiveTime, precision: u16) -> String {
+    let time = v.format("%H:%M:%S");
+    match precision.min(9) {
+        0 => time.to_string(),
+        p => {
+            let nanos = truncate_nanos(v.nanosecond() % 1_000_000_000, p);
+            let fraction = nanos / 10u32.pow(9 - u32::from(p));
+            format!("{time}.{fraction:0width$}", width = usize::from(p))
+        }
+    }
+}
+
+/// Cut the nanoseconds to `precision` fractional second digits, a precision over 9 keeps them.
+pub fn truncate_nanos(nanos: u32, precision: u16) -> u32 {
+    match 9u32.checked_sub(u32::from(precision)) {
+        Some(cut) if cut > 0 => nanos - nanos % 10u32.pow(cut),
+        _ => nanos,
+    }
+}
+
+/// Cut the fractional seconds of a time or timestamp to `precision` digits, e.g. the declared
+/// precision of a TIME(3)/TIMESTAMP(6) column.
+pub fn truncate_fraction<T: Timelike>(v: T, precision: u16) -> T {
+    let nanos = truncate_nanos(v.nanosecond(), precision);
+    v.with_nanosecond(nanos).unwrap_or(v)
+}
+
+/// The fractional second digits which keep all of `nanos`, e.g. 3 for 308_000_000.
+pub fn fraction_digits(nanos: u32) -> u16 {
+    let mut nanos = nanos % 1_000_000_000;
+    if nanos == 0 {
+        return 0;
+    }
+    let mut digits = 9;
+    while nanos % 10 == 0 {
+        nanos /= 10;
+        digits -= 1;
+    }
+    digits
+}
@@ -10,6 +10,7 @@ pub extern crate pg_helper;
 pub mod bridge;
 pub mod executor;
 pub mod extension;
+pub mod orm;
 
 pub use odbc_common::Print;
 
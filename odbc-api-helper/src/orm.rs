@@ -0,0 +1,238 @@
+//! Map structs from query rows and into statement parameters.
+//!
+//! With the `derive` feature, `#[derive(FromRow, ToParams, ToNamedParams)]` implements the traits
+//! below. The parameters are any [`SqlValue`], a derived struct converts every field with `Into`,
+//! e.g. into `PgValueInput`.
+//!
+//! # Example
+//!
+//! ```rust
+//! use odbc_api_helper::executor::batch::OdbcOperation;
+//! use odbc_api_helper::executor::statement::Statement;
+//! use odbc_api_helper::extension::pg::PgValueInput;
+//! use odbc_api_helper::orm::{FromRow, Row, ToParams};
+//! use odbc_api_helper::odbc_common::error::OdbcStdResult;
+//!
+//! #[derive(Debug)]
+//! struct User {
+//!     id: i32,
+//!     name: Option<String>,
+//! }
+//!
+//! impl FromRow for User {
+//!     fn from_row(row: &Row<'_>) -> OdbcStdResult<Self> {
+//!         Ok(User {
+//!             id: row.get("id")?,
+//!             name: row.get("name")?,
+//!         })
+//!     }
+//! }
+//!
+//! impl ToParams<PgValueInput> for User {
+//!     fn to_params(self) -> Vec<PgValueInput> {
+//!         vec![self.id.into(), self.name.into()]
+//!     }
+//! }
+//!
+//! let user = User { id: 1, name: None };
+//! let stmt: Statement<PgValueInput, OdbcOperation> =
+//!     user.to_statement("INSERT INTO USERS(ID, NAME) VALUES(?, ?)");
+//! assert_eq!(stmt.values, vec![PgValueInput::Int4(Some(1)), PgValueInput::Varchar(None)]);
+//! ```
+use crate::executor::batch::OdbcOperation;
+use crate::executor::query::QueryResult;
+use crate::executor::statement::{SqlValue, Statement};
+use crate::extension::odbc::{OdbcColumnDesc, OdbcColumnItem, OdbcColumnValue};
+use crate::extension::pg::PgValueInput;
+use crate::TryConvert;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use odbc_common::error::{OdbcStdError, OdbcStdResult};
+use std::fmt::Debug;
+
+#[cfg(feature = "derive")]
+pub use odbc_api_helper_derive::{FromRow, ToNamedParams, ToParams};
+
+/// Build a value from one row of a `QueryResult`.
+pub trait FromRow: Sized {
+    fn from_row(row: &Row<'_>) -> OdbcStdResult<Self>;
+}
+
+/// Turn a value into the parameters list of a `Statement`.
+pub trait ToParams<V: SqlValue + Debug = PgValueInput>: Sized {
+    fn to_params(self) -> Vec<V>;
+
+    fn to_statement<S: Into<String>>(self, sql: S) -> Statement<V, OdbcOperation> {
+        Statement::new(sql, self.to_params())
+    }
+}
+
+/// Turn a value into the named parameters of a `Statement`, see [`Statement::named`].
+pub trait ToNamedParams<V: SqlValue + Debug = PgValueInput>: Sized {
+    fn to_named_params(self) -> Vec<(String, V)>;
+
+    fn to_named_statement<S: AsRef<str>>(self, sql: S) -> OdbcStdResult<Statement<V, OdbcOperation>>
+    where
+        V: Clone,
+    {
+        Statement::named(sql, self.to_named_params())
+    }
+}
+
+/// A borrowed row of a `QueryResult`, the columns are looked up by name or index.
+#[derive(Debug, Clone, Copy)]
+pub struct Row<'a> {
+    columns: &'a [OdbcColumnDesc],
+    items: &'a [OdbcColumnItem],
+    case_sensitive: bool,
+}
+
+impl<'a> Row<'a> {
+    pub fn new(
+        columns: &'a [OdbcColumnDesc],
+        items: &'a [OdbcColumnItem],
+        case_sensitive: bool,
+    ) -> Self {
+        Self {
+            columns,
+            items,
+            case_sensitive,
+        }
+    }
+
+    /// Get the column index by name, ignoring case unless `case_sensitive` is set.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|c| {
+            if self.case_sensitive {
+                c.name == name
+            } else {
+                c.name.eq_ignore_ascii_case(name)
+            }
+        })
+    }
+
+    pub fn get<T>(&self, name: &str) -> OdbcStdResult<T>
+    where
+        for<'i> &'i OdbcColumnItem: TryConvert<T, Error = OdbcStdError>,
+    {
+        let index = self.index_of(name).ok_or_else(|| {
+            OdbcStdError::DeserializeError(format!("missing column `{name}` in query result"))
+        })?;
+        self.get_by_index(index)
+    }
+
+    pub fn get_by_index<T>(&self, index: usize) -> OdbcStdResult<T>
+    where
+        for<'i> &'i OdbcColumnItem: TryConvert<T, Error = OdbcStdError>,
+    {
+        let item = self.items.get(index).ok_or_else(|| {
+            OdbcStdError::DeserializeError(format!("missing column index {index} in query result"))
+        })?;
+        let name = self
+            .columns
+            .get(index)
+            .map(|c| c.name.as_str())
+            .unwrap_or_default();
+        item.try_convert()
+            .map_err(|e| OdbcStdError::DeserializeError(format!("column `{name}`: {e}")))
+    }
+}
+
+impl QueryResult {
+    /// Build every row into `T` with [`FromRow`].
+    pub fn from_rows<T: FromRow>(&self, case_sensitive: bool) -> OdbcStdResult<Vec<T>> {
+        self.data
+            .iter()
+            .enumerate()
+            .map(|(index, items)| {
+                T::from_row(&Row::new(&self.columns, items, case_sensitive))
+                    .map_err(|e| OdbcStdError::DeserializeError(format!("row {index}: {e}")))
+            })
+            .collect()
+    }
+}
+
+macro_rules! try_convert_item {
+    ($($t:ty),+) => {
+        $(
+            impl TryConvert<$t> for &OdbcColumnItem {
+                type Error = OdbcStdError;
+
+                fn try_convert(self) -> Result<$t, Self::Error> {
+                    match &self.value {
+                        Some(v) => v.try_convert(),
+                        None => Err(OdbcStdError::DeserializeError(format!(
+                            "NULL value can't convert to {}",
+                            stringify!($t)
+                        ))),
+                    }
+                }
+            }
+
+            impl TryConvert<Option<$t>> for &OdbcColumnItem {
+                type Error = OdbcStdError;
+
+                fn try_convert(self) -> Result<Option<$t>, Self::Error> {
+                    self.value
+                        .as_ref()
+                        .map(<&OdbcColumnValue as TryConvert<$t>>::try_convert)
+                        .transpose()
+                }
+            }
+        )+
+    };
+}
+
+try_convert_item!(
+    i8,
+    i16,
+    i32,
+    i64,
+    f32,
+    f64,
+    bool,
+    String,
+    Vec<u8>,
+    NaiveDate,
+    NaiveTime,
+    NaiveDateTime
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extension::odbc::OdbcColumnType;
+    use odbc_common::odbc_api::DataType;
+
+    #[derive(Debug, PartialEq)]
+    struct User {
+        id: i64,
+        name: Option<String>,
+    }
+
+    impl FromRow for User {
+        fn from_row(row: &Row<'_>) -> OdbcStdResult<Self> {
+            Ok(User {
+                id: row.get("id")?,
+                name: row.get_by_index(1)?,
+            })
+        }
+    }
+
+    #[test]
+    fn test_from_rows() {
+        let column = |name: &str| OdbcColumnDesc::new(name.to_string(), DataType::Integer, true);
+        let result = QueryResult {
+            columns: vec![column("ID"), column("NAME")],
+            data: vec![vec![
+                OdbcColumnItem::new(OdbcColumnType::I32, Some(OdbcColumnValue::I32(3))),
+                OdbcColumnItem::new(OdbcColumnType::Text, None),
+            ]],
+            truncated: vec![],
+        };
+        let users: Vec<User> = result.from_rows(false).unwrap();
+        assert_eq!(users, vec![User { id: 3, name: None }]);
+
+        let err = result.from_rows::<User>(true).unwrap_err();
+        assert!(err.to_string().contains("missing column `id`"), "{err}");
+    }
+}
@@ -0,0 +1,124 @@
+//! The derive macros refer to `::odbc_api_helper`, so they are tested from outside the crate.
+use odbc_api_helper::executor::batch::OdbcOperation;
+use odbc_api_helper::executor::query::QueryResult;
+use odbc_api_helper::executor::statement::Statement;
+use odbc_api_helper::extension::odbc::{
+    OdbcColumnDesc, OdbcColumnItem, OdbcColumnType, OdbcColumnValue,
+};
+use odbc_api_helper::extension::pg::PgValueInput;
+use odbc_api_helper::odbc_common::odbc_api::DataType;
+use odbc_api_helper::orm::{self, ToNamedParams as _, ToParams as _};
+use odbc_api_helper_derive::{FromRow, ToNamedParams, ToParams};
+
+#[derive(Debug, PartialEq, FromRow, ToParams, ToNamedParams)]
+struct User {
+    id: i64,
+    #[odbc(rename = "user_name")]
+    name: Option<String>,
+    active: bool,
+}
+
+#[derive(Debug, PartialEq, FromRow, ToParams)]
+struct Pair(i32, String);
+
+fn query_result() -> QueryResult {
+    let column = |name: &str| OdbcColumnDesc {
+        name: name.to_string(),
+        data_type: DataType::Integer,
+        nullable: true,
+    };
+    QueryResult {
+        columns: vec![column("ID"), column("USER_NAME"), column("ACTIVE")],
+        data: vec![vec![
+            OdbcColumnItem::new(OdbcColumnType::I32, Some(OdbcColumnValue::I32(3))),
+            OdbcColumnItem::new(OdbcColumnType::Text, None),
+            OdbcColumnItem::new(OdbcColumnType::Bit, Some(OdbcColumnValue::Bit(true))),
+        ]],
+        truncated: vec![],
+    }
+}
+
+#[test]
+fn test_derive_from_row() {
+    let users: Vec<User> = query_result().from_rows(false).unwrap();
+    assert_eq!(
+        users,
+        vec![User {
+            id: 3,
+            name: None,
+            active: true,
+        }]
+    );
+
+    let err = query_result().from_rows::<User>(true).unwrap_err();
+    assert!(err.to_string().contains("missing column `id`"), "{err}");
+
+    let err = query_result().from_rows::<Pair>(false).unwrap_err();
+    assert!(err.to_string().contains("column `USER_NAME`"), "{err}");
+}
+
+#[test]
+fn test_derive_to_params() {
+    let user = User {
+        id: 3,
+        name: Some("foo".to_string()),
+        active: false,
+    };
+    let stmt: Statement<PgValueInput, OdbcOperation> =
+        user.to_statement("INSERT INTO USERS VALUES(?, ?, ?)");
+    assert_eq!(
+        stmt.values,
+        vec![
+            PgValueInput::Int8(Some(3)),
+            PgValueInput::Varchar(Some("foo".to_string())),
+            PgValueInput::Bool(Some(false)),
+        ]
+    );
+    let params: Vec<PgValueInput> = Pair(1, "a".to_string()).to_params();
+    assert_eq!(
+        params,
+        vec![
+            PgValueInput::Int4(Some(1)),
+            PgValueInput::Varchar(Some("a".to_string()))
+        ]
+    );
+}
+
+#[test]
+fn test_derive_to_named_params() {
+    let user = User {
+        id: 3,
+        name: Some("foo".to_string()),
+        active: false,
+    };
+    let stmt: Statement<PgValueInput, OdbcOperation> = user
+        .to_named_statement("UPDATE USERS SET NAME = :user_name, ACTIVE = :active WHERE ID = :id")
+        .unwrap();
+    assert_eq!(
+        stmt.sql,
+        "UPDATE USERS SET NAME = ?, ACTIVE = ? WHERE ID = ?"
+    );
+    assert_eq!(
+        stmt.values,
+        vec![
+            PgValueInput::Varchar(Some("foo".to_string())),
+            PgValueInput::Bool(Some(false)),
+            PgValueInput::Int8(Some(3)),
+        ]
+    );
+
+    let user = User {
+        id: 3,
+        name: None,
+        active: false,
+    };
+    let err = orm::ToNamedParams::<PgValueInput>::to_named_statement(
+        user,
+        "UPDATE USERS SET NAME = :user_name WHERE ID = :id",
+    )
+    .unwrap_err();
+    assert!(
+        err.to_string().contains("unused parameter `active`"),
+        "{err}"
+    );
+}
@@ -35,6 +35,10 @@ fn main() {
         .unwrap();
 
     let connection = OdbcDbConnection::new(conn, Options::new(SupportDatabase::Dameng)).unwrap();
-    let cursor_impl = connection.conn.execute(&config.sql, ()).unwrap().unwrap();
+    let cursor_impl = connection
+        .connection()
+        .execute(&config.sql, ())
+        .unwrap()
+        .unwrap();
     cursor_impl.print_all_tables().unwrap()
 }
@@ -4,8 +4,10 @@ use std::{
     str::ParseBoolError,
 };
 
+use crate::state::{get_obj_by_state, OdbcState};
 use chrono::ParseError;
 pub use odbc_error::OdbcWrapperError;
+use odbc_error::StatementError;
 use thiserror::Error;
 
 pub type OdbcStdResult<T, E = OdbcStdError> = core::result::Result<T, E>;
@@ -18,8 +20,34 @@ pub enum OdbcStdError {
     SqlParamsError(String),
     #[error("Failed to convert byte to {0}")]
     TypeConversionError(String),
+    #[error("deserialize error:`{0}`")]
+    DeserializeError(String),
     #[error("{0}")]
     StringError(String),
+    /// The statement is canceled by a cancel handle or its query timeout expired.
+    #[error("statement canceled:`{0}`")]
+    Canceled(StatementError),
+}
+
+impl OdbcStdError {
+    /// The SQLSTATE of the diagnostic record, if the error is reported by the odbc driver.
+    pub fn state(&self) -> Option<&str> {
+        match self {
+            OdbcStdError::OdbcError(OdbcWrapperError::StatementError(e))
+            | OdbcStdError::Canceled(e) => Some(&e.state),
+            _ => None,
+        }
+    }
+
+    /// Whether the statement is canceled or timed out, see [`OdbcStdError::Canceled`].
+    pub fn is_canceled(&self) -> bool {
+        matches!(self, OdbcStdError::Canceled(_))
+    }
+
+    /// The [`OdbcState`] mapped from the SQLSTATE, see [`OdbcStdError::state`].
+    pub fn odbc_state(&self) -> Option<OdbcState> {
+        self.state().and_then(get_obj_by_state)
+    }
 }
 
 impl Default for OdbcStdError {
@@ -42,10 +70,45 @@ impl From<String> for OdbcStdError {
 
 impl From<odbc_api::Error> for OdbcStdError {
     fn from(e: odbc_api::Error) -> Self {
-        OdbcStdError::OdbcError(e.into())
+        let is_statement = matches!(
+            &e,
+            odbc_api::Error::Diagnostics { function, .. } if is_statement_function(function)
+        );
+        match e.into() {
+            OdbcWrapperError::StatementError(e) if is_statement && is_canceled_state(&e.state) => {
+                OdbcStdError::Canceled(e)
+            }
+            e => OdbcStdError::OdbcError(e),
+        }
     }
 }
 
+/// The functions which run a statement, a timeout of any other one (e.g. the login timeout of
+/// `SQLDriverConnect`) isn't a canceled statement.
+fn is_statement_function(function: &str) -> bool {
+    matches!(
+        function,
+        "SQLExecDirect"
+            | "SQLExecute"
+            | "SQLPrepare"
+            | "SQLFetch"
+            | "SQLGetData"
+            | "SQLParamData"
+            | "SQLPutData"
+            | "SQLMoreResults"
+            | "SQLColumns"
+            | "SQLTables"
+            | "SQLForeignKeys"
+    )
+}
+
+fn is_canceled_state(state: &str) -> bool {
+    matches!(
+        get_obj_by_state(state),
+        Some(OdbcState::STMT_OPERATION_CANCELED | OdbcState::STMT_TIMEOUT)
+    )
+}
+
 impl From<TryFromIntError> for OdbcStdError {
     fn from(e: TryFromIntError) -> Self {
         OdbcStdError::TypeConversionError(e.to_string())
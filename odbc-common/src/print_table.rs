@@ -66,7 +66,6 @@ where
                 // Within a row iterate over every column
                 let row_data = (0..batch.num_cols())
                     .map(|col_index| batch.at(col_index, row_index).unwrap_or(&[]))
-                    .into_iter()
                     .map(|x| String::from_utf8_lossy(x).to_string())
                     .collect();
                 data.push(row_data);
@@ -22,4 +22,6 @@ sqlstate_mapping! {
     ( STMT_FETCH_OUT_OF_RANGE,  "HY106" ); /* Fetch type out of range */
     ( STMT_INVALID_NULL_ARG,  "HY009" );
     ( STMT_COMMUNICATION_ERROR, "08S01" );
+    ( STMT_OPERATION_CANCELED, "HY008" ); /* canceled by SQLCancel */
+    ( STMT_TIMEOUT, "HYT00" ); /* query timeout expired */
 }
@@ -4,13 +4,17 @@
 #[macro_use]
 extern crate serde;
 
+pub mod numeric;
 mod parser;
 pub mod state;
 pub mod table;
+pub mod timetz;
+use odbc_common::error::{OdbcStdError, OdbcStdResult};
 pub use postgres_types::Type as PgType;
 
-pub fn oid_typlen(pg_type: PgType) -> i16 {
-    match pg_type {
+/// The `typlen` of `pg_type`, -1 for a variable length type.
+pub fn oid_typlen(pg_type: PgType) -> OdbcStdResult<i16> {
+    let typlen = match pg_type {
         PgType::BOOL => 1,
         PgType::BYTEA => -1,
         PgType::CHAR => 1,
@@ -27,9 +31,16 @@ pub fn oid_typlen(pg_type: PgType) -> i16 {
         PgType::TIMESTAMP => 8,
         PgType::TIMESTAMPTZ => 8,
         PgType::TIMETZ => 12,
+        PgType::INTERVAL => 16,
         PgType::BIT => -1,
         PgType::JSONB => -1,
         PgType::NAME => 64,
-        _ => panic!("unknown pg_type:{pg_type}"),
-    }
+        PgType::NUMERIC => -1,
+        _ => {
+            return Err(OdbcStdError::TypeConversionError(format!(
+                "typlen from pg type:{pg_type}"
+            )))
+        }
+    };
+    Ok(typlen)
 }
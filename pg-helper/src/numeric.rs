@@ -0,0 +1,364 @@
+//! An exact decimal of arbitrary precision, e.g. of a NUMERIC/DECIMAL column.
+//!
+//! The value is kept as its decimal digits and scale, so `12.50` keeps its trailing zero and
+//! a number wider than `i64`/`f64` isn't rounded. It's encoded in the binary format of the pg
+//! `numeric` type by [`Numeric::to_binary`].
+use odbc_common::error::{OdbcStdError, OdbcStdResult};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+// the pg numeric is stored as base 10000 digits
+const NBASE: u32 = 10000;
+const DEC_DIGITS: usize = 4;
+const NUMERIC_POS: u16 = 0x0000;
+const NUMERIC_NEG: u16 = 0x4000;
+const NUMERIC_NAN: u16 = 0xC000;
+// the max display scale of pg numeric
+const NUMERIC_MAX_SCALE: u16 = 0x3FFF;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Numeric {
+    negative: bool,
+    // the unscaled absolute value, without leading zeros and "0" for zero
+    digits: String,
+    scale: u16,
+}
+
+impl Numeric {
+    /// The number of digits after the decimal point.
+    pub fn scale(&self) -> u16 {
+        self.scale
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits == "0"
+    }
+
+    /// Change the scale, the extra digits are rounded half away from zero like pg does.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use pg_helper::numeric::Numeric;
+    ///
+    /// let n: Numeric = "12.5".parse().unwrap();
+    /// assert_eq!(n.with_scale(3).to_string(), "12.500");
+    /// let n: Numeric = "-12.345".parse().unwrap();
+    /// assert_eq!(n.with_scale(2).to_string(), "-12.35");
+    /// ```
+    pub fn with_scale(self, scale: u16) -> Numeric {
+        if scale >= self.scale {
+            let zeros = "0".repeat(usize::from(scale - self.scale));
+            return Numeric::new(self.negative, self.digits + &zeros, scale);
+        }
+        let cut = usize::from(self.scale - scale);
+        let mut digits = self.digits;
+        if digits.len() <= cut {
+            digits = format!("{}{digits}", "0".repeat(cut + 1 - digits.len()));
+        }
+        let round_up = digits.as_bytes()[digits.len() - cut] >= b'5';
+        digits.truncate(digits.len() - cut);
+        if round_up {
+            digits = increment(&digits);
+        }
+        Numeric::new(self.negative, digits, scale)
+    }
+
+    /// Encode in the binary format of pg `numeric`: the number of base 10000 digits, the weight
+    /// of the first digit, the sign, the display scale, then the digits, all big endian.
+    /// It fails when the number of digits or the weight doesn't fit an `i16`.
+    pub fn to_binary(&self, buf: &mut Vec<u8>) -> OdbcStdResult<()> {
+        let (weight, groups) = self.to_groups();
+        let out_of_range = || {
+            OdbcStdError::TypeConversionError(format!(
+                "numeric of {} digits is out of the range of pg numeric",
+                self.digits.len()
+            ))
+        };
+        let ndigits = i16::try_from(groups.len()).map_err(|_| out_of_range())?;
+        let weight = i16::try_from(weight).map_err(|_| out_of_range())?;
+        let sign = if self.negative {
+            NUMERIC_NEG
+        } else {
+            NUMERIC_POS
+        };
+        buf.extend_from_slice(&ndigits.to_be_bytes());
+        buf.extend_from_slice(&weight.to_be_bytes());
+        buf.extend_from_slice(&sign.to_be_bytes());
+        buf.extend_from_slice(&self.scale.to_be_bytes());
+        for group in groups {
+            buf.extend_from_slice(&group.to_be_bytes());
+        }
+        Ok(())
+    }
+
+    /// Decode the binary format of pg `numeric`, see [`Numeric::to_binary`].
+    pub fn from_binary(buf: &[u8]) -> OdbcStdResult<Numeric> {
+        let invalid = || OdbcStdError::TypeConversionError("invalid binary numeric".to_string());
+        let read = |i: usize| -> OdbcStdResult<u16> {
+            buf.get(i * 2..i * 2 + 2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]))
+                .ok_or_else(invalid)
+        };
+        let ndigits = read(0)? as i16;
+        let weight = read(1)? as i16;
+        let sign = read(2)?;
+        let scale = read(3)?;
+        if sign == NUMERIC_NAN {
+            return Err(OdbcStdError::TypeConversionError(
+                "numeric NaN is not supported".to_string(),
+            ));
+        }
+        if ndigits < 0 || (sign != NUMERIC_POS && sign != NUMERIC_NEG) {
+            return Err(invalid());
+        }
+        let mut groups = Vec::with_capacity(ndigits as usize);
+        for i in 0..ndigits as usize {
+            let group = read(4 + i)?;
+            if u32::from(group) >= NBASE {
+                return Err(invalid());
+            }
+            groups.push(group);
+        }
+        Ok(Numeric::from_groups(
+            sign == NUMERIC_NEG,
+            weight,
+            &groups,
+            scale,
+        ))
+    }
+
+    fn new(negative: bool, digits: String, scale: u16) -> Numeric {
+        let digits = match digits.trim_start_matches('0') {
+            "" => "0".to_string(),
+            d => d.to_string(),
+        };
+        let negative = negative && digits != "0";
+        Numeric {
+            negative,
+            digits,
+            scale,
+        }
+    }
+
+    /// The integer and fraction digits, the fraction has `scale` digits.
+    fn split(&self) -> (&str, String) {
+        let scale = usize::from(self.scale);
+        if self.digits.len() > scale {
+            let (int, frac) = self.digits.split_at(self.digits.len() - scale);
+            (int, frac.to_string())
+        } else {
+            let frac = format!("{}{}", "0".repeat(scale - self.digits.len()), self.digits);
+            ("0", frac)
+        }
+    }
+
+    /// The weight of the first base 10000 digit and the digits without leading and trailing
+    /// zeros.
+    fn to_groups(&self) -> (i64, Vec<u16>) {
+        let (int, frac) = self.split();
+        let int = int.trim_start_matches('0');
+        let int_pad = (DEC_DIGITS - int.len() % DEC_DIGITS) % DEC_DIGITS;
+        let frac_pad = (DEC_DIGITS - frac.len() % DEC_DIGITS) % DEC_DIGITS;
+        let padded = format!("{}{int}{frac}{}", "0".repeat(int_pad), "0".repeat(frac_pad));
+        let mut groups: Vec<u16> = padded
+            .as_bytes()
+            .chunks(DEC_DIGITS)
+            .map(|c| c.iter().fold(0, |acc, d| acc * 10 + u16::from(d - b'0')))
+            .collect();
+        let mut weight = ((int.len() + int_pad) / DEC_DIGITS) as i64 - 1;
+
+        let leading = groups.iter().take_while(|g| **g == 0).count();
+        groups.drain(..leading);
+        weight -= leading as i64;
+        while groups.last() == Some(&0) {
+            groups.pop();
+        }
+        if groups.is_empty() {
+            weight = 0;
+        }
+        (weight, groups)
+    }
+
+    fn from_groups(negative: bool, weight: i16, groups: &[u16], scale: u16) -> Numeric {
+        let weight = i32::from(weight);
+        // the digit of `weight` 0 is the last one before the decimal point
+        let mut int = String::new();
+        for w in (0..=weight).rev() {
+            int += &format!("{:04}", group_at(groups, weight, w));
+        }
+        let mut frac = String::new();
+        let frac_groups = usize::from(scale).div_ceil(DEC_DIGITS) as i32;
+        for w in 1..=frac_groups {
+            frac += &format!("{:04}", group_at(groups, weight, -w));
+        }
+        frac.truncate(usize::from(scale));
+        Numeric::new(negative, int + &frac, scale)
+    }
+}
+
+fn group_at(groups: &[u16], weight: i32, w: i32) -> u16 {
+    usize::try_from(weight - w)
+        .ok()
+        .and_then(|i| groups.get(i))
+        .copied()
+        .unwrap_or(0)
+}
+
+/// Add one to the decimal digits.
+fn increment(digits: &str) -> String {
+    let mut bytes = digits.as_bytes().to_vec();
+    for b in bytes.iter_mut().rev() {
+        if *b == b'9' {
+            *b = b'0';
+        } else {
+            *b += 1;
+            return String::from_utf8(bytes).expect("ascii digits");
+        }
+    }
+    format!("1{}", String::from_utf8(bytes).expect("ascii digits"))
+}
+
+impl FromStr for Numeric {
+    type Err = OdbcStdError;
+
+    /// Parse a plain decimal, e.g. `-12.50` or `.5`, the scale is the number of fraction digits.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || OdbcStdError::TypeConversionError(format!("invalid numeric:`{s}`"));
+        let v = s.trim();
+        let (negative, v) = match v.as_bytes().first() {
+            Some(b'-') => (true, &v[1..]),
+            Some(b'+') => (false, &v[1..]),
+            _ => (false, v),
+        };
+        let (int, frac) = v.split_once('.').unwrap_or((v, ""));
+        let is_digits = |p: &str| p.bytes().all(|b| b.is_ascii_digit());
+        if (int.is_empty() && frac.is_empty()) || !is_digits(int) || !is_digits(frac) {
+            return Err(invalid());
+        }
+        let scale = u16::try_from(frac.len())
+            .ok()
+            .filter(|s| *s <= NUMERIC_MAX_SCALE)
+            .ok_or_else(invalid)?;
+        Ok(Numeric::new(negative, format!("{int}{frac}"), scale))
+    }
+}
+
+impl Display for Numeric {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let sign = if self.negative { "-" } else { "" };
+        let (int, frac) = self.split();
+        if frac.is_empty() {
+            write!(f, "{sign}{int}")
+        } else {
+            write!(f, "{sign}{int}.{frac}")
+        }
+    }
+}
+
+macro_rules! numeric_from_integer {
+    ($($t:ty),+) => {
+        $(
+            impl From<$t> for Numeric {
+                fn from(v: $t) -> Self {
+                    Numeric::new(v < 0, v.unsigned_abs().to_string(), 0)
+                }
+            }
+        )+
+    };
+}
+
+numeric_from_integer!(i8, i16, i32, i64);
+
+impl From<u8> for Numeric {
+    fn from(v: u8) -> Self {
+        Numeric::new(false, v.to_string(), 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numeric(s: &str) -> Numeric {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_display() {
+        let cases = [
+            ("12.50", "12.50", 2),
+            ("-0.001", "-0.001", 3),
+            ("+007", "7", 0),
+            (".5", "0.5", 1),
+            ("-0.00", "0.00", 2),
+            (
+                "123456789012345678901234567890.123456789",
+                "123456789012345678901234567890.123456789",
+                9,
+            ),
+        ];
+        for (input, display, scale) in cases {
+            let n = numeric(input);
+            assert_eq!(n.to_string(), display, "{input}");
+            assert_eq!(n.scale(), scale, "{input}");
+        }
+        for invalid in ["", "-", ".", "1.2.3", "1e5", "abc", "1 2"] {
+            assert!(invalid.parse::<Numeric>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_with_scale() {
+        assert_eq!(numeric("0.995").with_scale(2).to_string(), "1.00");
+        assert_eq!(numeric("9.5").with_scale(0).to_string(), "10");
+        assert_eq!(numeric("0.004").with_scale(2).to_string(), "0.00");
+        assert_eq!(numeric("-0.004").with_scale(2).to_string(), "0.00");
+        assert_eq!(numeric("-1").with_scale(2).to_string(), "-1.00");
+    }
+
+    #[test]
+    fn test_binary() {
+        // the bytes are written by `SELECT '<value>'::numeric` of pg in binary format
+        let cases: [(&str, &[u8]); 5] = [
+            ("0", &[0, 0, 0, 0, 0, 0, 0, 0]),
+            ("12.50", &[0, 2, 0, 0, 0, 0, 0, 2, 0, 12, 19, 136]),
+            ("-0.001", &[0, 1, 255, 255, 64, 0, 0, 3, 0, 10]),
+            ("10000", &[0, 1, 0, 1, 0, 0, 0, 0, 0, 1]),
+            (
+                "123456789.0123",
+                &[0, 4, 0, 2, 0, 0, 0, 4, 0, 1, 9, 41, 26, 133, 0, 123],
+            ),
+        ];
+        for (value, bytes) in cases {
+            let mut buf = vec![];
+            numeric(value).to_binary(&mut buf).unwrap();
+            assert_eq!(buf, bytes, "{value}");
+            assert_eq!(Numeric::from_binary(bytes).unwrap(), numeric(value));
+        }
+        // the weight of the first digit is 32768
+        let wide = numeric(&format!("1{}", "0".repeat(4 * 32768)));
+        assert!(wide.to_binary(&mut vec![]).is_err());
+        let wide = numeric(&format!("1{}", "0".repeat(4 * 32767)));
+        assert!(wide.to_binary(&mut vec![]).is_ok());
+        // 34000 digits of base 10000
+        let long = numeric(&format!(
+            "{}.{}",
+            "1".repeat(4 * 30000),
+            "1".repeat(4 * 4000)
+        ));
+        assert!(long.to_binary(&mut vec![]).is_err());
+        assert!(Numeric::from_binary(&[0, 1, 0]).is_err());
+        assert!(Numeric::from_binary(&[0, 0, 0, 0, 0xC0, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_from_integer() {
+        assert_eq!(Numeric::from(i64::MIN).to_string(), "-9223372036854775808");
+        assert_eq!(Numeric::from(0i32), numeric("0"));
+    }
+}
@@ -22,6 +22,7 @@ odbc_common::sqlstate_mapping! {
     ( STMT_FETCH_OUT_OF_RANGE,  "42804" ); /* datatype_mismatch */
     ( STMT_INVALID_NULL_ARG,  "HV009" );
     ( STMT_COMMUNICATION_ERROR, "08000" );
+    ( STMT_OPERATION_CANCELED, "57014" ); /* query_canceled, also by statement_timeout */
 }
 
 #[cfg(test)]
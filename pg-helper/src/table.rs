@@ -34,6 +34,8 @@ pub struct PgTableItem {
     pub length: usize,
     // column date type scale
     pub scale: usize,
+    // the fractional second digits of a TIME/TIMETZ/TIMESTAMP/TIMESTAMPTZ column
+    pub precision: Option<usize>,
     pub nullable: bool,
     pub is_identity: bool,
     pub default_val: Option<String>,
@@ -0,0 +1,151 @@
+//! A time of day with its UTC offset, e.g. of a TIME WITH TIME ZONE column, and the UTC offset
+//! of a date/time text.
+//!
+//! chrono has no time of day with an offset, TIMESTAMP WITH TIME ZONE is a
+//! `chrono::DateTime<FixedOffset>`.
+use chrono::{FixedOffset, NaiveTime};
+use odbc_common::error::{OdbcStdError, OdbcStdResult};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimeTz {
+    time: NaiveTime,
+    offset: FixedOffset,
+}
+
+impl TimeTz {
+    pub fn new(time: NaiveTime, offset: FixedOffset) -> Self {
+        Self { time, offset }
+    }
+
+    /// The local time in the offset.
+    pub fn time(&self) -> NaiveTime {
+        self.time
+    }
+
+    pub fn offset(&self) -> FixedOffset {
+        self.offset
+    }
+}
+
+/// Split the UTC offset off a time or timestamp text, the offset follows the time, e.g.
+/// `2022-10-24 17:28:26.308 +08:00` gives `("2022-10-24 17:28:26.308", Some("+08:00"))`.
+///
+/// # Example
+///
+/// ```rust
+/// use pg_helper::timetz::split_offset;
+///
+/// assert_eq!(split_offset("17:28:26-05"), ("17:28:26", Some("-05")));
+/// assert_eq!(split_offset("2022-10-24 17:28:26Z"), ("2022-10-24 17:28:26", Some("Z")));
+/// assert_eq!(split_offset("2022-10-24 17:28:26"), ("2022-10-24 17:28:26", None));
+/// ```
+pub fn split_offset(v: &str) -> (&str, Option<&str>) {
+    let v = v.trim();
+    // the date has no `:`, so the sign of the offset is the first one after the time
+    let time_start = v.find(':').unwrap_or(v.len());
+    let at = v[time_start..]
+        .find(['+', '-', 'Z', 'z'])
+        .map(|i| time_start + i);
+    match at {
+        Some(at) => (v[..at].trim_end(), Some(&v[at..])),
+        None => (v, None),
+    }
+}
+
+/// Parse a UTC offset, e.g. `+08:00`, `+0800`, `-5` or `Z`.
+pub fn parse_offset(v: &str) -> OdbcStdResult<FixedOffset> {
+    let invalid = || OdbcStdError::TypeConversionError(format!("invalid utc offset:`{v}`"));
+    let offset = v.trim();
+    if offset.eq_ignore_ascii_case("z") {
+        return FixedOffset::east_opt(0).ok_or_else(invalid);
+    }
+    let (sign, offset) = match offset.as_bytes().first() {
+        Some(b'+') => (1, &offset[1..]),
+        Some(b'-') => (-1, &offset[1..]),
+        _ => return Err(invalid()),
+    };
+    let (hours, minutes) = match offset.split_once(':') {
+        Some((h, m)) => (h, m),
+        None if offset.len() == 4 => offset.split_at(2),
+        None => (offset, "0"),
+    };
+    let is_number =
+        |p: &str| !p.is_empty() && p.len() <= 2 && p.bytes().all(|b| b.is_ascii_digit());
+    if !is_number(hours) || !is_number(minutes) {
+        return Err(invalid());
+    }
+    let hours: i32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: i32 = minutes.parse().map_err(|_| invalid())?;
+    if hours > 15 || minutes > 59 {
+        return Err(invalid());
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).ok_or_else(invalid)
+}
+
+impl FromStr for TimeTz {
+    type Err = OdbcStdError;
+
+    /// Parse a time with a UTC offset, e.g. `17:28:26.308+08:00` or `17:28:26 +8:00`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match split_offset(s) {
+            (time, Some(offset)) => Ok(TimeTz::new(
+                NaiveTime::parse_from_str(time, "%H:%M:%S%.f")?,
+                parse_offset(offset)?,
+            )),
+            (_, None) => Err(OdbcStdError::TypeConversionError(format!(
+                "time with time zone without utc offset:`{s}`"
+            ))),
+        }
+    }
+}
+
+/// The pg text format, e.g. `17:28:26.308+08:00`.
+impl Display for TimeTz {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.time, self.offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offset(hours: i32, minutes: i32) -> FixedOffset {
+        FixedOffset::east_opt(hours * 3600 + minutes * 60).unwrap()
+    }
+
+    #[test]
+    fn test_parse_offset() {
+        let cases = [
+            ("+08:00", offset(8, 0)),
+            ("+8:00", offset(8, 0)),
+            ("-0530", offset(-5, -30)),
+            ("-05", offset(-5, 0)),
+            ("Z", offset(0, 0)),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_offset(input).unwrap(), expected, "{input}");
+        }
+        for invalid in ["", "08:00", "+", "+16:00", "+08:60", "+abc", "+080"] {
+            assert!(parse_offset(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_time_tz() {
+        let t: TimeTz = "17:28:26.308 +08:00".parse().unwrap();
+        assert_eq!(
+            t.time(),
+            NaiveTime::from_hms_milli_opt(17, 28, 26, 308).unwrap()
+        );
+        assert_eq!(t.offset(), offset(8, 0));
+        assert_eq!(t.to_string(), "17:28:26.308+08:00");
+        assert_eq!(t.to_string().parse::<TimeTz>().unwrap(), t);
+
+        let t: TimeTz = "01:02:03-03:30".parse().unwrap();
+        assert_eq!(t.to_string(), "01:02:03-03:30");
+        assert!("17:28:26".parse::<TimeTz>().is_err());
+    }
+}